@@ -0,0 +1,70 @@
+//! 应答JSON解析基准，对比serde_json与`util::from_json_str`(开启simd-json时为simd-json)
+//! 应答结构通过`util::parse_response`解析，与请求接口读取应答时的路径一致
+//! cargo bench --bench json
+//! cargo bench --bench json --features simd-json
+
+use serde_json::Value;
+use std::hint::black_box;
+use std::time::Instant;
+use wechat_pay_rust_sdk::response::{OrderQueryResponse, WeChatResponse};
+use wechat_pay_rust_sdk::util::{from_json_str, parse_response};
+
+const ITERATIONS: u32 = 200_000;
+
+/// 典型的订单查询应答，带代金券明细
+const ORDER_QUERY: &str = r#"{"amount":{"currency":"CNY","payer_currency":"CNY","payer_total":90,"total":100},"appid":"wxd678efh567hg6787","attach":"自定义数据","bank_type":"CMC","mchid":"1230000109","out_trade_no":"1217752501201407033233368018","payer":{"openid":"oUpF8uMuAJO_M2pxb1Q9zNjWeS6o"},"promotion_detail":[{"amount":10,"coupon_id":"109519","currency":"CNY","goods_detail":[{"discount_amount":1,"goods_id":"M1006","goods_remark":"商品备注信息","quantity":1,"unit_price":100}],"merchant_contribute":0,"name":"单品惠-6","other_contribute":0,"scope":"SINGLE","stock_id":"931386","type":"CASH","wechatpay_contribute":10}],"scene_info":{"device_id":"013467007045764"},"success_time":"2018-06-08T10:34:56+08:00","trade_state":"SUCCESS","trade_state_desc":"支付成功","trade_type":"JSAPI","transaction_id":"1217752501201407033233368018"}"#;
+
+fn bench(name: &str, f: impl Fn() -> usize) {
+    for _ in 0..ITERATIONS / 10 {
+        black_box(f());
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<48} {:>8.0} ns/iter",
+        name,
+        elapsed.as_nanos() as f64 / ITERATIONS as f64
+    );
+}
+
+fn main() {
+    let backend = if cfg!(feature = "simd-json") {
+        "simd-json"
+    } else {
+        "serde_json"
+    };
+    bench("serde_json Value", || {
+        let value: Value = serde_json::from_str(black_box(ORDER_QUERY)).unwrap();
+        value.as_object().map(|map| map.len()).unwrap_or_default()
+    });
+    bench(&format!("from_json_str Value ({})", backend), || {
+        let value: Value = from_json_str(black_box(ORDER_QUERY)).unwrap();
+        value.as_object().map(|map| map.len()).unwrap_or_default()
+    });
+    bench("serde_json OrderQueryResponse", || {
+        let response: OrderQueryResponse = serde_json::from_str(black_box(ORDER_QUERY)).unwrap();
+        response.trade_state.len()
+    });
+    bench(
+        &format!("parse_response OrderQueryResponse ({})", backend),
+        || {
+            let response: OrderQueryResponse = parse_response(black_box(ORDER_QUERY)).unwrap();
+            response.trade_state.len()
+        },
+    );
+    // 查询订单等接口实际返回的类型
+    bench(
+        &format!("parse_response WeChatResponse ({})", backend),
+        || {
+            let response: WeChatResponse<OrderQueryResponse> =
+                parse_response(black_box(ORDER_QUERY)).unwrap();
+            response
+                .ok()
+                .map(|order| order.trade_state.len())
+                .unwrap_or_default()
+        },
+    );
+}
@@ -3,6 +3,7 @@ use actix_web::{get, post, App, HttpRequest, HttpResponse, HttpServer, Responder
 use dotenvy::dotenv;
 use tracing::debug;
 use wechat_pay_rust_sdk::model::{H5Params, H5SceneInfo, WechatPayDecodeData, WechatPayNotify};
+use wechat_pay_rust_sdk::notify::NotificationRequest;
 use wechat_pay_rust_sdk::pay::{PayNotifyTrait, WechatPay};
 use wechat_pay_rust_sdk::util;
 
@@ -62,6 +63,26 @@ async fn pay_notify3(bytes: Bytes, req: HttpRequest) -> impl Responder {
     }))
 }
 
+#[post("/pay/notify4")]
+async fn pay_notify4(notification: NotificationRequest) -> impl Responder {
+    dotenv().ok();
+    let wechat_pay = WechatPay::from_env();
+    let pub_key = std::fs::read_to_string("pubkey.pem").unwrap();
+    match notification.decrypt(&wechat_pay, pub_key.as_str()) {
+        Ok(result) => {
+            debug!("result: {:#?}", result);
+            HttpResponse::Ok().json(serde_json::json!({
+                "code": "SUCCESS",
+                "message": "成功"
+            }))
+        }
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({
+            "code": "FAIL",
+            "message": e.to_string()
+        })),
+    }
+}
+
 #[get("/")]
 async fn home() -> impl Responder {
     HttpResponse::Ok().body("hello rust")
@@ -106,6 +127,7 @@ async fn main() -> std::io::Result<()> {
         App::new()
             .service(pay_notify)
             .service(pay_notify2)
+            .service(pay_notify4)
             .service(home)
     })
     .bind(("0.0.0.0", 8080))?
@@ -1,36 +1,123 @@
+use crate::client::{Client, Request, RequestBuilder, Response};
 use crate::debug;
+use crate::endpoints;
+use crate::error;
 use crate::error::PayError;
+use crate::info;
 use crate::model::AppParams;
+use crate::model::CouponStockParams;
+use crate::model::FapiaoApplicationParams;
 use crate::model::H5Params;
 use crate::model::JsapiParams;
 use crate::model::MicroParams;
 use crate::model::NativeParams;
+use crate::model::OrderQuery;
 use crate::model::ParamsTrait;
+use crate::model::ProfitSharingOrderParams;
+use crate::model::ProfitSharingReturnParams;
+use crate::model::ProfitSharingUnfreezeParams;
+use crate::model::RefundNotify;
 use crate::model::RefundsParams;
-use crate::pay::{WechatPay, WechatPayTrait};
-use crate::request::HttpMethod;
+use crate::model::TarType;
+use crate::model::TradeNo;
+use crate::model::TransferBillsParams;
+use crate::model::TransferReceiptParams;
+use crate::model::{CloseOrderParams, CombineCloseParams, CombineCloseSubOrder};
+use crate::model::{PayScoreCompleteParams, PayScoreModifyParams, PayScoreParams};
+use crate::model::{PayScorePermissionParams, PayScoreTerminateParams};
+use crate::notify::{NotificationEvent, NotificationRequest};
+use crate::pagination::Pages;
+use crate::pay::{PayNotifyTrait, WechatPay, WechatPayTrait};
+use crate::pay_type::PayType;
+use crate::request::{DryRunRequest, HttpMethod, InjectFields};
 use crate::response::AppResponse;
+use crate::response::BillResponse;
+use crate::response::CloseOrderOutcome;
+use crate::response::CombineOrderQueryResponse;
+use crate::response::ComplaintListResponse;
+use crate::response::CouponStockResponse;
+use crate::response::ErrorResponse;
+use crate::response::FapiaoApplicationResponse;
+use crate::response::H5LaunchInfo;
 use crate::response::H5Response;
 use crate::response::JsapiResponse;
+use crate::response::MarketingImageUploadResponse;
 use crate::response::MicroResponse;
+use crate::response::OrderQueryResponse;
+use crate::response::PayScoreOrderResponse;
+use crate::response::ProfitSharingAmountResponse;
+use crate::response::ProfitSharingOrderResponse;
+use crate::response::ProfitSharingReturnResponse;
+use crate::response::REQUEST_ID;
+use crate::response::Reconciliation;
 use crate::response::RefundsResponse;
+use crate::response::ResponseStatus;
 use crate::response::ResponseTrait;
+use crate::response::TransferBillsResponse;
+use crate::response::TransferReceiptResponse;
+use crate::response::UserCoupon;
+use crate::response::UserCouponListResponse;
 use crate::response::WeChatResponse;
 use crate::response::{CertificateResponse, NativeResponse};
-use reqwest::header::{HeaderMap, REFERER};
-use serde_json::{Map, Value};
-
-#[cfg(not(feature = "async"))]
-use reqwest::blocking::Client;
-#[cfg(feature = "async")]
-use reqwest::Client;
+use crate::response::{PayScorePermissionApplyResponse, PayScorePermissionResponse};
+use crate::util;
+use reqwest::StatusCode;
+use reqwest::Url;
+use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderValue, REFERER};
+use rsa::sha2::{Digest, Sha256};
+use std::collections::HashSet;
 
 #[cfg(feature = "async")]
 use maybe_async::maybe_async as maybe_async_attr;
 #[cfg(not(feature = "async"))]
 use maybe_async::must_be_sync as maybe_async_attr;
 
+/// 批量查询退款时的最大并发数
+#[cfg(feature = "async")]
+const QUERY_REFUNDS_CONCURRENCY: usize = 8;
+
+/// 微信支付API域名，应答中的下载地址指向这些域名时可以签名下载
+const WECHAT_PAY_API_ORIGINS: [&str; 2] = [
+    "https://api.mch.weixin.qq.com",
+    "https://api2.mch.weixin.qq.com",
+];
+
+/// 已读出应答体的签名请求应答，判断是否需要校正时间戳重签时要先读取401的应答体
+struct SignedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl SignedResponse {
+    fn request_id(&self) -> Option<String> {
+        self.headers
+            .get(REQUEST_ID)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    }
+
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// 非2xx应答解析为微信支付的错误，应答体不是错误JSON时返回`PayError::ResponseParse`
+    fn into_error(self) -> PayError {
+        let status = self.status.as_u16();
+        let text = self.text();
+        match util::from_json_str::<ErrorResponse>(&text) {
+            Ok(mut error) => {
+                error.request_id = self.request_id();
+                error.status = Some(status);
+                error.into()
+            }
+            Err(e) => PayError::response_parse(status, &text, e),
+        }
+    }
+}
+
 impl WechatPay {
+    /// 下单等接口，请求体注入appid、mchid和notify_url
     #[maybe_async_attr]
     pub async fn pay<P: ParamsTrait, R: ResponseTrait>(
         &self,
@@ -38,199 +125,1401 @@ impl WechatPay {
         url: &str,
         json: P,
     ) -> Result<R, PayError> {
+        self.request(method, url, json, InjectFields::ALL).await
+    }
+
+    /// 签名并发送参数，按inject向请求体注入商户字段
+    /// 新接口按微信文档声明需要注入的字段，请求体不能带appid等字段的接口使用`InjectFields::NONE`
+    #[maybe_async_attr]
+    pub async fn request<P: ParamsTrait, R: ResponseTrait>(
+        &self,
+        method: HttpMethod,
+        url: &str,
+        json: P,
+        inject: InjectFields,
+    ) -> Result<R, PayError> {
+        json.validate()?;
+        // 参数中自带的notify_url(如支付分、退款)按类型校验，WechatPay上配置的在注入时校验
+        if let Some(notify_url) = json.notify_url() {
+            util::validate_notify_url(notify_url)?;
+        }
         let json_str = json.to_json();
         debug!("json_str: {}", json_str);
-        let mut map: Map<String, Value> = serde_json::from_str(&json_str)?;
-        map.insert("appid".to_owned(), self.appid().into());
-        map.insert("mchid".to_owned(), self.mch_id().into());
-        map.insert("notify_url".to_owned(), self.notify_url().into());
-        let body = serde_json::to_string(&map)?;
-        let headers = self.build_header(method.clone(), url, body.as_str())?;
-        let client = Client::new();
-        let url = format!("{}{}", self.base_url(), url);
+        let body = self.inject_body(&json_str, inject)?;
+        let (url, signed_url) = self.request_url(url)?;
         debug!("url: {} body: {}", url, body);
-        let builder = match method {
+        self.send_signed(method, url, signed_url, body).await
+    }
+
+    #[maybe_async_attr]
+    pub async fn get_pay<R: ResponseTrait>(&self, url: &str) -> Result<R, PayError> {
+        let (url, signed_url) = self.request_url(url)?;
+        debug!("url: {}", url);
+        self.send_signed(HttpMethod::GET, url, signed_url, String::new())
+            .await
+    }
+
+    /// 为单次调用设置截止时间，超时后放弃等待并返回`PayError::Timeout`，比客户端统一的超时更灵活
+    /// 超时会丢弃进行中的请求，reqwest的请求可以安全取消，但请求可能已被微信受理，
+    /// 下单、退款等写操作超时后应先查询结果，不能直接换单号重试
+    #[cfg(feature = "async")]
+    pub async fn deadline<T>(
+        &self,
+        timeout: std::time::Duration,
+        future: impl std::future::Future<Output = Result<T, PayError>>,
+    ) -> Result<T, PayError> {
+        tokio::time::timeout(timeout, future)
+            .await
+            .map_err(|_| PayError::Timeout(timeout))?
+    }
+
+    /// 签名下载文件(账单、电子回单等)，download_url为应答中返回的完整地址或以/v3开头的路径
+    /// 返回去掉HTTP传输压缩后的文件内容，大小受max_response_size限制，下载大文件前需调大
+    /// 下载请求带有商户签名，完整地址只能指向base_url、备用域名或微信支付API域名，其他域名返回`PayError::InvalidParams`
+    #[maybe_async_attr]
+    pub async fn download(&self, download_url: &str) -> Result<Vec<u8>, PayError> {
+        let (url, signed_url) = if download_url.starts_with('/') {
+            self.request_url(download_url)?
+        } else {
+            let url = Url::parse(download_url).map_err(|e| {
+                PayError::InvalidParams(format!("invalid url {}: {}", download_url, e))
+            })?;
+            if !self.is_trusted_origin(&url) {
+                return Err(PayError::InvalidParams(format!(
+                    "download_url host not allowed: {}",
+                    download_url
+                )));
+            }
+            let signed_url = match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            };
+            (url, signed_url)
+        };
+        debug!("download url: {}", url);
+        let response = self
+            .execute_signed(HttpMethod::GET, url, &signed_url, "", Ok)
+            .await?;
+        if !response.status.is_success() {
+            return Err(response.into_error());
+        }
+        Ok(response.body)
+    }
+
+    /// url是否指向base_url、备用域名或微信支付API域名，只有这些地址可以带商户签名访问
+    fn is_trusted_origin(&self, url: &Url) -> bool {
+        let origin = url.origin();
+        std::iter::once(self.base_url.as_str())
+            .chain(self.fallback_hosts.iter().map(String::as_str))
+            .chain(WECHAT_PAY_API_ORIGINS)
+            .filter_map(|host| Url::parse(host).ok())
+            .any(|trusted| trusted.origin() == origin)
+    }
+
+    /// 下载转账电子回单并按应答中的hash_type/hash_value校验文件，回单状态需为FINISHED
+    #[maybe_async_attr]
+    pub async fn download_transfer_receipt(
+        &self,
+        receipt: &TransferReceiptResponse,
+    ) -> Result<Vec<u8>, PayError> {
+        let Some(download_url) = receipt.download_url.as_deref() else {
+            return Err(PayError::InvalidParams(format!(
+                "transfer receipt is {}, download_url not ready",
+                receipt.state
+            )));
+        };
+        let content = self.download(download_url).await?;
+        if let (Some(hash_type), Some(hash_value)) = (&receipt.hash_type, &receipt.hash_value) {
+            util::verify_hash(&content, hash_type, hash_value)?;
+        }
+        Ok(content)
+    }
+
+    /// 申请交易账单，bill_date格式为yyyy-MM-dd，只能申请三个月内的账单，当日账单次日9点后生成
+    #[maybe_async_attr]
+    pub async fn apply_trade_bill(
+        &self,
+        bill_date: impl AsRef<str>,
+    ) -> Result<WeChatResponse<BillResponse>, PayError> {
+        let url = util::with_query(
+            endpoints::BILL_TRADEBILL,
+            &[("bill_date", bill_date.as_ref()), ("bill_type", "ALL")],
+        );
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 申请并下载当日全部交易账单(ALL)，按应答中的SHA1校验文件后返回账单文本，用`util::parse_trade_bill`解析
+    #[maybe_async_attr]
+    pub async fn download_trade_bill(
+        &self,
+        bill_date: impl AsRef<str>,
+    ) -> Result<String, PayError> {
+        let bill = match self.apply_trade_bill(bill_date).await? {
+            WeChatResponse::Ok(bill) => bill,
+            WeChatResponse::Err(error) => return Err(error.into()),
+        };
+        let content = self.download_bill(&bill).await?;
+        String::from_utf8(content)
+            .map_err(|e| PayError::InvalidParams(format!("trade bill is not utf-8: {}", e)))
+    }
+
+    /// 申请分账账单，bill_date格式为yyyy-MM-dd，tar_type为None时返回账单原文，服务商模式下查询子商户的账单
+    #[maybe_async_attr]
+    pub async fn apply_profit_sharing_bill(
+        &self,
+        bill_date: impl AsRef<str>,
+        tar_type: Option<TarType>,
+    ) -> Result<WeChatResponse<BillResponse>, PayError> {
+        let tar_type = tar_type.map(|tar_type| tar_type.to_string());
+        let mut query = vec![("bill_date", bill_date.as_ref())];
+        if let Some(sub_mch_id) = &self.sub_mch_id {
+            query.push(("sub_mchid", sub_mch_id.as_str()));
+        }
+        if let Some(tar_type) = &tar_type {
+            query.push(("tar_type", tar_type.as_str()));
+        }
+        let url = util::with_query(endpoints::PROFITSHARING_BILLS, &query);
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 申请并下载分账账单，按应答中的hash校验文件，返回下载地址等信息和文件内容
+    /// tar_type为`TarType::Gzip`时文件为压缩包，hash针对压缩包本身计算
+    #[maybe_async_attr]
+    pub async fn download_profit_sharing_bill(
+        &self,
+        bill_date: impl AsRef<str>,
+        tar_type: Option<TarType>,
+    ) -> Result<(BillResponse, Vec<u8>), PayError> {
+        let bill = match self.apply_profit_sharing_bill(bill_date, tar_type).await? {
+            WeChatResponse::Ok(bill) => bill,
+            WeChatResponse::Err(error) => return Err(error.into()),
+        };
+        let content = self.download_bill(&bill).await?;
+        Ok((bill, content))
+    }
+
+    /// 下载账单并校验hash
+    #[maybe_async_attr]
+    async fn download_bill(&self, bill: &BillResponse) -> Result<Vec<u8>, PayError> {
+        let content = self.download(&bill.download_url).await?;
+        util::verify_hash(&content, &bill.hash_type, &bill.hash_value)?;
+        Ok(content)
+    }
+
+    /// 下载并解析当日交易账单，与我方订单号逐一核对，返回匹配、缺失和多余的订单
+    /// 只核对订单号是否出现在账单中，金额等字段可从matched中的明细自行比较
+    #[maybe_async_attr]
+    pub async fn reconcile<I, S>(
+        &self,
+        bill_date: impl AsRef<str>,
+        our_trade_nos: I,
+    ) -> Result<Reconciliation, PayError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let content = self.download_trade_bill(bill_date).await?;
+        let records = util::parse_trade_bill(&content)?;
+        let mut ours: Vec<String> = Vec::new();
+        let mut seen = HashSet::new();
+        for trade_no in our_trade_nos {
+            let trade_no = trade_no.as_ref();
+            if seen.insert(trade_no.to_string()) {
+                ours.push(trade_no.to_string());
+            }
+        }
+        let billed: HashSet<&str> = records
+            .iter()
+            .map(|record| record.out_trade_no.as_str())
+            .collect();
+        let unmatched = ours
+            .iter()
+            .filter(|trade_no| !billed.contains(trade_no.as_str()))
+            .cloned()
+            .collect();
+        let (matched, extra) = records
+            .into_iter()
+            .partition(|record| seen.contains(&record.out_trade_no));
+        Ok(Reconciliation {
+            matched,
+            unmatched,
+            extra,
+        })
+    }
+
+    /// 签名并POST参数，参数原样发送，不注入appid、mchid等下单字段
+    #[maybe_async_attr]
+    pub async fn post_pay<P: ParamsTrait, R: ResponseTrait>(
+        &self,
+        url: &str,
+        json: P,
+    ) -> Result<R, PayError> {
+        self.request(HttpMethod::POST, url, json, InjectFields::NONE)
+            .await
+    }
+
+    /// 签名并发送JSON请求体，解析应答
+    #[maybe_async_attr]
+    async fn send_signed<R: ResponseTrait>(
+        &self,
+        method: HttpMethod,
+        url: Url,
+        signed_url: String,
+        body: String,
+    ) -> Result<R, PayError> {
+        let response = self
+            .execute_signed(method, url, &signed_url, &body, |builder| {
+                self.request_body(builder, body.clone())
+            })
+            .await?;
+        self.read_response(response).await
+    }
+
+    /// 签名并发送，sign_body为参与签名的内容，build设置实际发送的请求体
+    /// 返回401、应答体说明签名时间戳超出范围且Date头显示本机时钟偏差超过MAX_CLOCK_SKEW_SECS时，
+    /// 记录时钟偏差并用校正后的时间戳重新签名，重发一次；其他原因的401直接返回
+    #[maybe_async_attr]
+    async fn execute_signed(
+        &self,
+        method: HttpMethod,
+        url: Url,
+        signed_url: &str,
+        sign_body: &str,
+        build: impl Fn(RequestBuilder) -> Result<RequestBuilder, PayError>,
+    ) -> Result<SignedResponse, PayError> {
+        let mut retried = false;
+        loop {
+            let headers = self.build_header(method.clone(), signed_url, sign_body)?;
+            let builder = self.request_builder(&self.client, method.clone(), url.clone());
+            let response = self.execute(build(builder.headers(headers))?).await?;
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = self.read_bytes(response).await?;
+            let response = SignedResponse {
+                status,
+                headers,
+                body,
+            };
+            if retried
+                || status != StatusCode::UNAUTHORIZED
+                || !util::is_timestamp_rejected(&response.text())
+            {
+                return Ok(response);
+            }
+            let Some(skew) = self.detect_clock_skew(&response.headers) else {
+                return Ok(response);
+            };
+            error!("clock skew {}s detected, re-sign and retry", skew);
+            retried = true;
+        }
+    }
+
+    /// 解析应答，开启应答验签时先验签再反序列化
+    #[maybe_async_attr]
+    async fn read_response<R: ResponseTrait>(
+        &self,
+        response: SignedResponse,
+    ) -> Result<R, PayError> {
+        let status = response.status.as_u16();
+        let verify = self.response_verify && response.status.is_success();
+        let request_id = response.request_id();
+        let logged_id = request_id.as_deref().unwrap_or("-");
+        if response.status.is_success() {
+            info!(
+                "wechat pay response status: {} request_id: {}",
+                status, logged_id
+            );
+        } else {
+            error!(
+                "wechat pay response status: {} request_id: {}",
+                status, logged_id
+            );
+        }
+        let body = response.text();
+        if verify {
+            self.verify_response(&response.headers, body.as_str())
+                .await?;
+        }
+        // 202/204等没有应答体的接口按null解析，失败状态码的空应答是网关等返回的错误
+        let body = if body.is_empty() {
+            if !response.status.is_success() {
+                return Err(response.into_error());
+            }
+            "null"
+        } else {
+            body.as_str()
+        };
+        let mut parsed = match util::parse_response::<R>(body) {
+            Ok(parsed) => parsed,
+            // 失败状态码的应答不是R的结构时按微信支付错误应答解析，如分页接口被限频
+            Err(_) if !response.status.is_success() => return Err(response.into_error()),
+            Err(e) => return Err(PayError::response_parse(status, body, e)),
+        };
+        parsed.set_status(status);
+        if let Some(request_id) = request_id {
+            parsed.set_request_id(request_id);
+        }
+        Ok(parsed)
+    }
+
+    /// 读取应答体，超过max_response_size时返回`PayError::ResponseTooLarge`，不会把超大应答整个读入内存
+    #[maybe_async_attr]
+    async fn read_body(&self, response: Response) -> Result<String, PayError> {
+        let body = self.read_bytes(response).await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// 读取应答原始字节，开启gzip等feature时为解压后的内容
+    #[maybe_async_attr]
+    async fn read_bytes(&self, response: Response) -> Result<Vec<u8>, PayError> {
+        let limit = self.max_response_size;
+        if response
+            .content_length()
+            .is_some_and(|length| length > limit as u64)
+        {
+            return Err(PayError::ResponseTooLarge(limit));
+        }
+        #[cfg(feature = "async")]
+        let body = {
+            let mut response = response;
+            let mut body = vec![];
+            while let Some(chunk) = response.chunk().await? {
+                if body.len() + chunk.len() > limit {
+                    return Err(PayError::ResponseTooLarge(limit));
+                }
+                body.extend_from_slice(&chunk);
+            }
+            body
+        };
+        #[cfg(not(feature = "async"))]
+        let body = {
+            use std::io::Read;
+            let mut body = vec![];
+            response.take(limit as u64 + 1).read_to_end(&mut body)?;
+            if body.len() > limit {
+                return Err(PayError::ResponseTooLarge(limit));
+            }
+            body
+        };
+        Ok(body)
+    }
+
+    /// 执行请求，dry-run模式下返回`PayError::DryRun`而不访问网络
+    #[maybe_async_attr]
+    async fn execute(&self, builder: RequestBuilder) -> Result<Response, PayError> {
+        let request = builder.build()?;
+        if self.dry_run {
+            let body = request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(|body| String::from_utf8_lossy(body).to_string())
+                .unwrap_or_default();
+            let request = DryRunRequest {
+                method: request.method().to_string(),
+                url: request.url().to_string(),
+                headers: request.headers().clone(),
+                body,
+            };
+            debug!("dry run: {}", request);
+            return Err(PayError::DryRun(Box::new(request)));
+        }
+        let mut fallback_hosts = self.fallback_hosts.iter();
+        let mut request = request;
+        loop {
+            let retry = request.try_clone();
+            let error = match self.client.execute(request).await {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+            // 只有连接失败时才切换域名，已发出的请求可能已被受理，不能重试
+            let next = match retry {
+                Some(retry) if error.is_connect() => fallback_hosts
+                    .by_ref()
+                    .find_map(|host| self.fallback_request(&retry, host)),
+                _ => None,
+            };
+            match next {
+                Some(next) => {
+                    debug!("connect error: {}, retry with fallback host", error);
+                    request = next;
+                }
+                None => return Err(error.into()),
+            }
+        }
+    }
+
+    /// 拼接base_url和接口路径，返回实际发送的URL和参与签名的路径
+    /// 路径和查询参数中的中文、空格等字符发送时会被编码，签名必须使用编码后的路径，否则验签失败
+    fn request_url(&self, path: &str) -> Result<(Url, String), PayError> {
+        let url = Url::parse(format!("{}{}", self.base_url(), path).as_str())
+            .map_err(|e| PayError::InvalidParams(format!("invalid url {}: {}", path, e)))?;
+        let signed_url = match url.as_str().strip_prefix(self.base_url.as_str()) {
+            Some(signed_url) => signed_url.to_string(),
+            None => match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            },
+        };
+        Ok((url, signed_url))
+    }
+
+    /// 按请求方法创建RequestBuilder，新增HttpMethod时只需修改这里
+    fn request_builder(&self, client: &Client, method: HttpMethod, url: Url) -> RequestBuilder {
+        match method {
             HttpMethod::GET => client.get(url),
             HttpMethod::POST => client.post(url),
             HttpMethod::PUT => client.put(url),
             HttpMethod::DELETE => client.delete(url),
             HttpMethod::PATCH => client.patch(url),
-        };
+        }
+    }
 
-        builder
-            .headers(headers)
-            .body(body)
-            .send()
-            .await?
-            .json::<R>()
-            .await
-            .map(Ok)?
+    /// 设置请求体，开启with_request_gzip且达到阈值时压缩发送，签名已按压缩前的body计算
+    fn request_body(
+        &self,
+        builder: RequestBuilder,
+        body: String,
+    ) -> Result<RequestBuilder, PayError> {
+        #[cfg(feature = "gzip")]
+        if let Some(min_size) = self.request_gzip
+            && !body.is_empty()
+            && body.len() >= min_size
+        {
+            let compressed = util::gzip(body.as_bytes())?;
+            return Ok(builder
+                .header(reqwest::header::CONTENT_ENCODING, "gzip")
+                .body(compressed));
+        }
+        Ok(builder.body(body))
+    }
+
+    /// 将请求的base_url替换为备用域名，签名只包含path，切换域名无需重新签名
+    fn fallback_request(&self, request: &Request, host: &str) -> Option<Request> {
+        let path = request
+            .url()
+            .as_str()
+            .strip_prefix(self.base_url.as_str())?;
+        let url = Url::parse(format!("{}{}", host.trim_end_matches('/'), path).as_str()).ok()?;
+        let mut request = request.try_clone()?;
+        *request.url_mut() = url;
+        Some(request)
     }
 
+    /// 使用应答头Wechatpay-Serial对应的平台证书验签
+    /// 缓存中没有该序列号时(如平台证书轮换)，下载一次平台证书后重试
     #[maybe_async_attr]
-    pub async fn get_pay<R: ResponseTrait>(&self, url: &str) -> Result<R, PayError> {
-        let body = "";
-        let headers = self.build_header(HttpMethod::GET, url, body)?;
-        let client = Client::new();
-        let url = format!("{}{}", self.base_url(), url);
-        debug!("url: {} body: {}", url, body);
-        client
-            .get(url)
-            .headers(headers)
-            .body(body)
-            .send()
-            .await?
-            .json::<R>()
-            .await
-            .map(Ok)?
+    pub async fn verify_response(&self, headers: &HeaderMap, body: &str) -> Result<(), PayError> {
+        let response = NotificationRequest::from_parts(headers, body)?;
+        let pub_key = self.platform_public_key(response.serial.as_str()).await?;
+        response.verify(self, pub_key.as_str())
+    }
+
+    /// 验签并解密回调，使用Wechatpay-Serial对应的平台证书，resource解密后解析为`T`
+    /// 支付回调使用`WechatPayDecodeData`，其他事件可按`NotificationRequest::event_type`选择对应的结构
+    /// 回调使用尚未缓存的新证书签名时，下载一次平台证书后重试，每次调用最多刷新一次
+    #[maybe_async_attr]
+    pub async fn decrypt_notification<T: ResponseTrait>(
+        &self,
+        notification: &NotificationRequest,
+    ) -> Result<T, PayError> {
+        let pub_key = self
+            .platform_public_key(notification.serial.as_str())
+            .await?;
+        notification.decrypt_as(self, pub_key.as_str())
+    }
+
+    /// 所有事件类型共用一个回调地址时的统一入口，验签并解密一次后按event_type返回`NotificationEvent`
+    /// 回调使用尚未缓存的新证书签名时，下载一次平台证书后重试
+    #[maybe_async_attr]
+    pub async fn handle_notification(
+        &self,
+        headers: &HeaderMap,
+        body: impl AsRef<[u8]>,
+    ) -> Result<NotificationEvent, PayError> {
+        let notification = NotificationRequest::from_parts(headers, body)?;
+        let pub_key = self
+            .platform_public_key(notification.serial.as_str())
+            .await?;
+        notification.decrypt_event(self, pub_key.as_str())
+    }
+
+    /// 从回调的header和原始body验签并解密退款回调
+    /// 退款是异步处理的，以退款回调或退款查询的结果为准更新退款单状态
+    #[maybe_async_attr]
+    pub async fn decrypt_refund_notification(
+        &self,
+        headers: &HeaderMap,
+        body: impl AsRef<[u8]>,
+    ) -> Result<RefundNotify, PayError> {
+        let notification = NotificationRequest::from_parts(headers, body)?;
+        self.decrypt_notification(&notification).await
+    }
+
+    /// 按序列号获取平台公钥，缓存中没有时下载一次平台证书，仍找不到则返回错误
+    #[maybe_async_attr]
+    async fn platform_public_key(&self, serial: &str) -> Result<String, PayError> {
+        if let Some(pinned) = self.platform_certs.pinned_serial()
+            && pinned != serial
+        {
+            return Err(PayError::VerifyError(format!(
+                "unexpected platform certificate serial: {}, pinned: {}",
+                serial, pinned
+            )));
+        }
+        if let Some(pub_key) = self.platform_certs.get(serial) {
+            return Ok(pub_key);
+        }
+        debug!("platform certificate {} not cached, refresh", serial);
+        self.refresh_certificates_single_flight().await?;
+        self.platform_certs.get(serial).ok_or_else(|| {
+            PayError::VerifyError(format!("platform certificate not found: {}", serial))
+        })
+    }
+
+    /// 切换固定的平台证书序列号，缓存中没有该证书时先下载平台证书，下载后仍找不到则保持原来的固定序列号并返回错误
+    /// 返回之前固定的序列号
+    #[maybe_async_attr]
+    pub async fn rotate_platform_serial(
+        &self,
+        serial_no: &str,
+    ) -> Result<Option<String>, PayError> {
+        if self.platform_certs.get(serial_no).is_none() {
+            self.refresh_certificates_single_flight().await?;
+        }
+        if self.platform_certs.get(serial_no).is_none() {
+            return Err(PayError::VerifyError(format!(
+                "platform certificate not found: {}",
+                serial_no
+            )));
+        }
+        Ok(self
+            .platform_certs
+            .set_pinned_serial(Some(serial_no.to_string())))
+    }
+
+    /// 同一时间只有一个请求下载平台证书，其余请求等待后直接使用下载结果
+    #[maybe_async_attr]
+    async fn refresh_certificates_single_flight(&self) -> Result<(), PayError> {
+        let generation = self.platform_certs.generation();
+        #[cfg(feature = "async")]
+        let _guard = self.platform_certs.refresh_lock.lock().await;
+        #[cfg(not(feature = "async"))]
+        let _guard = self
+            .platform_certs
+            .refresh_lock
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if self.platform_certs.generation() != generation {
+            return Ok(());
+        }
+        self.refresh_certificates().await.map(|_| ())
+    }
+
+    /// 下载并解密平台证书，按序列号写入缓存，返回下载到的证书数量
+    #[maybe_async_attr]
+    pub async fn refresh_certificates(&self) -> Result<usize, PayError> {
+        // 没有data的应答不能当作证书列表为空，否则会在没有任何证书的情况下标记为已刷新
+        let certificates = self.certificates().await?.data.ok_or_else(|| {
+            PayError::WechatError("certificates response has no data".to_string())
+        })?;
+        for certificate in &certificates {
+            let data = self.decrypt_aead(&certificate.encrypt_certificate)?;
+            let pub_key = util::x509_to_pem(data.as_slice())
+                .map_err(|e| PayError::VerifyError(format!("certificate parse error: {}", e)))?;
+            self.platform_certs
+                .insert(certificate.serial_no.as_str(), pub_key);
+        }
+        self.platform_certs.refreshed();
+        Ok(certificates.len())
     }
 
     #[maybe_async_attr]
     pub async fn h5_pay(&self, params: H5Params) -> Result<H5Response, PayError> {
-        let url = "/v3/pay/transactions/h5";
+        let url = self.transactions_url(&PayType::H5);
         self.pay(HttpMethod::POST, url, params).await
     }
     #[maybe_async_attr]
     pub async fn app_pay(&self, params: AppParams) -> Result<AppResponse, PayError> {
-        let url = "/v3/pay/transactions/app";
+        let url = self.transactions_url(&PayType::App);
         self.pay(HttpMethod::POST, url, params)
             .await
-            .map(|mut result: AppResponse| {
+            .and_then(|mut result: AppResponse| {
                 if let Some(prepay_id) = &result.prepay_id {
-                    result.sign_data = Some(self.mut_sign_data("", prepay_id));
+                    result.sign_data = Some(self.mut_sign_data("", prepay_id)?);
                 }
-                result
+                Ok(result)
             })
     }
     #[maybe_async_attr]
     pub async fn jsapi_pay(&self, params: JsapiParams) -> Result<JsapiResponse, PayError> {
-        let url = "/v3/pay/transactions/jsapi";
+        let url = self.transactions_url(&PayType::Jsapi);
         self.pay(HttpMethod::POST, url, params)
             .await
-            .map(|mut result: JsapiResponse| {
+            .and_then(|mut result: JsapiResponse| {
                 if let Some(prepay_id) = &result.prepay_id {
-                    result.sign_data = Some(self.mut_sign_data("prepay_id=", prepay_id));
+                    result.sign_data = Some(self.mut_sign_data("prepay_id=", prepay_id)?);
                 }
-                result
+                Ok(result)
             })
     }
+    /// 小程序支付
+    /// 小程序下单与JSAPI下单是同一个接口`endpoints::TRANSACTIONS_JSAPI`，这里直接复用`jsapi_pay`
+    /// 付款码支付(MICROPAY)只在v2接口中提供，不属于这里的micro
     #[maybe_async_attr]
     pub async fn micro_pay(&self, params: MicroParams) -> Result<MicroResponse, PayError> {
-        let url = "/v3/pay/transactions/jsapi";
-        self.pay(HttpMethod::POST, url, params)
-            .await
-            .map(|mut result: MicroResponse| {
-                if let Some(prepay_id) = &result.prepay_id {
-                    result.sign_data = Some(self.mut_sign_data("prepay_id=", prepay_id));
-                }
-                result
-            })
+        self.jsapi_pay(params.into()).await.map(MicroResponse::from)
     }
     #[maybe_async_attr]
     pub async fn native_pay(&self, params: NativeParams) -> Result<NativeResponse, PayError> {
-        let url = "/v3/pay/transactions/native";
+        let url = self.transactions_url(&PayType::Native);
         self.pay(HttpMethod::POST, url, params).await
     }
 
+    /// 按商户订单号或微信支付订单号查询订单，统一拼接查询地址和mchid参数
     #[maybe_async_attr]
-    pub async fn certificates(&self) -> Result<CertificateResponse, PayError> {
-        let url = "/v3/certificates";
-        self.get_pay(url).await
+    pub async fn query_order(
+        &self,
+        query: impl Into<OrderQuery>,
+    ) -> Result<WeChatResponse<OrderQueryResponse>, PayError> {
+        let url = util::with_query(&query.into().path(), &[("mchid", self.mch_id.as_str())]);
+        self.get_pay(url.as_str()).await
     }
+
     #[maybe_async_attr]
-    pub async fn get_weixin<S>(&self, h5_url: S, referer: S) -> Result<Option<String>, PayError>
-    where
-        S: AsRef<str>,
-    {
-        let client = Client::new();
-        let mut headers = HeaderMap::new();
-        headers.insert(REFERER, referer.as_ref().parse().unwrap());
-        let text = client
-            .get(h5_url.as_ref())
-            .headers(headers)
-            .send()
-            .await?
-            .text()
-            .await?;
-        text.split("\n")
-            .find(|line| line.contains("weixin://"))
-            .map(|line| {
-                line.split(r#"""#)
-                    .find(|line| line.contains("weixin://"))
-                    .map(|line| line.to_string())
-            })
-            .ok_or_else(|| PayError::WeixinNotFound)
+    pub async fn query_order_by_out_trade_no(
+        &self,
+        out_trade_no: impl AsRef<str>,
+    ) -> Result<WeChatResponse<OrderQueryResponse>, PayError> {
+        let out_trade_no = TradeNo::try_from(out_trade_no.as_ref())?;
+        self.query_order(out_trade_no).await
     }
-
     #[maybe_async_attr]
-    pub async fn refunds(
+    pub async fn query_order_by_transaction_id(
         &self,
-        params: RefundsParams,
-    ) -> Result<WeChatResponse<RefundsResponse>, PayError> {
-        let url = "/v3/refund/domestic/refunds";
-        let body = params.to_json();
-        let headers = self.build_header(HttpMethod::POST, url, body.as_str())?;
-        let client = Client::new();
-        let url = format!("{}{}", self.base_url(), url);
-        debug!("url: {} body: {}", url, body);
-        let builder = client.post(url);
-
-        builder
-            .headers(headers)
-            .body(body)
-            .send()
-            .await?
-            .json::<WeChatResponse<RefundsResponse>>()
+        transaction_id: impl AsRef<str>,
+    ) -> Result<WeChatResponse<OrderQueryResponse>, PayError> {
+        self.query_order(OrderQuery::transaction_id(transaction_id))
             .await
-            .map(Ok)?
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use dotenvy::dotenv;
-    use crate::model::{
-        AppParams, H5Params, H5SceneInfo, JsapiParams, MicroParams, NativeParams, RefundsParams,
-    };
-    use crate::pay::{PayNotifyTrait, WechatPay};
-    use crate::response::Certificate;
-    use crate::util;
-    use std::io::Write;
-    use tracing::debug;
+    /// 关闭订单，成功时微信返回204无应答体
+    #[maybe_async_attr]
+    pub async fn close_order(
+        &self,
+        out_trade_no: &TradeNo,
+    ) -> Result<WeChatResponse<()>, PayError> {
+        let url = format!("{}/close", OrderQuery::out_trade_no(out_trade_no).path());
+        let params = CloseOrderParams {
+            mchid: self.mch_id(),
+        };
+        self.post_pay(url.as_str(), params).await
+    }
 
-    #[test]
-    pub fn test_jsapi_pay() {
-        init_log();
-        dotenv().ok();
-        let wechat_pay = WechatPay::from_env();
-        let body = wechat_pay
-            .jsapi_pay(JsapiParams::new(
-                "测试支付1分",
-                "1243243",
-                1.into(),
-                "open_id".into(),
-            ))
-            .expect("jsapi_pay error");
-        debug!("body: {:?}", body);
+    /// 幂等关闭订单，订单已关闭、不存在或已支付时返回对应的终态而不是错误，适合定时清理未支付订单
+    /// 返回`CloseOrderOutcome::Paid`时订单已支付成功，应按支付成功处理而不是取消
+    #[maybe_async_attr]
+    pub async fn close_order_idempotent(
+        &self,
+        out_trade_no: &TradeNo,
+    ) -> Result<CloseOrderOutcome, PayError> {
+        match self.close_order(out_trade_no).await? {
+            WeChatResponse::Ok(_) => Ok(CloseOrderOutcome::Closed),
+            WeChatResponse::Err(error) => error
+                .error_code()
+                .as_ref()
+                .and_then(CloseOrderOutcome::from_error_code)
+                .ok_or_else(|| error.into()),
+        }
     }
 
-    #[test]
-    pub fn test_micro_pay() {
-        init_log();
-        dotenv().ok();
-        let wechat_pay = WechatPay::from_env();
-        let body = wechat_pay
-            .micro_pay(MicroParams::new(
-                "测试支付1分",
-                "1243243",
-                1.into(),
-                "open_id".into(),
-            ))
-            .expect("micro_pay error");
-        debug!("body: {:?}", body);
+    /// 查询投诉单列表，自动翻页
+    /// begin_date、end_date格式为yyyy-MM-dd，查询区间不超过30天；limit为每页条数，最大50
+    pub fn list_complaints(
+        &self,
+        begin_date: impl AsRef<str>,
+        end_date: impl AsRef<str>,
+        limit: u32,
+    ) -> Pages<'_, ComplaintListResponse> {
+        let url = util::with_query(
+            endpoints::MERCHANT_SERVICE_COMPLAINTS,
+            &[
+                ("begin_date", begin_date.as_ref()),
+                ("end_date", end_date.as_ref()),
+            ],
+        );
+        self.paginate::<ComplaintListResponse>(url, limit)
+    }
+
+    /// 根据商户号查用户的券，自动翻页；limit为每页条数，最大10
+    pub fn list_user_coupons(
+        &self,
+        openid: impl AsRef<str>,
+        limit: u32,
+    ) -> Pages<'_, UserCouponListResponse> {
+        let url = util::with_query(
+            &format!("{}/{}/coupons", endpoints::FAVOR_USERS, openid.as_ref()),
+            &[
+                ("appid", self.appid.as_str()),
+                ("creator_mchid", self.mch_id.as_str()),
+            ],
+        );
+        self.paginate::<UserCouponListResponse>(url, limit)
+    }
+
+    /// 查询用户的单张代金券详情，包含券状态、面额、可用时间和核销信息
+    #[maybe_async_attr]
+    pub async fn query_user_coupon(
+        &self,
+        openid: impl AsRef<str>,
+        coupon_id: impl AsRef<str>,
+    ) -> Result<WeChatResponse<UserCoupon>, PayError> {
+        let url = util::with_query(
+            &format!(
+                "{}/{}/coupons/{}",
+                endpoints::FAVOR_USERS,
+                openid.as_ref(),
+                coupon_id.as_ref()
+            ),
+            &[("appid", self.appid.as_str())],
+        );
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 上传营销图片(代金券的商户logo、券详情图片等)，返回图片的media_url
+    /// 仅支持JPG、BMP、PNG格式，大小不超过2M
+    #[maybe_async_attr]
+    pub async fn upload_marketing_image(
+        &self,
+        filename: impl AsRef<str>,
+        content: &[u8],
+    ) -> Result<WeChatResponse<MarketingImageUploadResponse>, PayError> {
+        let url = endpoints::FAVOR_MEDIA_IMAGE_UPLOAD;
+        let sha256 = format!("{:x}", Sha256::digest(content));
+        // 签名使用meta的JSON，而不是整个multipart请求体
+        let meta = serde_json::json!({
+            "filename": filename.as_ref(),
+            "sha256": sha256,
+        })
+        .to_string();
+        let boundary = format!("boundary{}", self.nonce_str());
+        let body = util::multipart_image_body(&boundary, &meta, filename.as_ref(), content)?;
+        let (url, signed_url) = self.request_url(url)?;
+        // 替换签名头中的application/json，header()会追加而不是覆盖
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", boundary)
+                .parse()
+                .unwrap(),
+        );
+        debug!("url: {} meta: {}", url, meta);
+        let response = self
+            .execute_signed(HttpMethod::POST, url, &signed_url, &meta, |builder| {
+                Ok(builder.headers(headers.clone()).body(body.clone()))
+            })
+            .await?;
+        self.read_response(response).await
+    }
+
+    /// 创建代金券批次
+    #[maybe_async_attr]
+    pub async fn create_coupon_stock(
+        &self,
+        params: CouponStockParams,
+    ) -> Result<WeChatResponse<CouponStockResponse>, PayError> {
+        self.post_pay(endpoints::FAVOR_COUPON_STOCKS, params).await
+    }
+
+    /// 上传券详情图片后创建代金券批次，上传返回的media_url写入`pattern_info.coupon_image`
+    /// params需要设置pattern_info，图片上传失败时直接返回上传接口的错误，不会创建批次
+    #[maybe_async_attr]
+    pub async fn upload_and_create_coupon_stock(
+        &self,
+        filename: impl AsRef<str>,
+        image: &[u8],
+        mut params: CouponStockParams,
+    ) -> Result<WeChatResponse<CouponStockResponse>, PayError> {
+        let Some(pattern_info) = params.pattern_info.as_mut() else {
+            return Err(PayError::InvalidParams(
+                "pattern_info is required to attach the coupon image".to_string(),
+            ));
+        };
+        match self.upload_marketing_image(filename, image).await? {
+            WeChatResponse::Ok(upload) => pattern_info.coupon_image = Some(upload.media_url),
+            WeChatResponse::Err(error) => return Ok(WeChatResponse::Err(error)),
+        }
+        self.create_coupon_stock(params).await
+    }
+
+    /// 开具电子发票，受理成功后通过get_fapiao_application查询开票结果
+    #[maybe_async_attr]
+    pub async fn create_fapiao(
+        &self,
+        params: FapiaoApplicationParams,
+    ) -> Result<WeChatResponse<()>, PayError> {
+        self.post_pay(endpoints::FAPIAO_APPLICATIONS, params).await
+    }
+
+    /// 查询电子发票，fapiao_id为空时返回该申请单下的全部发票
+    #[maybe_async_attr]
+    pub async fn get_fapiao_application(
+        &self,
+        fapiao_apply_id: impl AsRef<str>,
+        fapiao_id: Option<&str>,
+    ) -> Result<WeChatResponse<FapiaoApplicationResponse>, PayError> {
+        let path = format!(
+            "{}/{}",
+            endpoints::FAPIAO_APPLICATIONS,
+            fapiao_apply_id.as_ref()
+        );
+        let url = match fapiao_id {
+            Some(fapiao_id) => util::with_query(&path, &[("fapiao_id", fapiao_id)]),
+            None => path,
+        };
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 请求分账
+    #[maybe_async_attr]
+    pub async fn create_profit_sharing_order(
+        &self,
+        mut params: ProfitSharingOrderParams,
+    ) -> Result<WeChatResponse<ProfitSharingOrderResponse>, PayError> {
+        params.appid.get_or_insert_with(|| self.appid());
+        self.post_pay(endpoints::PROFITSHARING_ORDERS, params).await
+    }
+
+    /// 查询分账结果，返回每个分账接收方的分账结果
+    #[maybe_async_attr]
+    pub async fn query_profit_sharing_order(
+        &self,
+        out_order_no: impl AsRef<str>,
+        transaction_id: impl AsRef<str>,
+    ) -> Result<WeChatResponse<ProfitSharingOrderResponse>, PayError> {
+        let url = util::with_query(
+            &format!(
+                "{}/{}",
+                endpoints::PROFITSHARING_ORDERS,
+                out_order_no.as_ref()
+            ),
+            &[("transaction_id", transaction_id.as_ref())],
+        );
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 解冻剩余资金，不需要继续分账时将剩余资金解冻给本商户
+    #[maybe_async_attr]
+    pub async fn unfreeze_profit_sharing(
+        &self,
+        params: ProfitSharingUnfreezeParams,
+    ) -> Result<WeChatResponse<ProfitSharingOrderResponse>, PayError> {
+        self.post_pay(endpoints::PROFITSHARING_ORDERS_UNFREEZE, params)
+            .await
+    }
+
+    /// 请求分账回退，已分账的订单退款前需先从分账接收方回退资金
+    #[maybe_async_attr]
+    pub async fn profit_sharing_return(
+        &self,
+        params: ProfitSharingReturnParams,
+    ) -> Result<WeChatResponse<ProfitSharingReturnResponse>, PayError> {
+        self.post_pay(endpoints::PROFITSHARING_RETURN_ORDERS, params)
+            .await
+    }
+
+    /// 查询分账回退结果
+    #[maybe_async_attr]
+    pub async fn query_profit_sharing_return(
+        &self,
+        out_return_no: impl AsRef<str>,
+        out_order_no: impl AsRef<str>,
+    ) -> Result<WeChatResponse<ProfitSharingReturnResponse>, PayError> {
+        let url = util::with_query(
+            &format!(
+                "{}/{}",
+                endpoints::PROFITSHARING_RETURN_ORDERS,
+                out_return_no.as_ref()
+            ),
+            &[("out_order_no", out_order_no.as_ref())],
+        );
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 查询订单剩余待分金额
+    #[maybe_async_attr]
+    pub async fn query_profit_sharing_unsplit_amount(
+        &self,
+        transaction_id: impl AsRef<str>,
+    ) -> Result<WeChatResponse<ProfitSharingAmountResponse>, PayError> {
+        let url = format!(
+            "{}/{}/amounts",
+            endpoints::PROFITSHARING_TRANSACTIONS,
+            transaction_id.as_ref()
+        );
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 合单查询订单，返回每个子单的支付状态
+    #[maybe_async_attr]
+    pub async fn query_combine_order(
+        &self,
+        combine_out_trade_no: impl AsRef<str>,
+    ) -> Result<WeChatResponse<CombineOrderQueryResponse>, PayError> {
+        let url = format!(
+            "{}/{}",
+            endpoints::COMBINE_TRANSACTIONS_OUT_TRADE_NO,
+            combine_out_trade_no.as_ref()
+        );
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 合单关闭订单，需要传入要关闭的全部子单
+    #[maybe_async_attr]
+    pub async fn close_combine_order(
+        &self,
+        combine_out_trade_no: impl AsRef<str>,
+        sub_orders: Vec<CombineCloseSubOrder>,
+    ) -> Result<WeChatResponse<()>, PayError> {
+        let url = format!(
+            "{}/{}/close",
+            endpoints::COMBINE_TRANSACTIONS_OUT_TRADE_NO,
+            combine_out_trade_no.as_ref()
+        );
+        let params = CombineCloseParams {
+            combine_appid: self.appid(),
+            sub_orders,
+        };
+        self.post_pay(url.as_str(), params).await
+    }
+
+    /// 商家转账，用户确认收款后到账
+    #[maybe_async_attr]
+    pub async fn transfer_bills(
+        &self,
+        mut params: TransferBillsParams,
+    ) -> Result<WeChatResponse<TransferBillsResponse>, PayError> {
+        params.appid.get_or_insert_with(|| self.appid());
+        self.post_pay(endpoints::FUND_APP_TRANSFER_BILLS, params)
+            .await
+    }
+
+    /// 申请转账电子回单，回单异步生成，之后通过`query_transfer_receipt`获取下载地址
+    #[maybe_async_attr]
+    pub async fn apply_transfer_receipt(
+        &self,
+        out_bill_no: impl AsRef<str>,
+    ) -> Result<WeChatResponse<TransferReceiptResponse>, PayError> {
+        self.post_pay(
+            endpoints::FUND_APP_TRANSFER_ELECSIGN_OUT_BILL_NO,
+            TransferReceiptParams::new(out_bill_no),
+        )
+        .await
+    }
+
+    /// 查询转账电子回单，状态为FINISHED时返回download_url和文件hash
+    #[maybe_async_attr]
+    pub async fn query_transfer_receipt(
+        &self,
+        out_bill_no: impl AsRef<str>,
+    ) -> Result<WeChatResponse<TransferReceiptResponse>, PayError> {
+        let url = format!(
+            "{}/{}",
+            endpoints::FUND_APP_TRANSFER_ELECSIGN_OUT_BILL_NO,
+            out_bill_no.as_ref()
+        );
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 创建支付分订单，需要用户确认时应答中的package用于调起确认订单页面
+    #[maybe_async_attr]
+    pub async fn create_payscore_order(
+        &self,
+        mut params: PayScoreParams,
+    ) -> Result<WeChatResponse<PayScoreOrderResponse>, PayError> {
+        params.appid.get_or_insert_with(|| self.appid());
+        if params.notify_url.is_none() && !self.notify_url.is_empty() {
+            params.notify_url = Some(self.notify_url());
+        }
+        self.post_pay(endpoints::PAYSCORE_SERVICEORDER, params)
+            .await
+    }
+
+    /// 查询支付分订单，根据state和state_description判断订单所处阶段
+    #[maybe_async_attr]
+    pub async fn query_payscore_order(
+        &self,
+        service_id: impl AsRef<str>,
+        out_order_no: impl AsRef<str>,
+    ) -> Result<WeChatResponse<PayScoreOrderResponse>, PayError> {
+        let url = util::with_query(
+            endpoints::PAYSCORE_SERVICEORDER,
+            &[
+                ("service_id", service_id.as_ref()),
+                ("out_order_no", out_order_no.as_ref()),
+                ("appid", self.appid.as_str()),
+            ],
+        );
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 完结支付分订单，服务结束后按实际金额向用户收款
+    #[maybe_async_attr]
+    pub async fn complete_payscore_order(
+        &self,
+        out_order_no: impl AsRef<str>,
+        mut params: PayScoreCompleteParams,
+    ) -> Result<WeChatResponse<PayScoreOrderResponse>, PayError> {
+        params.appid.get_or_insert_with(|| self.appid());
+        let url = format!(
+            "{}/{}/complete",
+            endpoints::PAYSCORE_SERVICEORDER,
+            out_order_no.as_ref()
+        );
+        self.post_pay(url.as_str(), params).await
+    }
+
+    /// 修改支付分订单金额，完结后用户付款前可调整后付费项目，返回修改后的订单
+    #[maybe_async_attr]
+    pub async fn modify_payscore_order(
+        &self,
+        out_order_no: impl AsRef<str>,
+        mut params: PayScoreModifyParams,
+    ) -> Result<WeChatResponse<PayScoreOrderResponse>, PayError> {
+        params.appid.get_or_insert_with(|| self.appid());
+        let url = format!(
+            "{}/{}/modify",
+            endpoints::PAYSCORE_SERVICEORDER,
+            out_order_no.as_ref()
+        );
+        self.post_pay(url.as_str(), params).await
+    }
+
+    /// 支付分商户预授权，创建支付分订单前需要用户先授权
+    /// 应答的`mini_program_launch`生成跳转支付分小程序授权页面的参数
+    #[maybe_async_attr]
+    pub async fn payscore_permission_apply(
+        &self,
+        mut params: PayScorePermissionParams,
+    ) -> Result<WeChatResponse<PayScorePermissionApplyResponse>, PayError> {
+        params.appid.get_or_insert_with(|| self.appid());
+        if params.notify_url.is_none() && !self.notify_url.is_empty() {
+            params.notify_url = Some(self.notify_url());
+        }
+        self.post_pay(endpoints::PAYSCORE_PERMISSIONS, params).await
+    }
+
+    /// 通过授权协议号查询用户的授权状态
+    #[maybe_async_attr]
+    pub async fn payscore_permission_query(
+        &self,
+        service_id: impl AsRef<str>,
+        authorization_code: impl AsRef<str>,
+    ) -> Result<WeChatResponse<PayScorePermissionResponse>, PayError> {
+        let url = util::with_query(
+            &format!(
+                "{}/authorization-code/{}",
+                endpoints::PAYSCORE_PERMISSIONS,
+                authorization_code.as_ref()
+            ),
+            &[("service_id", service_id.as_ref())],
+        );
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 通过授权协议号解除用户的授权关系，成功时无应答体
+    #[maybe_async_attr]
+    pub async fn payscore_permission_terminate(
+        &self,
+        service_id: impl AsRef<str>,
+        authorization_code: impl AsRef<str>,
+        reason: impl AsRef<str>,
+    ) -> Result<WeChatResponse<()>, PayError> {
+        let url = format!(
+            "{}/authorization-code/{}/terminate",
+            endpoints::PAYSCORE_PERMISSIONS,
+            authorization_code.as_ref()
+        );
+        let params = PayScoreTerminateParams {
+            service_id: service_id.as_ref().to_string(),
+            reason: reason.as_ref().to_string(),
+        };
+        self.post_pay(url.as_str(), params).await
+    }
+
+    #[maybe_async_attr]
+    pub async fn certificates(&self) -> Result<CertificateResponse, PayError> {
+        // 下载证书本身不走应答验签，否则缓存为空时无法完成首次下载
+        let url = endpoints::CERTIFICATES;
+        let body = "";
+        let (url, signed_url) = self.request_url(url)?;
+        debug!("url: {} body: {}", url, body);
+        let response = self
+            .execute_signed(HttpMethod::GET, url, &signed_url, body, Ok)
+            .await?;
+        if !response.status.is_success() {
+            return Err(response.into_error());
+        }
+        let status = response.status.as_u16();
+        let body = response.text();
+        util::from_json_str(&body).map_err(|e| PayError::response_parse(status, &body, e))
+    }
+    /// 校验商户配置，发送一次签名的下载证书请求
+    /// 签名或商户号、证书序列号错误(401)时返回`PayError::AuthFailed`，可在服务启动时提前发现配置问题
+    #[maybe_async_attr]
+    pub async fn validate_credentials(&self) -> Result<(), PayError> {
+        let url = endpoints::CERTIFICATES;
+        let (url, signed_url) = self.request_url(url)?;
+        let response = self
+            .execute_signed(HttpMethod::GET, url, &signed_url, "", Ok)
+            .await?;
+        let status = response.status;
+        if status.is_success() {
+            return Ok(());
+        }
+        let text = response.text();
+        let error = util::from_json_str::<ErrorResponse>(&text)
+            .ok()
+            .map(|error| ErrorResponse {
+                status: Some(status.as_u16()),
+                ..error
+            });
+        if status == StatusCode::UNAUTHORIZED {
+            let message = error.and_then(|error| error.message).unwrap_or(text);
+            Err(PayError::AuthFailed(message))
+        } else {
+            Err(error
+                .map(PayError::from)
+                .unwrap_or(PayError::WechatError(text)))
+        }
+    }
+
+    #[maybe_async_attr]
+    pub async fn get_weixin<S>(&self, h5_url: S, referer: S) -> Result<Option<String>, PayError>
+    where
+        S: AsRef<str>,
+    {
+        let client = &self.client;
+        let mut headers = HeaderMap::new();
+        let referer = HeaderValue::from_str(referer.as_ref())
+            .map_err(|e| PayError::InvalidParams(format!("invalid referer: {}", e)))?;
+        headers.insert(REFERER, referer);
+        let builder = client.get(h5_url.as_ref()).headers(headers);
+        let response = self.execute(builder).await?;
+        let text = self.read_body(response).await?;
+        text.split("\n")
+            .find(|line| line.contains("weixin://"))
+            .map(|line| {
+                line.split(r#"""#)
+                    .find(|line| line.contains("weixin://"))
+                    .map(|line| line.to_string())
+            })
+            .ok_or_else(|| PayError::WeixinNotFound)
+    }
+
+    /// H5下单并解析拉起微信客户端的weixin://链接，referer需与H5支付申请的域名一致
+    /// 页面中找不到weixin://链接(如页面结构变化)时返回`PayError::WeixinLaunchUrlNotFound`，其中携带h5_url，可退回直接跳转h5_url
+    /// 下单应答为错误码时返回对应的`PayError::Api`，应答中没有h5_url时返回`PayError::WechatError`
+    #[maybe_async_attr]
+    pub async fn h5_pay_and_resolve(
+        &self,
+        params: H5Params,
+        referer: impl AsRef<str>,
+    ) -> Result<H5LaunchInfo, PayError> {
+        let response = self.h5_pay(params).await?;
+        let Some(h5_url) = response.h5_url else {
+            return Err(match ResponseStatus::err(&response) {
+                Some(error) => error.into(),
+                None => PayError::WechatError("h5_pay response missing h5_url".to_string()),
+            });
+        };
+        match self.get_weixin(h5_url.as_str(), referer.as_ref()).await {
+            Ok(Some(weixin_url)) => Ok(H5LaunchInfo { h5_url, weixin_url }),
+            Ok(None) | Err(PayError::WeixinNotFound) => {
+                Err(PayError::WeixinLaunchUrlNotFound(h5_url))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    #[maybe_async_attr]
+    pub async fn refunds(
+        &self,
+        params: RefundsParams,
+    ) -> Result<WeChatResponse<RefundsResponse>, PayError> {
+        // 退款请求体不能带appid
+        self.post_pay(endpoints::REFUND_DOMESTIC_REFUNDS, params)
+            .await
+    }
+
+    /// 按商户退款单号查询单笔退款，服务商模式下带sub_mchid
+    #[maybe_async_attr]
+    pub async fn query_refund(
+        &self,
+        out_refund_no: impl AsRef<str>,
+    ) -> Result<WeChatResponse<RefundsResponse>, PayError> {
+        let path = format!(
+            "{}/{}",
+            endpoints::REFUND_DOMESTIC_REFUNDS,
+            out_refund_no.as_ref()
+        );
+        let url = match &self.sub_mch_id {
+            Some(sub_mch_id) => util::with_query(&path, &[("sub_mchid", sub_mch_id.as_str())]),
+            None => path,
+        };
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 批量查询退款，按传入顺序返回，任一笔查询失败时返回错误
+    /// async模式下最多QUERY_REFUNDS_CONCURRENCY笔并发查询
+    #[cfg(feature = "async")]
+    pub async fn query_refunds<I, S>(
+        &self,
+        out_refund_nos: I,
+    ) -> Result<Vec<RefundsResponse>, PayError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        use futures_util::{StreamExt, TryStreamExt, stream};
+
+        let out_refund_nos = out_refund_nos
+            .into_iter()
+            .map(|out_refund_no| out_refund_no.as_ref().to_string())
+            .collect::<Vec<_>>();
+        stream::iter(out_refund_nos)
+            .map(|out_refund_no| async move {
+                match self.query_refund(out_refund_no).await? {
+                    WeChatResponse::Ok(refund) => Ok(refund),
+                    WeChatResponse::Err(error) => Err(error.into()),
+                }
+            })
+            .buffered(QUERY_REFUNDS_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+
+    /// 批量查询退款，按传入顺序逐笔查询，任一笔查询失败时返回错误
+    #[cfg(not(feature = "async"))]
+    pub fn query_refunds<I, S>(&self, out_refund_nos: I) -> Result<Vec<RefundsResponse>, PayError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        out_refund_nos
+            .into_iter()
+            .map(|out_refund_no| match self.query_refund(out_refund_no)? {
+                WeChatResponse::Ok(refund) => Ok(refund),
+                WeChatResponse::Err(error) => Err(error.into()),
+            })
+            .collect()
+    }
+
+    /// 查询订单的退款记录，微信支付没有按订单列出退款的接口，需传入该订单已知的商户退款单号
+    /// 查询到的退款不属于该订单时返回`PayError::PaymentMismatch`
+    #[maybe_async_attr]
+    pub async fn list_refunds_for_order<I, S>(
+        &self,
+        out_trade_no: impl AsRef<str>,
+        out_refund_nos: I,
+    ) -> Result<Vec<RefundsResponse>, PayError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let out_trade_no = out_trade_no.as_ref();
+        let refunds = self.query_refunds(out_refund_nos).await?;
+        if let Some(refund) = refunds
+            .iter()
+            .find(|refund| refund.out_trade_no != out_trade_no)
+        {
+            return Err(PayError::PaymentMismatch {
+                field: "out_trade_no",
+                expected: out_trade_no.to_string(),
+                actual: refund.out_trade_no.clone(),
+            });
+        }
+        Ok(refunds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::PayError;
+    use crate::mock_server::{self, PRIVATE_KEY, SignedBy, header, path_and_query, run, test_pay};
+    use crate::model::CombineCloseSubOrder;
+    use crate::model::WechatPayDecodeData;
+    #[cfg(not(feature = "async"))]
+    use crate::model::{AppParams, H5Params, H5SceneInfo, JsapiParams, MicroParams};
+    use crate::model::{
+        CouponStockParams, CouponStockPatternInfo, CouponStockUseRule, CouponUseRule,
+        FixedNormalCoupon,
+    };
+    use crate::model::{
+        FapiaoApplicationParams, FapiaoBuyerInformation, FapiaoInformationParams, FapiaoItem,
+        ProfitSharingOrderParams, ProfitSharingReceiverParams, ProfitSharingReturnParams,
+        ProfitSharingUnfreezeParams,
+    };
+    use crate::model::{NativeParams, OrderQuery, RefundsParams, TarType, TradeNo};
+    use crate::model::{TransferBillState, TransferBillsParams, TransferSceneReportInfo};
+    use crate::notify::NotificationRequest;
+    #[cfg(not(feature = "async"))]
+    use crate::pay::PayNotifyTrait;
+    use crate::pay::{WechatPay, WechatPayTrait};
+    use crate::request::HttpMethod;
+    #[cfg(not(feature = "async"))]
+    use crate::response::Certificate;
+    use crate::response::CloseOrderOutcome;
+    use crate::sign;
+    #[cfg(not(feature = "async"))]
+    use crate::util;
+    use aes_gcm::aead::{Aead, Payload};
+    use aes_gcm::{Aes256Gcm, aead::KeyInit};
+    use dotenvy::dotenv;
+    use rsa::pkcs8::{DecodePrivateKey, EncodePublicKey, LineEnding};
+    use rsa::sha2::{Digest, Sha256};
+    use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+    #[cfg(not(feature = "async"))]
+    use std::io::Write;
+    use tracing::debug;
+    use wiremock::matchers::method;
+    use wiremock::{Match, Mock, MockServer, Request, ResponseTemplate};
+
+    #[test]
+    #[cfg(not(feature = "async"))]
+    pub fn test_jsapi_pay() {
+        init_log();
+        dotenv().ok();
+        let wechat_pay = WechatPay::from_env();
+        let body = wechat_pay
+            .jsapi_pay(JsapiParams::new(
+                "测试支付1分",
+                "1243243",
+                1.into(),
+                "open_id".into(),
+            ))
+            .expect("jsapi_pay error");
+        debug!("body: {:?}", body);
     }
 
     #[test]
+    #[cfg(not(feature = "async"))]
+    pub fn test_micro_pay() {
+        init_log();
+        dotenv().ok();
+        let wechat_pay = WechatPay::from_env();
+        let body = wechat_pay
+            .micro_pay(MicroParams::new(
+                "测试支付1分",
+                "1243243",
+                1.into(),
+                "open_id".into(),
+            ))
+            .expect("micro_pay error");
+        debug!("body: {:?}", body);
+    }
+
+    #[test]
+    #[cfg(not(feature = "async"))]
     pub fn test_app_pay() {
         init_log();
         dotenv().ok();
@@ -248,6 +1537,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "async"))]
     pub fn test_h5_pay() {
         init_log();
         dotenv().ok();
@@ -267,6 +1557,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "async"))]
     pub fn test_certificates() {
         init_log();
         dotenv().ok();
@@ -292,6 +1583,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "async"))]
     pub fn test_decode_certificates() {
         init_log();
         dotenv().ok();
@@ -308,6 +1600,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "async"))]
     pub fn test_blocking_refunds() {
         init_log();
         dotenv().ok();
@@ -391,4 +1684,2175 @@ mod tests {
             debug!("refunds error: {:?}", body.err());
         }
     }
+
+    const NOTIFY_URL: &str = "https://mydomain.com/notify";
+    const PLATFORM_KEY: &str = include_str!("../testdata/platform_key.pem");
+    const PLATFORM_CERT: &str = include_str!("../testdata/platform_cert.pem");
+    const V3_KEY: &str = "0123456789abcdef0123456789abcdef";
+    const ORDER_PATH: &str = "/v3/pay/transactions/id/4200000000000000000000000001";
+
+    fn certificates_body(serial_no: &str) -> String {
+        let nonce = "34246e50ad4a";
+        let ciphertext = Aes256Gcm::new(V3_KEY.as_bytes().into())
+            .encrypt(
+                nonce.as_bytes().into(),
+                Payload {
+                    msg: PLATFORM_CERT.as_bytes(),
+                    aad: b"certificate",
+                },
+            )
+            .unwrap();
+        serde_json::json!({
+            "data": [{
+                "serial_no": serial_no,
+                "effective_time": "2023-03-22T22:58:57+08:00",
+                "expire_time": "2099-03-20T22:58:57+08:00",
+                "encrypt_certificate": {
+                    "algorithm": "AEAD_AES_256_GCM",
+                    "nonce": nonce,
+                    "associated_data": "certificate",
+                    "ciphertext": crate::util::base64_encode(ciphertext),
+                }
+            }]
+        })
+        .to_string()
+    }
+
+    fn signed_response(body: &str, serial_no: &str) -> ResponseTemplate {
+        let timestamp = "1705066785";
+        let nonce = "Jh9oPZelCJIQeQ47kz4stzvDKpLEUhCX";
+        let signature = sign::sha256_sign(
+            PLATFORM_KEY,
+            format!("{}\n{}\n{}\n", timestamp, nonce, body).as_str(),
+        )
+        .unwrap();
+        ResponseTemplate::new(200)
+            .set_body_string(body)
+            .insert_header("Wechatpay-Timestamp", timestamp)
+            .insert_header("Wechatpay-Nonce", nonce)
+            .insert_header("Wechatpay-Signature", signature)
+            .insert_header("Wechatpay-Serial", serial_no)
+    }
+
+    /// 应答使用NEW证书签名，平台证书接口返回的证书序列号为certificates_serial
+    async fn start_server(certificates_serial: &'static str) -> MockServer {
+        mock_server::start(move |request: &Request| match request.url.path() {
+            "/v3/certificates" => ResponseTemplate::new(200).set_body_string(certificates_body(certificates_serial)),
+            _ => signed_response(
+                r#"{"appid":"wx0000000000000001","mchid":"1900000001","out_trade_no":"1243243","transaction_id":"4200000000000000000000000001","trade_state":"SUCCESS","trade_state_desc":"支付成功","new_field":"new"}"#,
+                "NEW",
+            ),
+        })
+        .await
+    }
+
+    /// 配置了证书序列号、APIv3密钥和回调地址的测试商户
+    fn merchant_pay(base_url: &str) -> WechatPay {
+        WechatPay::try_new(
+            "wx0000000000000001",
+            "1900000001",
+            PRIVATE_KEY,
+            "serial_no",
+            V3_KEY,
+            NOTIFY_URL,
+        )
+        .unwrap()
+        .with_base_url(base_url)
+    }
+
+    fn wechat_pay(server: &MockServer) -> WechatPay {
+        let old_key = RsaPublicKey::from(&RsaPrivateKey::from_pkcs8_pem(PRIVATE_KEY).unwrap())
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        merchant_pay(&server.uri())
+            .with_response_verify(true)
+            .with_platform_certificate("OLD", old_key)
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_refresh_certificates_errors() {
+        let server = run!(mock_server::start(|request: &Request| {
+            if request.headers.get("Authorization").is_some_and(|value| {
+                value
+                    .to_str()
+                    .is_ok_and(|value| value.contains("serial_no=\"serial_no\""))
+            }) {
+                return ResponseTemplate::new(200).set_body_string("{}");
+            }
+            ResponseTemplate::new(401)
+                .set_body_string(r#"{"code":"SIGN_ERROR","message":"签名错误"}"#)
+        }));
+        // 401等失败应答返回微信支付错误，不标记为已刷新
+        let wechat_pay = test_pay(&server);
+        let error = wechat_pay.refresh_certificates().await.unwrap_err();
+        assert!(
+            matches!(&error, PayError::Api { status: Some(401), code, .. } if code == "SIGN_ERROR")
+        );
+        assert_eq!(wechat_pay.platform_certs.generation(), 0);
+        // 成功应答中没有data时同样返回错误
+        let wechat_pay = merchant_pay(&server.uri());
+        let result = wechat_pay.refresh_certificates().await;
+        assert!(result.is_err());
+        assert_eq!(wechat_pay.platform_certs.generation(), 0);
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_verify_response_refresh_on_serial_mismatch() {
+        let server = run!(start_server("NEW"));
+        let wechat_pay = wechat_pay(&server);
+        let response = wechat_pay
+            .query_order_by_transaction_id("4200000000000000000000000001")
+            .await
+            .expect("verify after refresh");
+        let order = response.ok().unwrap();
+        assert_eq!(order.out_trade_no, "1243243");
+        assert_eq!(order.raw()["new_field"], "new");
+        assert_eq!(run!(mock_server::hits(&server, "/v3/certificates")), 1);
+        assert_eq!(run!(mock_server::hits(&server, ORDER_PATH)), 1);
+        let request = &run!(mock_server::requests(&server))[0];
+        assert_eq!(request.method, "GET");
+        assert!(
+            header(request, "Authorization")
+                .unwrap()
+                .starts_with("WECHATPAY2-SHA256-RSA2048")
+        );
+
+        // 证书已缓存，不再重复下载
+        wechat_pay
+            .query_order_by_transaction_id("4200000000000000000000000001")
+            .await
+            .expect("verify with cached certificate");
+        assert_eq!(run!(mock_server::hits(&server, "/v3/certificates")), 1);
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_verify_response_unknown_serial_after_refresh() {
+        let server = run!(start_server("OTHER"));
+        let wechat_pay = wechat_pay(&server);
+        let error = wechat_pay
+            .query_order_by_transaction_id("4200000000000000000000000001")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, PayError::VerifyError(_)));
+        // 只刷新一次，不会无限重试
+        assert_eq!(run!(mock_server::hits(&server, "/v3/certificates")), 1);
+    }
+
+    fn notification(serial_no: &str) -> NotificationRequest {
+        let plaintext = r#"{"mchid":"1900000001","appid":"wx0000000000000001","out_trade_no":"1243243","transaction_id":"4200000000000000000000000001","trade_type":"JSAPI","trade_state":"SUCCESS","trade_state_desc":"支付成功","bank_type":"OTHERS","attach":"","success_time":"2024-01-12T10:36:13+08:00","payer":{"openid":"open_id"},"amount":{"total":1}}"#;
+        encrypted_notification(serial_no, "TRANSACTION.SUCCESS", "transaction", plaintext)
+    }
+
+    /// 使用V3_KEY加密resource、PLATFORM_KEY签名的回调
+    fn encrypted_notification(
+        serial_no: &str,
+        event_type: &str,
+        original_type: &str,
+        plaintext: &str,
+    ) -> NotificationRequest {
+        let nonce = "gZiqzlfayUu2";
+        let ciphertext = Aes256Gcm::new(V3_KEY.as_bytes().into())
+            .encrypt(
+                nonce.as_bytes().into(),
+                Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: original_type.as_bytes(),
+                },
+            )
+            .unwrap();
+        let body = serde_json::json!({
+            "id": "29a61973-babf-599a-966d-6bcdcf17360c",
+            "create_time": "2024-01-12T21:39:44+08:00",
+            "event_type": event_type,
+            "resource_type": "encrypt-resource",
+            "resource": {
+                "algorithm": "AEAD_AES_256_GCM",
+                "ciphertext": crate::util::base64_encode(ciphertext),
+                "associated_data": original_type,
+                "original_type": original_type,
+                "nonce": nonce,
+            },
+            "summary": "通知",
+        })
+        .to_string();
+        let timestamp = "1705066785";
+        let nonce = "Jh9oPZelCJIQeQ47kz4stzvDKpLEUhCX";
+        let signature = sign::sha256_sign(
+            PLATFORM_KEY,
+            format!("{}\n{}\n{}\n", timestamp, nonce, body).as_str(),
+        )
+        .unwrap();
+        NotificationRequest::new(
+            timestamp,
+            nonce,
+            signature.as_str(),
+            serial_no,
+            body.as_str(),
+        )
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_decrypt_notification_refresh_on_unknown_serial() {
+        let server = run!(start_server("NEW"));
+        let wechat_pay = wechat_pay(&server);
+        let data = wechat_pay
+            .decrypt_notification::<WechatPayDecodeData>(&notification("NEW"))
+            .await
+            .expect("decrypt after refresh");
+        assert_eq!(data.out_trade_no, "1243243");
+        assert_eq!(run!(mock_server::hits(&server, "/v3/certificates")), 1);
+
+        // 证书已缓存，不再重复下载
+        wechat_pay
+            .decrypt_notification::<WechatPayDecodeData>(&notification("NEW"))
+            .await
+            .expect("decrypt with cached certificate");
+        assert_eq!(run!(mock_server::hits(&server, "/v3/certificates")), 1);
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_decrypt_refund_notification() {
+        let server = run!(start_server("NEW"));
+        let wechat_pay = wechat_pay(&server);
+        let plaintext = r#"{"mchid":"1900000001","out_trade_no":"1217752501201407033233368018","transaction_id":"1217752501201407033233368018","out_refund_no":"1217752501201407033233368018","refund_id":"1217752501201407033233368018","refund_status":"SUCCESS","success_time":"2018-06-08T10:34:56+08:00","user_received_account":"招商银行信用卡0403","amount":{"total":999,"refund":999,"payer_total":999,"payer_refund":999}}"#;
+        let notification = encrypted_notification("NEW", "REFUND.SUCCESS", "refund", plaintext);
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in [
+            (crate::notify::WECHATPAY_TIMESTAMP, &notification.timestamp),
+            (crate::notify::WECHATPAY_NONCE, &notification.nonce),
+            (crate::notify::WECHATPAY_SIGNATURE, &notification.signature),
+            (crate::notify::WECHATPAY_SERIAL, &notification.serial),
+        ] {
+            headers.insert(name, value.parse().unwrap());
+        }
+        let refund = wechat_pay
+            .decrypt_refund_notification(&headers, notification.body.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(refund.refund_status, crate::model::RefundStatus::Success);
+        assert_eq!(refund.user_received_account, "招商银行信用卡0403");
+        assert_eq!(refund.amount.payer_refund, 999);
+
+        let mut tampered = notification.body.clone();
+        tampered.push(' ');
+        let error = wechat_pay
+            .decrypt_refund_notification(&headers, tampered.as_bytes())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, PayError::VerifyError(_)));
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_handle_notification() {
+        use crate::notify::NotificationEvent;
+
+        let server = run!(start_server("NEW"));
+        let wechat_pay = wechat_pay(&server);
+        let cases = [
+            (
+                "TRANSACTION.SUCCESS",
+                "transaction",
+                r#"{"mchid":"1900000001","appid":"wx_appid","out_trade_no":"1243243","transaction_id":"4200001","trade_type":"NATIVE","trade_state":"SUCCESS","trade_state_desc":"支付成功","bank_type":"OTHERS","attach":"","success_time":"2024-05-01T10:00:00+08:00","payer":{"openid":"open_id"},"amount":{"total":1}}"#,
+            ),
+            (
+                "REFUND.SUCCESS",
+                "refund",
+                r#"{"mchid":"1900000001","out_trade_no":"1243243","transaction_id":"4200001","out_refund_no":"R1","refund_id":"50300001","refund_status":"SUCCESS","success_time":"2024-05-01T10:00:00+08:00","user_received_account":"支付用户零钱","amount":{"total":100,"refund":10,"payer_total":100,"payer_refund":10}}"#,
+            ),
+            (
+                "MCHTRANSFER.BILL.FINISHED",
+                "mch_payment",
+                r#"{"mch_id":"1900000001","out_bill_no":"plfk2020042013","transfer_bill_no":"1330000071100999991182020050700019480001","state":"SUCCESS","transfer_amount":2000,"openid":"open_id","create_time":"2024-05-01T10:00:00+08:00","update_time":"2024-05-01T10:00:05+08:00"}"#,
+            ),
+            (
+                "PROFITSHARING.SUCCESS",
+                "profitsharing",
+                r#"{"sp_mchid":"1900000100","sub_mchid":"1900000109","transaction_id":"4200001","order_id":"3008450740201411110007820472","out_order_no":"P20150806125346","receiver":{"type":"MERCHANT_ID","account":"1900000110","amount":888,"description":"运费分账"},"success_time":"2024-05-01T10:00:00+08:00"}"#,
+            ),
+            (
+                "COUPON.USE",
+                "coupon",
+                r#"{"stock_id":"9856888","coupon_id":"98674556"}"#,
+            ),
+        ];
+        let mut events = vec![];
+        for (event_type, original_type, plaintext) in cases {
+            let notification = encrypted_notification("NEW", event_type, original_type, plaintext);
+            let mut headers = reqwest::header::HeaderMap::new();
+            for (name, value) in [
+                (crate::notify::WECHATPAY_TIMESTAMP, &notification.timestamp),
+                (crate::notify::WECHATPAY_NONCE, &notification.nonce),
+                (crate::notify::WECHATPAY_SIGNATURE, &notification.signature),
+                (crate::notify::WECHATPAY_SERIAL, &notification.serial),
+            ] {
+                headers.insert(name, value.parse().unwrap());
+            }
+            let event = wechat_pay
+                .handle_notification(&headers, notification.body.as_bytes())
+                .await
+                .unwrap();
+            events.push(event);
+
+            let mut tampered = notification.body.clone();
+            tampered.push(' ');
+            let error = wechat_pay
+                .handle_notification(&headers, tampered.as_bytes())
+                .await
+                .unwrap_err();
+            assert!(matches!(error, PayError::VerifyError(_)));
+        }
+        assert!(
+            matches!(&events[0], NotificationEvent::Payment(data) if data.out_trade_no == "1243243")
+        );
+        assert!(
+            matches!(&events[1], NotificationEvent::Refund(refund) if refund.out_refund_no == "R1")
+        );
+        assert!(matches!(
+            &events[2],
+            NotificationEvent::Transfer(transfer)
+                if transfer.state == crate::model::TransferBillState::Success && transfer.transfer_amount == 2000
+        ));
+        assert!(matches!(
+            &events[3],
+            NotificationEvent::ProfitSharing(sharing)
+                if sharing.receiver.receiver_type == "MERCHANT_ID" && sharing.receiver.amount == 888
+        ));
+        assert!(matches!(
+            &events[4],
+            NotificationEvent::Other { event_type, raw }
+                if event_type == "COUPON.USE" && raw["coupon_id"] == "98674556"
+        ));
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_decrypt_notification_unknown_serial_after_refresh() {
+        let server = run!(start_server("OTHER"));
+        let wechat_pay = wechat_pay(&server);
+        let error = wechat_pay
+            .decrypt_notification::<WechatPayDecodeData>(&notification("NEW"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, PayError::VerifyError(_)));
+        // 只刷新一次，不会无限重试
+        assert_eq!(run!(mock_server::hits(&server, "/v3/certificates")), 1);
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_pinned_platform_serial() {
+        let server = run!(start_server("NEW"));
+        let wechat_pay = wechat_pay(&server);
+        wechat_pay.pin_platform_serial("OLD");
+        let error = wechat_pay
+            .query_order_by_transaction_id("4200000000000000000000000001")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(error, PayError::VerifyError(message) if message.contains("unexpected platform certificate serial"))
+        );
+        let error = wechat_pay
+            .decrypt_notification::<WechatPayDecodeData>(&notification("NEW"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, PayError::VerifyError(_)));
+        // 固定序列号时不会自动下载证书
+        assert_eq!(run!(mock_server::hits(&server, "/v3/certificates")), 0);
+
+        // 切换到平台证书接口返回的新证书
+        let previous = wechat_pay.rotate_platform_serial("NEW").await.unwrap();
+        assert_eq!(previous.as_deref(), Some("OLD"));
+        assert_eq!(run!(mock_server::hits(&server, "/v3/certificates")), 1);
+        assert_eq!(
+            wechat_pay.clone().pinned_platform_serial().as_deref(),
+            Some("NEW")
+        );
+        wechat_pay
+            .query_order_by_transaction_id("4200000000000000000000000001")
+            .await
+            .expect("verify with pinned serial");
+
+        // 证书不存在时不切换
+        let result = wechat_pay.rotate_platform_serial("MISSING").await;
+        assert!(result.is_err());
+        assert_eq!(wechat_pay.pinned_platform_serial().as_deref(), Some("NEW"));
+        assert_eq!(wechat_pay.unpin_platform_serial().as_deref(), Some("NEW"));
+        assert_eq!(wechat_pay.pinned_platform_serial(), None);
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    struct TradeStateNotify {
+        out_trade_no: String,
+        trade_state: String,
+    }
+
+    impl crate::response::ResponseTrait for TradeStateNotify {}
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_decrypt_notification_generic() {
+        let server = run!(start_server("NEW"));
+        let wechat_pay = wechat_pay(&server);
+        let notification = notification("NEW");
+        assert_eq!(notification.event_type().unwrap(), "TRANSACTION.SUCCESS");
+        let data = wechat_pay
+            .decrypt_notification::<TradeStateNotify>(&notification)
+            .await
+            .unwrap();
+        assert_eq!(data.out_trade_no, "1243243");
+        assert_eq!(data.trade_state, "SUCCESS");
+        let raw = wechat_pay
+            .decrypt_notification::<serde_json::Value>(&notification)
+            .await
+            .unwrap();
+        assert_eq!(raw["payer"]["openid"], "open_id");
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_dry_run_returns_signed_request() {
+        let wechat_pay = merchant_pay("http://127.0.0.1:9").with_dry_run(true);
+        let params =
+            crate::model::JsapiParams::new("测试支付1分", "1243243", 1.into(), "open_id".into());
+        let error = wechat_pay.jsapi_pay(params).await.unwrap_err();
+        let PayError::DryRun(request) = error else {
+            panic!("expect dry run error, got {:?}", error);
+        };
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.url, "http://127.0.0.1:9/v3/pay/transactions/jsapi");
+        let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(body["appid"], "wx0000000000000001");
+        assert_eq!(body["notify_url"], "https://mydomain.com/notify");
+
+        // 按Authorization中的参数还原签名串，用商户公钥验签
+        let authorization = request.headers["authorization"].to_str().unwrap();
+        let field = |name: &str| {
+            authorization
+                .split([' ', ','])
+                .find_map(|pair| pair.strip_prefix(&format!("{}=\"", name)))
+                .map(|value| value.trim_end_matches('"').to_string())
+                .unwrap()
+        };
+        assert_eq!(field("serial_no"), "serial_no");
+        let message = format!(
+            "POST\n/v3/pay/transactions/jsapi\n{}\n{}\n{}\n",
+            field("timestamp"),
+            field("nonce_str"),
+            request.body
+        );
+        let hashed = Sha256::new().chain_update(message).finalize();
+        let signature = crate::util::base64_decode(field("signature")).unwrap();
+        RsaPublicKey::from(&RsaPrivateKey::from_pkcs8_pem(PRIVATE_KEY).unwrap())
+            .verify(Pkcs1v15Sign::new::<Sha256>(), &hashed, &signature)
+            .expect("signature verify");
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_fallback_host_on_connect_error() {
+        let server = run!(start_server("NEW"));
+        // 端口1无服务监听，连接失败后切换到备用域名
+        let wechat_pay = merchant_pay("http://127.0.0.1:1")
+            .with_fallback_hosts(vec!["not a url".to_string(), server.uri()]);
+        let response = wechat_pay
+            .query_order_by_transaction_id("4200000000000000000000000001")
+            .await
+            .expect("query with fallback host");
+        assert!(response.is_success());
+        assert_eq!(run!(mock_server::hits(&server, ORDER_PATH)), 1);
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_http_notify_url_rejected() {
+        let server = run!(mock_server::start(
+            |_: &Request| ResponseTemplate::new(200).set_body_string("{}")
+        ));
+        let http_notify = WechatPay::try_new(
+            "wx0000000000000001",
+            "1900000001",
+            PRIVATE_KEY,
+            "serial_no",
+            V3_KEY,
+            "http://mydomain.com/notify",
+        )
+        .unwrap()
+        .with_base_url(server.uri());
+        let params = crate::model::NativeParams::new("测试支付1分", "1243243", 1.into());
+        let error = http_notify.native_pay(params).await.unwrap_err();
+        assert!(
+            matches!(&error, PayError::InvalidParams(message) if message.contains("http://mydomain.com/notify"))
+        );
+
+        // 订单上指定的notify_url同样校验
+        let mut params = crate::model::NativeParams::new("测试支付1分", "1243243", 1.into());
+        params.notify_url = Some("http://mydomain.com/mall/notify".to_string());
+        let result = wechat_pay(&server).native_pay(params).await;
+        assert!(matches!(result, Err(PayError::InvalidParams(_))));
+
+        // 不注入字段的接口按参数上的notify_url校验，其他字段中包含notify_url字样不受影响
+        let refund = |notify_url: Option<&str>| {
+            let mut params = RefundsParams::new("1243243", 1, 1, Some("1243243"), None);
+            params.notify_url = notify_url.map(str::to_string);
+            params.reason = Some(r#"{"notify_url":"http://mydomain.com/notify"}"#.to_string());
+            params
+        };
+        let result = test_pay(&server)
+            .refunds(refund(Some("http://mydomain.com/refund/notify")))
+            .await;
+        assert!(matches!(result, Err(PayError::InvalidParams(_))));
+        assert!(run!(mock_server::requests(&server)).is_empty());
+        let result = test_pay(&server).refunds(refund(None)).await;
+        assert!(!matches!(result, Err(PayError::InvalidParams(_))));
+        assert_eq!(run!(mock_server::requests(&server)).len(), 1);
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_fapiao_application() {
+        let server = run!(mock_server::start(|request: &Request| {
+            match request.method.as_str() {
+            "POST" => ResponseTemplate::new(202),
+            _ => ResponseTemplate::new(200).set_body_string(
+                r#"{"total_count":1,"fapiao_information":[{"fapiao_id":"20200701123456","status":"ISSUED","blue_fapiao":{"fapiao_code":"044001911211","fapiao_number":"12897794","check_code":"69001808340631374774","password":"006>299-375/326>2+7/*0-+<351059<80<4*/5>+<11631+*3030/5*>7-42/2+7/9359+*5+>8>","fapiao_time":"2020-07-01T12:00:00+08:00"},"total_amount":382895,"tax_amount":44050,"amount":338845,"items":[{"tax_code":"3010101020203000000","goods_name":"出租汽车客运服务","quantity":100000000,"total_amount":42400,"tax_rate":600}]}]}"#,
+            ),
+        }
+        }));
+        let wechat_pay = merchant_pay(&server.uri());
+        let mut buyer = FapiaoBuyerInformation::new("INDIVIDUAL", "张三");
+        buyer.out_trade_no = Some("20150806125346".to_string());
+        buyer.amount = Some(42400);
+        let params = FapiaoApplicationParams::new(
+            "WITH_WECHATPAY",
+            "4200000444201910177461284488",
+            buyer,
+            vec![FapiaoInformationParams {
+                fapiao_id: "20200701123456".to_string(),
+                total_amount: 42400,
+                need_list: None,
+                remark: None,
+                items: vec![FapiaoItem {
+                    tax_code: "3010101020203000000".to_string(),
+                    goods_name: None,
+                    specification: None,
+                    unit: None,
+                    quantity: 100000000,
+                    total_amount: 42400,
+                    tax_rate: Some(600),
+                    tax_prefer_mark: None,
+                    discount: None,
+                }],
+            }],
+        );
+        let response = wechat_pay.create_fapiao(params).await.unwrap();
+        assert!(response.is_success());
+        let body: serde_json::Value =
+            serde_json::from_slice(&run!(mock_server::requests(&server))[0].body).unwrap();
+        assert_eq!(body["buyer_information"]["type"], "INDIVIDUAL");
+        assert!(body.get("appid").is_none());
+        assert!(body.get("notify_url").is_none());
+
+        let response = wechat_pay
+            .get_fapiao_application("4200000444201910177461284488", Some("20200701123456"))
+            .await
+            .unwrap();
+        let fapiao = &response.ok().unwrap().fapiao_information[0];
+        assert_eq!(fapiao.status, "ISSUED");
+        assert_eq!(
+            fapiao.blue_fapiao.as_ref().unwrap().fapiao_number,
+            "12897794"
+        );
+        assert_eq!(
+            path_and_query(&run!(mock_server::requests(&server))[1]),
+            "/v3/new-tax-control-fapiao/fapiao-applications/4200000444201910177461284488?fapiao_id=20200701123456"
+        );
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_profit_sharing_lifecycle() {
+        let server = run!(mock_server::start(|request: &Request| {
+            let state = if request.url.path().ends_with("/unfreeze") {
+                "FINISHED"
+            } else {
+                "PROCESSING"
+            };
+            ResponseTemplate::new(200).set_body_string(format!(
+                r#"{{"transaction_id":"4208450740201411110007820472","out_order_no":"P20150806125346","order_id":"3008450740201411110007820472","state":"{}","receivers":[{{"amount":100,"description":"分给商户A","type":"MERCHANT_ID","account":"86693852","result":"CLOSED","fail_reason":"ACCOUNT_ABNORMAL","detail_id":"36011111111111111111111","create_time":"2015-05-20T13:29:35+08:00","finish_time":"2015-05-20T13:29:35+08:00"}}]}}"#,
+                state
+            ))
+        }));
+        let wechat_pay = merchant_pay(&server.uri());
+        let params = ProfitSharingOrderParams::new(
+            "4208450740201411110007820472",
+            "P20150806125346",
+            vec![ProfitSharingReceiverParams {
+                receiver_type: "MERCHANT_ID".to_string(),
+                account: "86693852".to_string(),
+                name: None,
+                amount: 100,
+                description: "分给商户A".to_string(),
+            }],
+            false,
+        );
+        let response = wechat_pay
+            .create_profit_sharing_order(params)
+            .await
+            .unwrap();
+        let order = response.ok().unwrap();
+        assert_eq!(order.state, "PROCESSING");
+        let body: serde_json::Value =
+            serde_json::from_slice(&run!(mock_server::requests(&server))[0].body).unwrap();
+        assert_eq!(body["appid"], "wx0000000000000001");
+        assert_eq!(body["receivers"][0]["type"], "MERCHANT_ID");
+        assert!(body.get("mchid").is_none());
+        assert!(body.get("notify_url").is_none());
+
+        let response = wechat_pay
+            .query_profit_sharing_order("P20150806125346", "4208450740201411110007820472")
+            .await
+            .unwrap();
+        let receiver = &response.ok().unwrap().receivers[0];
+        assert_eq!(receiver.result, "CLOSED");
+        assert_eq!(receiver.fail_reason.as_deref(), Some("ACCOUNT_ABNORMAL"));
+        assert_eq!(run!(mock_server::requests(&server))[1].method, "GET");
+        assert_eq!(
+            path_and_query(&run!(mock_server::requests(&server))[1]),
+            "/v3/profitsharing/orders/P20150806125346?transaction_id=4208450740201411110007820472"
+        );
+
+        let params = ProfitSharingUnfreezeParams::new(
+            "4208450740201411110007820472",
+            "P20150806125346",
+            "解冻全部剩余资金",
+        );
+        let response = wechat_pay.unfreeze_profit_sharing(params).await.unwrap();
+        assert_eq!(response.ok().unwrap().state, "FINISHED");
+        let body: serde_json::Value =
+            serde_json::from_slice(&run!(mock_server::requests(&server))[2].body).unwrap();
+        assert!(body.get("appid").is_none());
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_profit_sharing_return() {
+        let server = run!(mock_server::start(|_: &Request| {
+            ResponseTemplate::new(200).set_body_string(
+                r#"{"order_id":"3008450740201411110007820472","out_order_no":"P20150806125346","out_return_no":"R20190516001","return_id":"3008450740201411110007820472","return_mchid":"86693852","amount":10,"description":"用户退款","result":"SUCCESS","create_time":"2015-05-20T13:29:35+08:00","finish_time":"2015-05-20T13:29:35+08:00"}"#,
+            )
+        }));
+        let wechat_pay = merchant_pay(&server.uri());
+        let params = ProfitSharingReturnParams::new(
+            "P20150806125346",
+            "R20190516001",
+            "86693852",
+            10,
+            "用户退款",
+        );
+        let response = wechat_pay.profit_sharing_return(params).await.unwrap();
+        assert_eq!(response.ok().unwrap().result, "SUCCESS");
+        let body: serde_json::Value =
+            serde_json::from_slice(&run!(mock_server::requests(&server))[0].body).unwrap();
+        assert_eq!(body["out_order_no"], "P20150806125346");
+        assert!(body.get("order_id").is_none());
+        assert!(body.get("appid").is_none());
+
+        let response = wechat_pay
+            .query_profit_sharing_return("R20190516001", "P20150806125346")
+            .await
+            .unwrap();
+        assert_eq!(response.ok().unwrap().amount, 10);
+        assert_eq!(
+            path_and_query(&run!(mock_server::requests(&server))[1]),
+            "/v3/profitsharing/return-orders/R20190516001?out_order_no=P20150806125346"
+        );
+
+        let mut params = ProfitSharingReturnParams::new("", "R20190516001", "86693852", 10, "");
+        params.out_order_no = None;
+        let error = wechat_pay.profit_sharing_return(params).await.unwrap_err();
+        assert!(matches!(error, PayError::InvalidParams(_)));
+        assert_eq!(run!(mock_server::requests(&server)).len(), 2);
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_profit_sharing_unsplit_amount() {
+        let server = run!(mock_server::start(|_: &Request| {
+            ResponseTemplate::new(200).set_body_string(
+                r#"{"transaction_id":"4208450740201411110007820472","unsplit_amount":1000}"#,
+            )
+        }));
+        let wechat_pay = test_pay(&server);
+        let response = wechat_pay
+            .query_profit_sharing_unsplit_amount("4208450740201411110007820472")
+            .await
+            .unwrap();
+        assert_eq!(response.ok().unwrap().unsplit_amount, 1000);
+        let request = &run!(mock_server::requests(&server))[0];
+        assert_eq!(request.method, "GET");
+        assert_eq!(
+            path_and_query(request),
+            "/v3/profitsharing/transactions/4208450740201411110007820472/amounts"
+        );
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_validate_credentials() {
+        let server = run!(mock_server::start(|request: &Request| {
+            if header(request, "authorization")
+                .unwrap()
+                .contains("mchid=\"1900000001\"")
+            {
+                ResponseTemplate::new(200).set_body_string(certificates_body("NEW"))
+            } else {
+                ResponseTemplate::new(401)
+                    .set_body_string(r#"{"code":"SIGN_ERROR","message":"签名错误"}"#)
+            }
+        }));
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "serial", "", "")
+                .unwrap()
+                .with_base_url(server.uri());
+        wechat_pay.validate_credentials().await.unwrap();
+        assert_eq!(
+            path_and_query(&run!(mock_server::requests(&server))[0]),
+            "/v3/certificates"
+        );
+
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000002", PRIVATE_KEY, "serial", "", "")
+                .unwrap()
+                .with_base_url(server.uri());
+        let error = wechat_pay.validate_credentials().await.unwrap_err();
+        assert!(matches!(error, PayError::AuthFailed(message) if message == "签名错误"));
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_response_too_large() {
+        let server = run!(mock_server::start(|_: &Request| {
+            ResponseTemplate::new(200).set_body_string(format!(
+                r#"{{"appid":"wx_appid","mchid":"1900000001","out_trade_no":"1243243","trade_state":"NOTPAY","trade_state_desc":"{}"}}"#,
+                "x".repeat(2048)
+            ))
+        }));
+        let wechat_pay = test_pay(&server).with_max_response_size(1024);
+        let error = wechat_pay
+            .query_order_by_out_trade_no("1243243")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, PayError::ResponseTooLarge(1024)));
+
+        let wechat_pay = wechat_pay.with_max_response_size(4096);
+        let response = wechat_pay
+            .query_order_by_out_trade_no("1243243")
+            .await
+            .unwrap();
+        assert_eq!(response.ok().unwrap().trade_state, "NOTPAY");
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_combine_order_query_and_close() {
+        let server = run!(mock_server::start(|request: &Request| {
+            match request.method.as_str() {
+            "POST" => ResponseTemplate::new(204),
+            _ => ResponseTemplate::new(200).set_body_string(
+                r#"{"combine_appid":"wxd678efh567hg6787","combine_mchid":"1900000109","combine_out_trade_no":"P20150806125346","sub_orders":[{"mchid":"1900000109","trade_type":"JSAPI","trade_state":"SUCCESS","bank_type":"CMC","attach":"深圳分店","success_time":"2015-05-20T13:29:35+08:00","transaction_id":"1009660380201506130728806387","out_trade_no":"20150806125346","amount":{"total_amount":10,"currency":"CNY","payer_amount":10,"payer_currency":"CNY"}},{"mchid":"1900000109","trade_state":"NOTPAY","out_trade_no":"20150806125347","amount":{"total_amount":20}}],"combine_payer_info":{"openid":"oUpF8uMuAJO_M2pxb1Q9zNjWeS6o"}}"#,
+            ),
+        }
+        }));
+        let wechat_pay =
+            WechatPay::try_new("wxd678efh567hg6787", "1900000109", PRIVATE_KEY, "", "", "")
+                .unwrap()
+                .with_base_url(server.uri());
+        let response = wechat_pay
+            .query_combine_order("P20150806125346")
+            .await
+            .unwrap();
+        let order = response.ok().unwrap();
+        let states = order
+            .sub_orders
+            .iter()
+            .map(|sub_order| sub_order.trade_state.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(states, vec!["SUCCESS", "NOTPAY"]);
+        assert_eq!(order.sub_orders[0].amount.payer_amount, Some(10));
+        assert_eq!(
+            path_and_query(&run!(mock_server::requests(&server))[0]),
+            "/v3/combine-transactions/out-trade-no/P20150806125346"
+        );
+
+        let response = wechat_pay
+            .close_combine_order(
+                "P20150806125346",
+                vec![CombineCloseSubOrder::new("1900000109", "20150806125347")],
+            )
+            .await
+            .unwrap();
+        assert!(response.is_success());
+        let request = &run!(mock_server::requests(&server))[1];
+        assert_eq!(
+            path_and_query(request),
+            "/v3/combine-transactions/out-trade-no/P20150806125346/close"
+        );
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["combine_appid"], "wxd678efh567hg6787");
+        assert_eq!(body["sub_orders"][0]["out_trade_no"], "20150806125347");
+        assert!(body["sub_orders"][0].get("sub_mchid").is_none());
+    }
+
+    fn coupon_stock_params() -> CouponStockParams {
+        let mut params = CouponStockParams::new(
+            "微信支付代金券批次",
+            "1900000001",
+            "2024-01-01T00:00:00+08:00",
+            "2024-01-31T23:59:59+08:00",
+            CouponStockUseRule {
+                max_coupons: 100,
+                max_amount: 5000,
+                max_amount_by_day: None,
+                max_coupons_per_user: 1,
+                natural_person_limit: false,
+                prevent_api_abuse: false,
+            },
+            CouponUseRule {
+                fixed_normal_coupon: FixedNormalCoupon {
+                    coupon_amount: 50,
+                    transaction_minimum: 100,
+                },
+                goods_tag: None,
+                available_merchants: vec!["1900000001".to_string()],
+            },
+            "89560002019101000121",
+        );
+        params.pattern_info = Some(CouponStockPatternInfo {
+            description: "微信支付营销代金券".to_string(),
+            ..Default::default()
+        });
+        params
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_upload_and_create_coupon_stock() {
+        let server = run!(mock_server::start(|request: &Request| {
+            match request.url.path() {
+                "/v3/marketing/favor/media/image-upload" => ResponseTemplate::new(200)
+                    .set_body_string(
+                        r#"{"media_url":"https://wxpaylogo.qpic.cn/wxpaylogo/xxxxx/0"}"#,
+                    ),
+                _ => ResponseTemplate::new(200).set_body_string(
+                    r#"{"stock_id":"9856000","create_time":"2015-05-20T13:29:35+08:00"}"#,
+                ),
+            }
+        }));
+        let wechat_pay = test_pay(&server);
+        let response = wechat_pay
+            .upload_and_create_coupon_stock("coupon.png", b"PNGDATA", coupon_stock_params())
+            .await
+            .unwrap();
+        assert_eq!(response.ok().unwrap().stock_id, "9856000");
+
+        let upload = &run!(mock_server::requests(&server))[0];
+        assert_eq!(
+            path_and_query(upload),
+            "/v3/marketing/favor/media/image-upload"
+        );
+        let content_type = header(upload, "Content-Type").unwrap();
+        assert!(content_type.starts_with("multipart/form-data; boundary="));
+        let sha256 = format!("{:x}", Sha256::digest(b"PNGDATA"));
+        let upload_body = String::from_utf8_lossy(&upload.body);
+        assert!(upload_body.contains(&format!(
+            r#"{{"filename":"coupon.png","sha256":"{}"}}"#,
+            sha256
+        )));
+        assert!(upload_body.contains("Content-Type: image/png\r\n\r\nPNGDATA\r\n"));
+
+        let create = &run!(mock_server::requests(&server))[1];
+        assert_eq!(path_and_query(create), "/v3/marketing/favor/coupon-stocks");
+        let body: serde_json::Value = serde_json::from_slice(&create.body).unwrap();
+        assert_eq!(
+            body["pattern_info"]["coupon_image"],
+            "https://wxpaylogo.qpic.cn/wxpaylogo/xxxxx/0"
+        );
+        assert_eq!(body["stock_type"], "NORMAL");
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_upload_and_create_coupon_stock_upload_error() {
+        let server = run!(mock_server::start(|_: &Request| ResponseTemplate::new(400)
+            .set_body_string(r#"{"code":"PARAM_ERROR","message":"图片格式错误"}"#)));
+        let wechat_pay = test_pay(&server);
+        let response = wechat_pay
+            .upload_and_create_coupon_stock("coupon.gif", b"GIF", coupon_stock_params())
+            .await
+            .unwrap();
+        assert_eq!(response.err().unwrap().code.as_deref(), Some("PARAM_ERROR"));
+        assert_eq!(run!(mock_server::requests(&server)).len(), 1);
+
+        let mut params = coupon_stock_params();
+        params.pattern_info = None;
+        let result = wechat_pay
+            .upload_and_create_coupon_stock("coupon.png", b"PNGDATA", params)
+            .await;
+        assert!(matches!(result, Err(PayError::InvalidParams(_))));
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_query_user_coupon() {
+        let server = run!(mock_server::start(|_: &Request| {
+            ResponseTemplate::new(200).set_body_string(
+                r#"{"stock_creator_mchid":"9800064","stock_id":"9865888","coupon_id":"98674556","coupon_name":"微信支付代金券","status":"USED","description":"微信支付营销","create_time":"2015-05-20T13:29:35+08:00","coupon_type":"NORMAL","no_cash":false,"available_begin_time":"2015-05-20T13:29:35+08:00","available_end_time":"2015-05-25T13:29:35+08:00","singleitem":false,"normal_coupon_information":{"coupon_amount":100,"transaction_minimum":100},"consume_information":{"consume_time":"2015-05-21T13:29:35+08:00","consume_mchid":"9856081","transaction_id":"4200000000000000000"}}"#,
+            )
+        }));
+        let wechat_pay = test_pay(&server);
+        let response = wechat_pay
+            .query_user_coupon("oHkLxt_htg84TUEbzvlMwQzVDBqo", "98674556")
+            .await
+            .unwrap();
+        let coupon = response.ok().unwrap();
+        assert_eq!(coupon.status, "USED");
+        assert_eq!(
+            coupon
+                .normal_coupon_information
+                .as_ref()
+                .unwrap()
+                .coupon_amount,
+            100
+        );
+        assert_eq!(
+            coupon.consume_information.as_ref().unwrap().transaction_id,
+            "4200000000000000000"
+        );
+
+        let request = &run!(mock_server::requests(&server))[0];
+        assert_eq!(request.method, "GET");
+        assert_eq!(
+            path_and_query(request),
+            "/v3/marketing/favor/users/oHkLxt_htg84TUEbzvlMwQzVDBqo/coupons/98674556?appid=wx_appid"
+        );
+    }
+
+    fn payscore_params() -> crate::model::PayScoreParams {
+        crate::model::PayScoreParams::new(
+            "500001",
+            "1234323JKHDFE1243252",
+            "某某酒店",
+            crate::model::PayScoreTimeRange {
+                start_time: "OnAccept".to_string(),
+                ..Default::default()
+            },
+            crate::model::PayScoreRiskFund::new("DEPOSIT", 10000),
+        )
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_payscore_order_lifecycle() {
+        let server = run!(mock_server::start(|request: &Request| {
+            if request.method == "POST" && request.url.path().ends_with("/complete") {
+                ResponseTemplate::new(200).set_body_string(
+                    r#"{"appid":"wx_appid","mchid":"1900000001","out_order_no":"1234323JKHDFE1243252","service_id":"500001","state":"DOING","state_description":"MCH_COMPLETE","total_amount":3900,"post_payments":[{"name":"就餐费用","amount":4000}],"post_discounts":[{"name":"满减","description":"满40减1","amount":100}],"order_id":"15646546545165651651","need_collection":true}"#,
+                )
+            } else if request.method == "POST" {
+                ResponseTemplate::new(200).set_body_string(
+                    r#"{"appid":"wx_appid","mchid":"1900000001","out_order_no":"1234323JKHDFE1243252","service_id":"500001","service_introduction":"某某酒店","state":"CREATED","risk_fund":{"name":"DEPOSIT","amount":10000},"time_range":{"start_time":"OnAccept"},"notify_url":"https://mydomain.com/notify","order_id":"15646546545165651651","package":"DJIOSQPYWDxsjdldeskdfnasdfasdf"}"#,
+                )
+            } else {
+                ResponseTemplate::new(200).set_body_string(
+                    r#"{"appid":"wx_appid","mchid":"1900000001","out_order_no":"1234323JKHDFE1243252","service_id":"500001","state":"DONE","state_description":"USER_PAID","total_amount":3900,"collection":{"state":"USER_PAID","total_amount":3900,"paying_amount":0,"paid_amount":3900,"details":[{"seq":1,"amount":3900,"paid_type":"NEWTON","paid_time":"20091225091210","transaction_id":"4200000000000000000"}]}}"#,
+                )
+            }
+        }));
+        let wechat_pay = WechatPay::try_new(
+            "wx_appid",
+            "1900000001",
+            PRIVATE_KEY,
+            "",
+            "",
+            "https://mydomain.com/notify",
+        )
+        .unwrap()
+        .with_base_url(server.uri());
+
+        let response = wechat_pay
+            .create_payscore_order(payscore_params())
+            .await
+            .unwrap();
+        let order = response.ok().unwrap();
+        assert_eq!(order.state, crate::model::PayScoreState::Created);
+        assert_eq!(
+            order.package.as_deref(),
+            Some("DJIOSQPYWDxsjdldeskdfnasdfasdf")
+        );
+        let create = &run!(mock_server::requests(&server))[0];
+        assert_eq!(path_and_query(create), "/v3/payscore/serviceorder");
+        let body: serde_json::Value = serde_json::from_slice(&create.body).unwrap();
+        assert_eq!(body["appid"], "wx_appid");
+        assert_eq!(body["notify_url"], "https://mydomain.com/notify");
+        assert_eq!(body["risk_fund"]["amount"], 10000);
+
+        let params = crate::model::PayScoreCompleteParams::new(
+            "500001",
+            vec![crate::model::PayScorePostPayment::new("就餐费用", 4000)],
+            vec![crate::model::PayScorePostDiscount {
+                name: "满减".to_string(),
+                description: "满40减1".to_string(),
+                amount: Some(100),
+                count: None,
+            }],
+        );
+        let response = wechat_pay
+            .complete_payscore_order("1234323JKHDFE1243252", params)
+            .await
+            .unwrap();
+        assert_eq!(response.ok().unwrap().total_amount, Some(3900));
+        let complete = &run!(mock_server::requests(&server))[1];
+        assert_eq!(
+            path_and_query(complete),
+            "/v3/payscore/serviceorder/1234323JKHDFE1243252/complete"
+        );
+        let body: serde_json::Value = serde_json::from_slice(&complete.body).unwrap();
+        assert_eq!(body["total_amount"], 3900);
+        assert_eq!(body["appid"], "wx_appid");
+
+        let response = wechat_pay
+            .query_payscore_order("500001", "1234323JKHDFE1243252")
+            .await
+            .unwrap();
+        let order = response.ok().unwrap();
+        assert!(order.state.is_final());
+        assert_eq!(order.collection.as_ref().unwrap().paid_amount, Some(3900));
+        assert_eq!(
+            path_and_query(&run!(mock_server::requests(&server))[2]),
+            "/v3/payscore/serviceorder?service_id=500001&out_order_no=1234323JKHDFE1243252&appid=wx_appid"
+        );
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_create_payscore_order_requires_notify_url() {
+        let server = run!(mock_server::start(
+            |_: &Request| ResponseTemplate::new(200).set_body_string("{}")
+        ));
+        let wechat_pay = test_pay(&server);
+        let result = wechat_pay.create_payscore_order(payscore_params()).await;
+        assert!(matches!(result, Err(PayError::InvalidParams(_))));
+        assert!(run!(mock_server::requests(&server)).is_empty());
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_modify_payscore_order() {
+        let server = run!(mock_server::start(|_: &Request| {
+            ResponseTemplate::new(200).set_body_string(
+                r#"{"appid":"wx_appid","mchid":"1900000001","out_order_no":"1234323JKHDFE1243252","service_id":"500001","state":"DOING","state_description":"MCH_COMPLETE","total_amount":3500,"post_payments":[{"name":"就餐费用","amount":3500}],"order_id":"15646546545165651651","need_collection":true}"#,
+            )
+        }));
+        let wechat_pay = test_pay(&server);
+        let params = crate::model::PayScoreModifyParams::new(
+            "500001",
+            vec![crate::model::PayScorePostPayment::new("就餐费用", 3500)],
+            vec![],
+            "用户投诉",
+        );
+        let response = wechat_pay
+            .modify_payscore_order("1234323JKHDFE1243252", params)
+            .await
+            .unwrap();
+        assert_eq!(response.ok().unwrap().total_amount, Some(3500));
+        let request = &run!(mock_server::requests(&server))[0];
+        assert_eq!(
+            path_and_query(request),
+            "/v3/payscore/serviceorder/1234323JKHDFE1243252/modify"
+        );
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "appid": "wx_appid",
+                "service_id": "500001",
+                "post_payments": [{"name": "就餐费用", "amount": 3500}],
+                "total_amount": 3500,
+                "reason": "用户投诉",
+            })
+        );
+
+        // 缺少修改原因时不发送请求
+        let params = crate::model::PayScoreModifyParams::new(
+            "500001",
+            vec![crate::model::PayScorePostPayment::new("就餐费用", 3500)],
+            vec![],
+            "",
+        );
+        let result = wechat_pay
+            .modify_payscore_order("1234323JKHDFE1243252", params)
+            .await;
+        assert!(matches!(result, Err(PayError::InvalidParams(_))));
+        assert_eq!(run!(mock_server::requests(&server)).len(), 1);
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_payscore_permission() {
+        let server = run!(mock_server::start(|request: &Request| {
+            if request.url.path().ends_with("/terminate") {
+                ResponseTemplate::new(204)
+            } else if request.method == "POST" {
+                ResponseTemplate::new(200)
+                    .set_body_string(r#"{"apply_permissions_token":"1230000109"}"#)
+            } else {
+                ResponseTemplate::new(200).set_body_string(
+                    r#"{"service_id":"500001","appid":"wx_appid","mchid":"1900000001","authorization_code":"4534323JKHDFE1243252","authorization_state":"AVAILABLE","notify_url":"https://mydomain.com/notify","authorization_success_time":"2015-05-20T13:29:35+08:00","openid":"oUpF8uMuAJO_M2pxb1Q9zNjWeS6o"}"#,
+                )
+            }
+        }));
+        let wechat_pay = WechatPay::try_new(
+            "wx_appid",
+            "1900000001",
+            PRIVATE_KEY,
+            "",
+            "",
+            "https://mydomain.com/notify",
+        )
+        .unwrap()
+        .with_base_url(server.uri());
+
+        let params = crate::model::PayScorePermissionParams::new("500001", "4534323JKHDFE1243252");
+        let response = wechat_pay.payscore_permission_apply(params).await.unwrap();
+        let launch = response.ok().unwrap().mini_program_launch();
+        assert_eq!(
+            serde_json::to_value(&launch).unwrap(),
+            serde_json::json!({
+                "appId": "wxd8f3793ea3b935b8",
+                "path": "pages/use/enable",
+                "extraData": {"apply_permissions_token": "1230000109"}
+            })
+        );
+        let apply = &run!(mock_server::requests(&server))[0];
+        assert_eq!(path_and_query(apply), "/v3/payscore/permissions");
+        let body: serde_json::Value = serde_json::from_slice(&apply.body).unwrap();
+        assert_eq!(body["appid"], "wx_appid");
+        assert_eq!(body["notify_url"], "https://mydomain.com/notify");
+
+        let response = wechat_pay
+            .payscore_permission_query("500001", "4534323JKHDFE1243252")
+            .await
+            .unwrap();
+        assert_eq!(
+            response.ok().unwrap().authorization_state,
+            crate::model::PayScoreAuthorizationState::Available
+        );
+        assert_eq!(
+            path_and_query(&run!(mock_server::requests(&server))[1]),
+            "/v3/payscore/permissions/authorization-code/4534323JKHDFE1243252?service_id=500001"
+        );
+
+        let response = wechat_pay
+            .payscore_permission_terminate("500001", "4534323JKHDFE1243252", "用户注销")
+            .await
+            .unwrap();
+        assert!(response.is_success());
+        let terminate = &run!(mock_server::requests(&server))[2];
+        assert_eq!(
+            path_and_query(terminate),
+            "/v3/payscore/permissions/authorization-code/4534323JKHDFE1243252/terminate"
+        );
+        let body: serde_json::Value = serde_json::from_slice(&terminate.body).unwrap();
+        assert_eq!(body["reason"], "用户注销");
+    }
+
+    /// 服务端按收到的method、path(含查询参数)和body，用商户公钥校验Authorization中的签名
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_signed_url_matches_sent_url() {
+        let server = run!(mock_server::start(
+            |_: &Request| ResponseTemplate::new(200).set_body_string("{}")
+        ));
+        let wechat_pay = test_pay(&server);
+        let _ = wechat_pay
+            .query_order_by_out_trade_no("1217752501201407033233368018")
+            .await;
+        // 查询参数中的中文和空格发送时会被编码，签名需使用编码后的路径
+        let _ = wechat_pay
+            .get_pay::<serde_json::Value>(
+                "/v3/merchant-service/complaints-v2?begin_date=2024-01-01&keyword=退款 申请",
+            )
+            .await;
+        let _ = wechat_pay
+            .close_combine_order("P20150806125346", vec![])
+            .await;
+
+        let requests = run!(mock_server::requests(&server));
+        assert_eq!(requests.len(), 3);
+        assert_eq!(
+            path_and_query(&requests[0]),
+            "/v3/pay/transactions/out-trade-no/1217752501201407033233368018?mchid=1900000001"
+        );
+        assert!(
+            path_and_query(&requests[1])
+                .contains("keyword=%E9%80%80%E6%AC%BE%20%E7%94%B3%E8%AF%B7")
+        );
+        for request in &requests {
+            assert!(
+                SignedBy::merchant().matches(request),
+                "{}",
+                path_and_query(request)
+            );
+        }
+
+        // 签名后再追加查询参数，服务端按收到的URL验签失败
+        let mut tampered = requests[0].clone();
+        tampered.url.query_pairs_mut().append_pair("limit", "10");
+        assert!(!SignedBy::merchant().matches(&tampered));
+    }
+
+    /// 查询参数的值含&、#、+、空格时按表单编码，不会拆出新的参数，签名与发送的URL一致
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_query_values_are_encoded() {
+        let server = run!(mock_server::start(
+            |_: &Request| ResponseTemplate::new(200).set_body_string("{}")
+        ));
+        let wechat_pay = test_pay(&server);
+        let _ = wechat_pay
+            .query_payscore_order("500001", "order&appid=evil #1+2")
+            .await;
+        let _ = wechat_pay.apply_trade_bill("2024-05-01&bill_type=X").await;
+
+        let requests = run!(mock_server::requests(&server));
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            path_and_query(&requests[0]),
+            "/v3/payscore/serviceorder?service_id=500001&out_order_no=order%26appid%3Devil+%231%2B2&appid=wx_appid"
+        );
+        assert_eq!(
+            path_and_query(&requests[1]),
+            "/v3/bill/tradebill?bill_date=2024-05-01%26bill_type%3DX&bill_type=ALL"
+        );
+        for request in &requests {
+            assert!(
+                SignedBy::merchant().matches(request),
+                "{}",
+                path_and_query(request)
+            );
+        }
+    }
+
+    fn h5_params() -> crate::model::H5Params {
+        crate::model::H5Params::new(
+            "测试支付1分",
+            "1243243",
+            1.into(),
+            crate::model::H5SceneInfo::new("183.6.105.1", "我的测试网站", "https://mydomain.com"),
+        )
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_h5_pay_and_resolve() {
+        let server = run!(mock_server::start(|request: &Request| {
+            ResponseTemplate::new(200).set_body_string(format!(
+                r#"{{"h5_url":"http://{}/cgi-bin/mmpayweb-bin/checkmweb?prepay_id=wx2916263004719461949c84457c735b0000"}}"#,
+                header(request, "Host").unwrap_or_default()
+            ))
+        }));
+        // 中间页不带商户签名
+        run!(
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(
+                    "<html>\n<script>\nvar url=\"weixin://wap/pay?prepayid%3Dwx2916263004719461949c84457c735b0000&package=2150917749\";\n</script>\n</html>",
+                ))
+                .mount(&server)
+        );
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "", "", NOTIFY_URL)
+                .unwrap()
+                .with_base_url(server.uri());
+        let info = wechat_pay
+            .h5_pay_and_resolve(h5_params(), "https://mydomain.com")
+            .await
+            .unwrap();
+        assert!(
+            info.h5_url
+                .ends_with("prepay_id=wx2916263004719461949c84457c735b0000")
+        );
+        assert_eq!(
+            info.weixin_url,
+            "weixin://wap/pay?prepayid%3Dwx2916263004719461949c84457c735b0000&package=2150917749"
+        );
+        let page = &run!(mock_server::requests(&server))[1];
+        assert_eq!(header(page, "Referer"), Some("https://mydomain.com"));
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_h5_pay_and_resolve_not_found() {
+        let server = run!(mock_server::start(|request: &Request| {
+            ResponseTemplate::new(200).set_body_string(format!(
+                r#"{{"h5_url":"http://{}/checkmweb"}}"#,
+                header(request, "Host").unwrap_or_default()
+            ))
+        }));
+        run!(
+            Mock::given(method("GET"))
+                .respond_with(ResponseTemplate::new(200).set_body_string("<html>新版页面</html>"))
+                .mount(&server)
+        );
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "", "", NOTIFY_URL)
+                .unwrap()
+                .with_base_url(server.uri());
+        let error = wechat_pay
+            .h5_pay_and_resolve(h5_params(), "https://mydomain.com")
+            .await
+            .unwrap_err();
+        let expected = format!("{}/checkmweb", server.uri());
+        assert!(matches!(&error, PayError::WeixinLaunchUrlNotFound(h5_url) if *h5_url == expected));
+        assert!(
+            error
+                .to_string()
+                .contains("page structure may have changed")
+        );
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_h5_pay_and_resolve_errors() {
+        let server = run!(mock_server::start(
+            ResponseTemplate::new(200).set_body_string("{}")
+        ));
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "", "", NOTIFY_URL)
+                .unwrap()
+                .with_base_url(server.uri());
+        let error = wechat_pay
+            .h5_pay_and_resolve(h5_params(), "https://mydomain.com")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, PayError::WechatError(message) if message == "h5_pay response missing h5_url")
+        );
+
+        // referer不是合法的header值时返回错误，不会panic
+        let error = wechat_pay
+            .get_weixin(
+                format!("{}/checkmweb", server.uri()).as_str(),
+                "https://mydomain.com\n",
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, PayError::InvalidParams(_)));
+        assert_eq!(run!(mock_server::hits(&server, "/checkmweb")), 0);
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_response_parse_error() {
+        let server = run!(mock_server::start(|_: &Request| ResponseTemplate::new(502)
+            .set_body_string("<html>502 Bad Gateway</html>")));
+        let wechat_pay = test_pay(&server);
+        let error = wechat_pay
+            .query_order_by_out_trade_no("1243243")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(error, PayError::ResponseParseError { status: 502, body, .. } if body == "<html>502 Bad Gateway</html>")
+        );
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_with_client() {
+        let server = run!(mock_server::start(|_: &Request| {
+            ResponseTemplate::new(200).set_body_string(
+                r#"{"appid":"wx_appid","mchid":"1900000001","out_trade_no":"1243243","trade_state":"SUCCESS","trade_state_desc":"支付成功"}"#,
+            )
+        }));
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-proxy-tag", "shop".parse().unwrap());
+        let client = crate::client::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap();
+        let wechat_pay = test_pay(&server)
+            .with_client(client)
+            .with_pool_max_idle_per_host(1)
+            .unwrap();
+        wechat_pay
+            .query_order_by_out_trade_no("1243243")
+            .await
+            .unwrap();
+        let request = &run!(mock_server::requests(&server))[0];
+        // 外部客户端的配置保留，签名头由SDK添加
+        assert_eq!(header(request, "x-proxy-tag"), Some("shop"));
+        assert!(header(request, "Authorization").is_some());
+    }
+
+    #[cfg(any(feature = "native-tls", feature = "rustls"))]
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_with_client_cert() {
+        let cert_pem = include_str!("../testdata/platform_cert.pem");
+        let key_pem = include_str!("../testdata/platform_key.pem");
+        let server =
+            run!(mock_server::start(|_: &Request| ResponseTemplate::new(200)
+                .set_body_string(r#"{"trade_state":"NOTPAY"}"#)));
+        let wechat_pay = test_pay(&server)
+            .with_client_cert(cert_pem, key_pem)
+            .unwrap();
+        assert!(wechat_pay.http_config.identity.is_some());
+        // 重建连接池时保留客户端证书
+        let wechat_pay = wechat_pay.with_pool_max_idle_per_host(1).unwrap();
+        assert!(wechat_pay.http_config.identity.is_some());
+        let _ = wechat_pay.query_order_by_out_trade_no("1243243").await;
+        assert_eq!(run!(mock_server::requests(&server)).len(), 1);
+
+        let result = WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "", "", "")
+            .unwrap()
+            .with_client_cert("not a cert", key_pem);
+        assert!(matches!(result, Err(PayError::RequestError(_))));
+        let result = WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "", "", "")
+            .unwrap()
+            .with_client(crate::client::Client::new())
+            .with_client_cert(cert_pem, key_pem);
+        assert!(matches!(result, Err(PayError::InvalidParams(_))));
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_transfer_bills() {
+        let server = run!(mock_server::start(|_: &Request| {
+            ResponseTemplate::new(200).set_body_string(
+                r#"{"out_bill_no":"plfk2020042013","transfer_bill_no":"1330000071100999991182020050700019480001","create_time":"2015-05-20T13:29:35+08:00","state":"WAIT_USER_CONFIRM","package_info":"affffddafdfafddffda=="}"#,
+            )
+        }));
+        let wechat_pay = test_pay(&server);
+        let params = TransferBillsParams::new(
+            "plfk2020042013",
+            "1000",
+            "o-MYE42l80oelYMDE34nYD456Xoy",
+            400000,
+            "新会员开通有礼",
+            TransferSceneReportInfo::cash_marketing("新会员有礼", "注册会员抽奖一等奖"),
+        );
+        let response = wechat_pay.transfer_bills(params).await.unwrap();
+        let bill = response.ok().unwrap();
+        assert_eq!(bill.state, TransferBillState::WaitUserConfirm);
+        assert_eq!(bill.package_info.as_deref(), Some("affffddafdfafddffda=="));
+        let data = wechat_pay.merchant_transfer_data(bill).unwrap();
+        assert_eq!(data.mch_id, "1900000001");
+        assert_eq!(data.app_id, "wx_appid");
+        assert_eq!(data.package, "affffddafdfafddffda==");
+        let request = &run!(mock_server::requests(&server))[0];
+        assert_eq!(
+            path_and_query(request),
+            "/v3/fund-app/mch-transfer/transfer-bills"
+        );
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["appid"], "wx_appid");
+        assert_eq!(body["transfer_amount"], 400000);
+        assert!(body.get("mchid").is_none());
+
+        // 报备信息不符时不发送请求
+        let params = TransferBillsParams::new(
+            "plfk2020042014",
+            "1000",
+            "o-MYE42l80oelYMDE34nYD456Xoy",
+            400000,
+            "新会员开通有礼",
+            vec![],
+        );
+        let error = wechat_pay.transfer_bills(params).await.unwrap_err();
+        assert!(matches!(error, PayError::InvalidParams(_)));
+        assert_eq!(run!(mock_server::requests(&server)).len(), 1);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_deadline() {
+        use std::time::Duration;
+        // 只接受连接不返回应答的服务
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut connections = vec![];
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+        let wechat_pay = WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "", "", "")
+            .unwrap()
+            .with_base_url(format!("http://{}", address));
+        let result = wechat_pay
+            .deadline(
+                Duration::from_millis(200),
+                wechat_pay.query_order_by_out_trade_no("1243243"),
+            )
+            .await;
+        assert!(
+            matches!(result, Err(PayError::Timeout(timeout)) if timeout == Duration::from_millis(200))
+        );
+
+        let server = run!(mock_server::start(|_: &Request| {
+            ResponseTemplate::new(200).set_body_string(
+                r#"{"appid":"wx_appid","mchid":"1900000001","out_trade_no":"1243243","transaction_id":"4200001","trade_state":"NOTPAY","trade_state_desc":"订单未支付"}"#,
+            )
+        }));
+        let wechat_pay = wechat_pay.with_base_url(server.uri());
+        let result = wechat_pay
+            .deadline(
+                Duration::from_secs(5),
+                wechat_pay.query_order_by_out_trade_no("1243243"),
+            )
+            .await;
+        assert!(result.unwrap().is_success());
+        assert_eq!(run!(mock_server::requests(&server)).len(), 1);
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_request_id() {
+        let server = run!(mock_server::start(|request: &Request| {
+            let (status, body) = if request
+                .url
+                .path()
+                .starts_with("/v3/pay/transactions/native")
+            {
+                (
+                    200,
+                    r#"{"code_url":"weixin://wxpay/bizpayurl?pr=p4lpSuKzz"}"#,
+                )
+            } else if request
+                .url
+                .path()
+                .starts_with("/v3/profitsharing/transactions/")
+            {
+                (
+                    200,
+                    r#"{"transaction_id":"4208450740201411110007820472","unsplit_amount":1000}"#,
+                )
+            } else {
+                (404, r#"{"code":"ORDER_NOT_EXIST","message":"订单不存在"}"#)
+            };
+            ResponseTemplate::new(status)
+                .insert_header("Request-ID", "08F78BB5AF0610D302189F8B2D2EA2E8B7E60C9")
+                .set_body_string(body)
+        }));
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "", "", NOTIFY_URL)
+                .unwrap()
+                .with_base_url(server.uri());
+        let params = crate::model::NativeParams::new("测试支付1分", "1243243", 1.into());
+        let response = wechat_pay.native_pay(params).await.unwrap();
+        assert_eq!(
+            response.request_id(),
+            Some("08F78BB5AF0610D302189F8B2D2EA2E8B7E60C9")
+        );
+        let response = wechat_pay
+            .query_order_by_out_trade_no("1243243")
+            .await
+            .unwrap();
+        assert_eq!(
+            response.request_id(),
+            Some("08F78BB5AF0610D302189F8B2D2EA2E8B7E60C9")
+        );
+        let error = PayError::from(response.err().unwrap().clone());
+        assert_eq!(
+            error.to_string(),
+            "pay error: ORDER_NOT_EXIST: 订单不存在 (request_id: 08F78BB5AF0610D302189F8B2D2EA2E8B7E60C9)"
+        );
+        assert_eq!(
+            error.request_id(),
+            Some("08F78BB5AF0610D302189F8B2D2EA2E8B7E60C9")
+        );
+        // 所有接口的应答都保留Request-ID
+        let response = wechat_pay
+            .query_profit_sharing_unsplit_amount("4208450740201411110007820472")
+            .await
+            .unwrap();
+        assert_eq!(
+            response.ok().and_then(|amount| amount.request_id()),
+            Some("08F78BB5AF0610D302189F8B2D2EA2E8B7E60C9")
+        );
+    }
+
+    /// 十六进制的文件摘要，与账单、回单应答中的hash_value格式一致
+    fn hex_digest<D: Digest>(content: &[u8]) -> String {
+        D::digest(content)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// 以apply_path开头的申请账单请求返回SHA1和指向本服务的download_url，其他请求返回账单内容
+    async fn bill_server(apply_path: &'static str, content: &'static str) -> MockServer {
+        let hash_value = hex_digest::<sha1::Sha1>(content.as_bytes());
+        mock_server::start(move |request: &Request| {
+            if request.url.path().starts_with(apply_path) {
+                ResponseTemplate::new(200).set_body_string(format!(
+                    r#"{{"hash_type":"SHA1","hash_value":"{}","download_url":"http://{}/v3/billdownload/file?token=xxx"}}"#,
+                    hash_value,
+                    header(request, "host").unwrap_or_default()
+                ))
+            } else {
+                ResponseTemplate::new(200).set_body_string(content)
+            }
+        })
+        .await
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_download_transfer_receipt() {
+        let content = "%PDF-1.4 receipt";
+        let hash_value = hex_digest::<Sha256>(content.as_bytes());
+        let server = run!(mock_server::start(move |request: &Request| {
+            if request
+                .url
+                .path()
+                .starts_with("/v3/transferdownload/signfile")
+            {
+                ResponseTemplate::new(200).set_body_string(content)
+            } else {
+                ResponseTemplate::new(404)
+                    .set_body_string(r#"{"code":"RESOURCE_NOT_EXISTS","message":"文件不存在"}"#)
+            }
+        }));
+        let wechat_pay = test_pay(&server);
+        let mut receipt: crate::response::TransferReceiptResponse =
+            serde_json::from_value(serde_json::json!({
+                "state": "FINISHED",
+                "hash_type": "SHA256",
+                "hash_value": hash_value,
+                "download_url": format!("{}/v3/transferdownload/signfile?token=xxx", server.uri()),
+            }))
+            .unwrap();
+        let bytes = wechat_pay
+            .download_transfer_receipt(&receipt)
+            .await
+            .unwrap();
+        assert_eq!(bytes, content.as_bytes());
+        let request = &run!(mock_server::requests(&server))[0];
+        assert_eq!(
+            path_and_query(request),
+            "/v3/transferdownload/signfile?token=xxx"
+        );
+        assert!(SignedBy::merchant().matches(request));
+
+        // hash不一致
+        receipt.hash_value = Some("00".to_string());
+        let result = wechat_pay.download_transfer_receipt(&receipt).await;
+        assert!(matches!(result, Err(PayError::VerifyError(_))));
+
+        // 其他域名的下载地址不签名、不发送
+        let sent = run!(mock_server::requests(&server)).len();
+        for download_url in [
+            "https://evil.example.com/v3/billdownload/file?token=xxx",
+            "http://api.mch.weixin.qq.com/v3/billdownload/file?token=xxx",
+        ] {
+            let result = wechat_pay.download(download_url).await;
+            assert!(
+                matches!(&result, Err(PayError::InvalidParams(message)) if message.contains("not allowed")),
+                "{}",
+                download_url
+            );
+        }
+        assert_eq!(run!(mock_server::requests(&server)).len(), sent);
+
+        // 下载失败时返回微信的错误信息
+        let result = wechat_pay.download("/v3/billdownload/file?token=xxx").await;
+        assert!(matches!(
+            result,
+            Err(PayError::Api { status: Some(404), ref code, .. }) if code == "RESOURCE_NOT_EXISTS"
+        ));
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_query_order() {
+        let server = run!(mock_server::start(|_: &Request| {
+            ResponseTemplate::new(200).set_body_string(
+                r#"{"appid":"wx_appid","mchid":"1900000001","out_trade_no":"1243243","transaction_id":"4200001","trade_state":"SUCCESS","trade_state_desc":"支付成功"}"#,
+            )
+        }));
+        let wechat_pay = test_pay(&server);
+        let response = wechat_pay
+            .query_order(OrderQuery::out_trade_no(
+                &TradeNo::try_from("1243243").unwrap(),
+            ))
+            .await
+            .unwrap();
+        assert!(response.is_success());
+        let trade_no = TradeNo::try_from("1243243").unwrap();
+        wechat_pay.query_order(trade_no).await.unwrap();
+        wechat_pay
+            .query_order(OrderQuery::TransactionId("4200001".to_string()))
+            .await
+            .unwrap();
+        let paths = run!(mock_server::requests(&server))
+            .into_iter()
+            .map(|request| path_and_query(&request))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                "/v3/pay/transactions/out-trade-no/1243243?mchid=1900000001",
+                "/v3/pay/transactions/out-trade-no/1243243?mchid=1900000001",
+                "/v3/pay/transactions/id/4200001?mchid=1900000001",
+            ]
+        );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_request_gzip() {
+        use std::io::Read;
+
+        let server = run!(mock_server::start(|_: &Request| ResponseTemplate::new(200)
+            .set_body_string(r#"{"code_url":"weixin://wxpay/1"}"#)));
+        let wechat_pay = WechatPay::try_new(
+            "wx_appid",
+            "1900000001",
+            PRIVATE_KEY,
+            "",
+            "",
+            "https://example.com/notify",
+        )
+        .unwrap()
+        .with_base_url(server.uri());
+        let params = NativeParams::new("测试支付1分", "1243243", 1.into());
+        wechat_pay.native_pay(params.clone()).await.unwrap();
+        let gzip_pay = wechat_pay.clone().with_request_gzip(16);
+        gzip_pay.native_pay(params.clone()).await.unwrap();
+        wechat_pay
+            .clone()
+            .with_request_gzip(1 << 20)
+            .native_pay(params)
+            .await
+            .unwrap();
+        let requests = run!(mock_server::requests(&server));
+        assert_eq!(header(&requests[0], "Content-Encoding"), None);
+        assert_eq!(header(&requests[2], "Content-Encoding"), None);
+        let request = &requests[1];
+        assert_eq!(header(request, "Content-Encoding"), Some("gzip"));
+        let mut body = vec![];
+        flate2::read::GzDecoder::new(request.body.as_slice())
+            .read_to_end(&mut body)
+            .unwrap();
+        assert_eq!(body, requests[0].body);
+        // 签名按压缩前的JSON计算，SignedBy解压后验签
+        assert!(SignedBy::merchant().matches(request));
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_close_order_idempotent() {
+        let server = run!(mock_server::start(|request: &Request| {
+            let code = match request.url.path() {
+                "/v3/pay/transactions/out-trade-no/closed/close" => "ORDER_CLOSED",
+                "/v3/pay/transactions/out-trade-no/paid01/close" => "ORDERPAID",
+                "/v3/pay/transactions/out-trade-no/missing/close" => "ORDERNOTEXIST",
+                "/v3/pay/transactions/out-trade-no/busy01/close" => "SYSTEM_ERROR",
+                "/v3/pay/transactions/out-trade-no/gateway/close" => {
+                    return ResponseTemplate::new(502);
+                }
+                _ => {
+                    return ResponseTemplate::new(204);
+                }
+            };
+            ResponseTemplate::new(400)
+                .set_body_string(format!(r#"{{"code":"{}","message":"error"}}"#, code))
+        }));
+        let wechat_pay = test_pay(&server);
+        let trade_no = |out_trade_no: &str| TradeNo::try_from(out_trade_no).unwrap();
+        let outcome = wechat_pay
+            .close_order_idempotent(&trade_no("1243243"))
+            .await
+            .unwrap();
+        assert_eq!(outcome, CloseOrderOutcome::Closed);
+        let outcome = wechat_pay
+            .close_order_idempotent(&trade_no("closed"))
+            .await
+            .unwrap();
+        assert_eq!(outcome, CloseOrderOutcome::AlreadyClosed);
+        let outcome = wechat_pay
+            .close_order_idempotent(&trade_no("paid01"))
+            .await
+            .unwrap();
+        assert_eq!(outcome, CloseOrderOutcome::Paid);
+        let outcome = wechat_pay
+            .close_order_idempotent(&trade_no("missing"))
+            .await
+            .unwrap();
+        assert_eq!(outcome, CloseOrderOutcome::NotExist);
+        let error = wechat_pay
+            .close_order_idempotent(&trade_no("busy01"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.wechat_error_code(),
+            Some(crate::error::WechatErrorCode::SystemError)
+        );
+        // 网关返回的空应答不能当作关单成功
+        let error = wechat_pay
+            .close_order_idempotent(&trade_no("gateway"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            PayError::ResponseParseError { status: 502, .. }
+        ));
+        let request = &run!(mock_server::requests(&server))[0];
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.body, br#"{"mchid":"1900000001"}"#);
+        assert!(SignedBy::merchant().matches(request));
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_list_refunds_for_order() {
+        let server = run!(mock_server::start(|request: &Request| {
+            let path = request.url.path();
+            let out_refund_no = path.rsplit('/').next().unwrap_or_default();
+            if out_refund_no == "R-missing" {
+                return ResponseTemplate::new(404)
+                    .set_body_string(r#"{"code":"RESOURCE_NOT_EXISTS","message":"退款单不存在"}"#);
+            }
+            let out_trade_no = if out_refund_no == "R-other" {
+                "9999999"
+            } else {
+                "1243243"
+            };
+            ResponseTemplate::new(200).set_body_string(format!(
+                r#"{{"refund_id":"5030000{out_refund_no}","out_refund_no":"{out_refund_no}","transaction_id":"4200001","out_trade_no":"{out_trade_no}","channel":"ORIGINAL","user_received_account":"支付用户零钱","create_time":"2024-05-01T10:00:00+08:00","status":"SUCCESS","funds_account":"AVAILABLE","amount":{{"total":100,"refund":10,"payer_total":100,"payer_refund":10,"settlement_refund":10,"settlement_total":100,"discount_refund":0,"currency":"CNY"}}}}"#
+            ))
+        }));
+        let wechat_pay = test_pay(&server);
+        let refunds = wechat_pay
+            .list_refunds_for_order("1243243", ["R1", "R2", "R3"])
+            .await
+            .unwrap();
+        let out_refund_nos = refunds
+            .iter()
+            .map(|refund| refund.out_refund_no.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(out_refund_nos, vec!["R1", "R2", "R3"]);
+        assert_eq!(run!(mock_server::requests(&server)).len(), 3);
+        assert!(run!(mock_server::requests(&server)).iter().all(|request| {
+            request.method == "GET"
+                && request
+                    .url
+                    .path()
+                    .starts_with("/v3/refund/domestic/refunds/R")
+        }));
+
+        let error = wechat_pay
+            .list_refunds_for_order("1243243", ["R1", "R-other"])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            PayError::PaymentMismatch { field: "out_trade_no", ref actual, .. } if actual == "9999999"
+        ));
+        let error = wechat_pay
+            .query_refunds(vec!["R1".to_string(), "R-missing".to_string()])
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.wechat_error_code(),
+            Some(crate::error::WechatErrorCode::ResourceNotExists)
+        );
+
+        let partner = wechat_pay.with_sub_mch_id("1900000109");
+        let refund = partner.query_refund("R1").await.unwrap();
+        assert!(refund.is_success());
+        assert_eq!(
+            path_and_query(run!(mock_server::requests(&server)).last().unwrap()),
+            "/v3/refund/domestic/refunds/R1?sub_mchid=1900000109"
+        );
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_error_body_with_200() {
+        let server = run!(mock_server::start(|_: &Request| {
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"code":"SYSTEM_ERROR","message":"系统繁忙"}"#)
+        }));
+        let wechat_pay = test_pay(&server);
+        let response = wechat_pay.query_refund("R1").await.unwrap();
+        assert!(!response.is_success());
+        assert!(response.err().unwrap().is_retryable());
+        let error = wechat_pay.query_refunds(["R1"]).await.unwrap_err();
+        assert_eq!(
+            error.wechat_error_code(),
+            Some(crate::error::WechatErrorCode::SystemError)
+        );
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_clock_skew_retry() {
+        use chrono::TimeZone;
+
+        // 微信支付服务器时间比本机快600秒，时间戳相差超过60秒时返回401
+        const SERVER_TIME: i64 = 1700000600;
+        let server = run!(mock_server::start(|request: &Request| {
+            let authorization = header(request, "Authorization").unwrap_or_default();
+            let timestamp = authorization
+                .split("timestamp=\"")
+                .nth(1)
+                .and_then(|value| value.split('"').next())
+                .and_then(|value| value.parse::<i64>().ok())
+                .unwrap_or_default();
+            if (SERVER_TIME - timestamp).abs() > 60 {
+                let date = chrono::Utc
+                    .timestamp_opt(SERVER_TIME, 0)
+                    .unwrap()
+                    .format("%a, %d %b %Y %H:%M:%S GMT")
+                    .to_string();
+                // 只有应答体说明时间戳超出范围时才按Date头校正重签
+                let message = if request.url.path().contains("sign-error") {
+                    "签名错误"
+                } else {
+                    "签名时间戳与当前时间相差过大"
+                };
+                let response = ResponseTemplate::new(401).set_body_string(format!(
+                    r#"{{"code":"SIGN_ERROR","message":"{}"}}"#,
+                    message
+                ));
+                // 模拟服务总会带上Date头，用无法解析的值模拟缺少服务端时间
+                return if request.url.path().contains("no-date") {
+                    response.insert_header("Date", "unknown")
+                } else {
+                    response.insert_header("Date", date)
+                };
+            }
+            if request.url.path().starts_with("/v3/billdownload") {
+                return ResponseTemplate::new(200).set_body_string("bill");
+            }
+            ResponseTemplate::new(200).set_body_string(
+                r#"{"appid":"wx_appid","mchid":"1900000001","out_trade_no":"1243243","transaction_id":"4200001","trade_state":"SUCCESS","trade_state_desc":"支付成功"}"#,
+            )
+        }));
+        let new_pay =
+            || test_pay(&server).with_clock(crate::clock::FixedClock::from_timestamp(1700000000));
+        let wechat_pay = new_pay();
+        // Date头无法解析时无法判断时钟偏差，不重试
+        let response = wechat_pay
+            .query_order_by_out_trade_no("no-date")
+            .await
+            .unwrap();
+        assert!(!response.is_success());
+        assert_eq!(run!(mock_server::requests(&server)).len(), 1);
+        assert_eq!(wechat_pay.clock_skew(), 0);
+
+        // 其他原因的401即使Date头显示时钟偏差也不重试，不记录偏差
+        let response = wechat_pay
+            .query_order_by_out_trade_no("sign-error")
+            .await
+            .unwrap();
+        assert!(!response.is_success());
+        assert_eq!(run!(mock_server::requests(&server)).len(), 2);
+        assert_eq!(wechat_pay.clock_skew(), 0);
+        let server_requests = run!(mock_server::requests(&server)).len();
+
+        let response = wechat_pay
+            .query_order_by_out_trade_no("1243243")
+            .await
+            .unwrap();
+        assert!(response.is_success());
+        assert_eq!(
+            run!(mock_server::requests(&server)).len(),
+            server_requests + 2
+        );
+        assert_eq!(wechat_pay.clock_skew(), 600);
+        assert_eq!(wechat_pay.now().timestamp(), SERVER_TIME);
+        assert!(
+            SignedBy::merchant()
+                .matches(&run!(mock_server::requests(&server))[server_requests + 1])
+        );
+
+        // 克隆出的实例共享校正结果，后续请求直接使用校正后的时间戳
+        let cloned = wechat_pay.clone();
+        let response = cloned.query_order_by_out_trade_no("1243243").await.unwrap();
+        assert!(response.is_success());
+        assert_eq!(
+            run!(mock_server::requests(&server)).len(),
+            server_requests + 3
+        );
+
+        // 下载文件、下载证书、校验商户配置同样校正后重签
+        let content = new_pay()
+            .download("/v3/billdownload/file?token=xxx")
+            .await
+            .unwrap();
+        assert_eq!(content, b"bill");
+        assert_eq!(
+            run!(mock_server::requests(&server)).len(),
+            server_requests + 5
+        );
+        new_pay().validate_credentials().await.unwrap();
+        assert_eq!(
+            run!(mock_server::requests(&server)).len(),
+            server_requests + 7
+        );
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_download_profit_sharing_bill() {
+        let content = "分账时间,分账发起方,分账方,分账接收方\r\n";
+        let server = run!(bill_server("/v3/profitsharing/bills", content));
+        let wechat_pay = test_pay(&server);
+        let (bill, bytes) = wechat_pay
+            .download_profit_sharing_bill("2024-05-01", None)
+            .await
+            .unwrap();
+        assert_eq!(bill.hash_type, "SHA1");
+        assert_eq!(bytes, content.as_bytes());
+        let requests = run!(mock_server::requests(&server));
+        assert_eq!(
+            path_and_query(&requests[0]),
+            "/v3/profitsharing/bills?bill_date=2024-05-01"
+        );
+        assert!(
+            requests
+                .iter()
+                .all(|request| SignedBy::merchant().matches(request))
+        );
+
+        let wechat_pay = wechat_pay.with_sub_mch_id("1900000109");
+        let response = wechat_pay
+            .apply_profit_sharing_bill("2024-05-01", Some(TarType::Gzip))
+            .await
+            .unwrap();
+        assert!(response.is_success());
+        assert_eq!(
+            path_and_query(&run!(mock_server::requests(&server))[2]),
+            "/v3/profitsharing/bills?bill_date=2024-05-01&sub_mchid=1900000109&tar_type=GZIP"
+        );
+    }
+
+    #[test]
+    fn test_request_builder() {
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "", "", "").unwrap();
+        let url = reqwest::Url::parse("https://api.mch.weixin.qq.com/v3/certificates").unwrap();
+        for method in [
+            HttpMethod::GET,
+            HttpMethod::POST,
+            HttpMethod::PUT,
+            HttpMethod::DELETE,
+            HttpMethod::PATCH,
+        ] {
+            let request = wechat_pay
+                .request_builder(&wechat_pay.client, method.clone(), url.clone())
+                .build()
+                .unwrap();
+            assert_eq!(request.method().as_str(), method.to_string());
+            assert_eq!(request.url(), &url);
+        }
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_reconcile() {
+        const TRADE_BILL: &str = include_str!("../testdata/tradebill.csv");
+        let server = run!(bill_server("/v3/bill/tradebill", TRADE_BILL));
+        let wechat_pay = test_pay(&server);
+        let reconciliation = wechat_pay
+            .reconcile("2024-05-01", ["T001", "T003", "T001"])
+            .await
+            .unwrap();
+        assert!(!reconciliation.is_balanced());
+        assert_eq!(reconciliation.matched.len(), 2);
+        assert!(
+            reconciliation
+                .matched
+                .iter()
+                .all(|record| record.out_trade_no == "T001")
+        );
+        assert_eq!(reconciliation.unmatched, vec!["T003".to_string()]);
+        assert_eq!(reconciliation.extra.len(), 1);
+        assert_eq!(reconciliation.extra[0].out_trade_no, "T002");
+        let requests = run!(mock_server::requests(&server));
+        assert_eq!(
+            path_and_query(&requests[0]),
+            "/v3/bill/tradebill?bill_date=2024-05-01&bill_type=ALL"
+        );
+        assert_eq!(
+            path_and_query(&requests[1]),
+            "/v3/billdownload/file?token=xxx"
+        );
+        assert!(
+            requests
+                .iter()
+                .all(|request| SignedBy::merchant().matches(request))
+        );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_accept_encoding() {
+        let server =
+            run!(mock_server::start(|_: &Request| ResponseTemplate::new(200)
+                .set_body_string(r#"{"trade_state":"NOTPAY"}"#)));
+        let wechat_pay = test_pay(&server);
+        let _ = wechat_pay.query_order_by_out_trade_no("1243243").await;
+        let request = &run!(mock_server::requests(&server))[0];
+        assert!(header(request, "accept-encoding").is_some_and(|value| value.contains("gzip")));
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_transfer_receipt() {
+        let server = run!(mock_server::start(|request: &Request| {
+            match request.method.as_str() {
+            "POST" => ResponseTemplate::new(200).set_body_string(
+                r#"{"state":"GENERATING","create_time":"2015-05-20T13:29:35+08:00"}"#,
+            ),
+            _ => ResponseTemplate::new(200).set_body_string(
+                r#"{"state":"FINISHED","create_time":"2015-05-20T13:29:35+08:00","update_time":"2015-05-20T13:29:45+08:00","hash_type":"SHA256","hash_value":"ea2c5b4d2d4d3e53e2a43b4c7a0e1e4c7c53b4a3e1e7b1a1f3c0c5a5d3e2f1a0","download_url":"https://api.mch.weixin.qq.com/v3/transferdownload/signfile?token=xxx"}"#,
+            ),
+        }
+        }));
+        let wechat_pay = test_pay(&server);
+        let response = wechat_pay
+            .apply_transfer_receipt("plfk2020042013")
+            .await
+            .unwrap();
+        assert_eq!(
+            response.ok().unwrap().state,
+            crate::model::TransferReceiptState::Generating
+        );
+        let response = wechat_pay
+            .query_transfer_receipt("plfk2020042013")
+            .await
+            .unwrap();
+        let receipt = response.ok().unwrap();
+        assert_eq!(receipt.state, crate::model::TransferReceiptState::Finished);
+        assert_eq!(
+            receipt.download_url.as_deref(),
+            Some("https://api.mch.weixin.qq.com/v3/transferdownload/signfile?token=xxx")
+        );
+        let requests = run!(mock_server::requests(&server));
+        assert_eq!(
+            path_and_query(&requests[0]),
+            "/v3/fund-app/mch-transfer/elecsign/out-bill-no"
+        );
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body, serde_json::json!({"out_bill_no": "plfk2020042013"}));
+        assert_eq!(
+            path_and_query(&requests[1]),
+            "/v3/fund-app/mch-transfer/elecsign/out-bill-no/plfk2020042013"
+        );
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_concurrent_verify_downloads_certificates_once() {
+        let server = run!(mock_server::start(|request: &Request| {
+            match request.url.path() {
+                "/v3/certificates" => {
+                    // 放慢下载，让其他请求在刷新期间到达
+                    ResponseTemplate::new(200)
+                        .set_body_string(certificates_body("NEW"))
+                        .set_delay(std::time::Duration::from_millis(200))
+                }
+                _ => signed_response(
+                    r#"{"appid":"wx0000000000000001","mchid":"1900000001","out_trade_no":"1243243","trade_state":"NOTPAY","trade_state_desc":"未支付"}"#,
+                    "NEW",
+                ),
+            }
+        }));
+        let wechat_pay = wechat_pay(&server);
+        let transaction_id = "4200000000000000000000000001";
+        #[cfg(feature = "async")]
+        let results = futures_util::future::join_all(
+            (0..8).map(|_| wechat_pay.query_order_by_transaction_id(transaction_id)),
+        )
+        .await;
+        #[cfg(not(feature = "async"))]
+        let results = std::thread::scope(|scope| {
+            let handles = (0..8)
+                .map(|_| scope.spawn(|| wechat_pay.query_order_by_transaction_id(transaction_id)))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(results.len(), 8);
+        for result in results {
+            assert!(result.expect("verify response").is_success());
+        }
+        assert_eq!(run!(mock_server::hits(&server, "/v3/certificates")), 1);
+        assert_eq!(run!(mock_server::hits(&server, ORDER_PATH)), 8);
+    }
 }
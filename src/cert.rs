@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 刷新证书的锁，保证同一时间只有一个请求在下载平台证书
+#[cfg(feature = "async")]
+pub(crate) type RefreshLock = tokio::sync::Mutex<()>;
+#[cfg(not(feature = "async"))]
+pub(crate) type RefreshLock = std::sync::Mutex<()>;
+
+/// 平台证书缓存，按证书序列号保存平台公钥
+/// 证书轮换期间微信会同时返回新旧两张证书，应答验签时按Wechatpay-Serial选择对应的公钥
+#[derive(Debug, Default)]
+pub(crate) struct PlatformCertificates {
+    keys: RwLock<HashMap<String, String>>,
+    /// 已完成的刷新次数，等待刷新锁的请求据此判断是否已有其他请求刷新过
+    generation: AtomicU64,
+    pub(crate) refresh_lock: RefreshLock,
+    /// 固定信任的平台证书序列号，设置后只接受该序列号签名的应答和回调
+    pinned_serial: RwLock<Option<String>>,
+}
+
+impl PlatformCertificates {
+    pub(crate) fn get(&self, serial_no: &str) -> Option<String> {
+        self.keys
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(serial_no)
+            .cloned()
+    }
+
+    pub(crate) fn insert(&self, serial_no: impl AsRef<str>, pub_key: impl AsRef<str>) {
+        self.keys
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(serial_no.as_ref().to_string(), pub_key.as_ref().to_string());
+    }
+
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn refreshed(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub(crate) fn pinned_serial(&self) -> Option<String> {
+        self.pinned_serial
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub(crate) fn set_pinned_serial(&self, serial_no: Option<String>) -> Option<String> {
+        std::mem::replace(
+            &mut *self
+                .pinned_serial
+                .write()
+                .unwrap_or_else(|e| e.into_inner()),
+            serial_no,
+        )
+    }
+}
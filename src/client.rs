@@ -0,0 +1,71 @@
+use crate::error::PayError;
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+use reqwest::Identity;
+use std::time::Duration;
+
+#[cfg(feature = "async")]
+pub(crate) use reqwest::Client;
+#[cfg(feature = "async")]
+use reqwest::ClientBuilder;
+#[cfg(not(feature = "async"))]
+pub(crate) use reqwest::blocking::Client;
+#[cfg(not(feature = "async"))]
+use reqwest::blocking::ClientBuilder;
+#[cfg(not(feature = "async"))]
+pub(crate) use reqwest::blocking::{Request, RequestBuilder, Response};
+#[cfg(feature = "async")]
+pub(crate) use reqwest::{Request, RequestBuilder, Response};
+
+/// http客户端配置，所有请求共用同一个连接池
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HttpConfig {
+    /// 每个host保留的最大空闲连接数，未设置时使用reqwest默认值(不限制)
+    pub pool_max_idle_per_host: Option<usize>,
+    /// 空闲连接的保活时间，未设置时使用reqwest默认值(90秒)
+    pub pool_idle_timeout: Option<Duration>,
+    /// 双向TLS的客户端证书，部分接口要求携带商户证书
+    #[cfg(any(feature = "native-tls", feature = "rustls"))]
+    pub identity: Option<Identity>,
+    /// 使用外部传入的客户端，连接池由调用方管理，不再按上述配置重建
+    pub external: bool,
+}
+
+impl HttpConfig {
+    /// 按配置构建客户端，TLS后端初始化失败或证书不被后端接受时返回错误
+    pub(crate) fn build_client(&self) -> Result<Client, PayError> {
+        let mut builder = ClientBuilder::new();
+        // 同时开启native-tls和rustls时优先使用rustls
+        #[cfg(feature = "rustls")]
+        {
+            builder = builder.use_rustls_tls();
+        }
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+        if let Some(idle_timeout) = self.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(idle_timeout);
+        }
+        #[cfg(any(feature = "native-tls", feature = "rustls"))]
+        if let Some(identity) = &self.identity {
+            builder = builder.identity(identity.clone());
+        }
+        Ok(builder.build()?)
+    }
+}
+
+/// 按开启的TLS后端解析商户证书和私钥(PKCS#8 PEM，即apiclient_cert.pem和apiclient_key.pem)
+/// rustls要求证书和私钥在同一个PEM中，native-tls分开传入，同时开启时与build_client一致优先使用rustls
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+pub(crate) fn client_identity(cert_pem: &[u8], key_pem: &[u8]) -> Result<Identity, PayError> {
+    #[cfg(feature = "rustls")]
+    {
+        let mut pem = cert_pem.to_vec();
+        pem.push(b'\n');
+        pem.extend_from_slice(key_pem);
+        Ok(Identity::from_pem(&pem)?)
+    }
+    #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+    {
+        Ok(Identity::from_pkcs8_pem(cert_pem, key_pem)?)
+    }
+}
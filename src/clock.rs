@@ -0,0 +1,54 @@
+//! 时钟，签名时间戳、证书有效期判断等读取当前时间的逻辑统一通过`Clock`获取，测试时可注入固定时间
+
+use chrono::{DateTime, TimeZone, Utc};
+use std::fmt::Debug;
+
+/// 当前时间来源，默认使用系统时间`SystemClock`
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// 当前的unix时间戳，单位秒
+    fn timestamp(&self) -> i64 {
+        self.now().timestamp()
+    }
+}
+
+/// 系统时钟
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// 固定时间的时钟，用于测试签名时间戳、证书过期等与时间相关的逻辑
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl FixedClock {
+    /// 使用unix时间戳(秒)创建，时间戳超出范围时panic
+    pub fn from_timestamp(timestamp: i64) -> Self {
+        Self(Utc.timestamp_opt(timestamp, 0).unwrap())
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, FixedClock, SystemClock};
+
+    #[test]
+    fn test_clock() {
+        let clock = FixedClock::from_timestamp(1432099775);
+        assert_eq!(clock.timestamp(), 1432099775);
+        assert_eq!(clock.now().to_rfc3339(), "2015-05-20T05:29:35+00:00");
+        assert!(SystemClock.timestamp() > 1432099775);
+    }
+}
@@ -0,0 +1,59 @@
+//! 微信支付API路径，所有接口路径统一在这里定义
+
+/// JSAPI/小程序下单
+pub const TRANSACTIONS_JSAPI: &str = "/v3/pay/transactions/jsapi";
+/// APP下单
+pub const TRANSACTIONS_APP: &str = "/v3/pay/transactions/app";
+/// H5下单
+pub const TRANSACTIONS_H5: &str = "/v3/pay/transactions/h5";
+/// Native下单
+pub const TRANSACTIONS_NATIVE: &str = "/v3/pay/transactions/native";
+/// 服务商JSAPI/小程序下单
+pub const PARTNER_TRANSACTIONS_JSAPI: &str = "/v3/pay/partner/transactions/jsapi";
+/// 服务商APP下单
+pub const PARTNER_TRANSACTIONS_APP: &str = "/v3/pay/partner/transactions/app";
+/// 服务商H5下单
+pub const PARTNER_TRANSACTIONS_H5: &str = "/v3/pay/partner/transactions/h5";
+/// 服务商Native下单
+pub const PARTNER_TRANSACTIONS_NATIVE: &str = "/v3/pay/partner/transactions/native";
+/// 商户订单号查询订单，后接`/{out_trade_no}`
+pub const TRANSACTIONS_OUT_TRADE_NO: &str = "/v3/pay/transactions/out-trade-no";
+/// 微信支付订单号查询订单，后接`/{transaction_id}`
+pub const TRANSACTIONS_ID: &str = "/v3/pay/transactions/id";
+/// 申请交易账单，带查询参数`bill_date`
+pub const BILL_TRADEBILL: &str = "/v3/bill/tradebill";
+/// 下载平台证书
+pub const CERTIFICATES: &str = "/v3/certificates";
+/// 退款申请
+pub const REFUND_DOMESTIC_REFUNDS: &str = "/v3/refund/domestic/refunds";
+/// 查询投诉单列表
+pub const MERCHANT_SERVICE_COMPLAINTS: &str = "/v3/merchant-service/complaints-v2";
+/// 代金券用户接口，后接`/{openid}/coupons`
+pub const FAVOR_USERS: &str = "/v3/marketing/favor/users";
+/// 营销图片上传
+pub const FAVOR_MEDIA_IMAGE_UPLOAD: &str = "/v3/marketing/favor/media/image-upload";
+/// 创建代金券批次
+pub const FAVOR_COUPON_STOCKS: &str = "/v3/marketing/favor/coupon-stocks";
+/// 开具电子发票，查询时后接`/{fapiao_apply_id}`
+pub const FAPIAO_APPLICATIONS: &str = "/v3/new-tax-control-fapiao/fapiao-applications";
+/// 请求分账，查询时后接`/{out_order_no}`
+pub const PROFITSHARING_ORDERS: &str = "/v3/profitsharing/orders";
+/// 解冻剩余资金
+pub const PROFITSHARING_ORDERS_UNFREEZE: &str = "/v3/profitsharing/orders/unfreeze";
+/// 请求分账回退，查询时后接`/{out_return_no}`
+pub const PROFITSHARING_RETURN_ORDERS: &str = "/v3/profitsharing/return-orders";
+/// 申请分账账单，带查询参数`bill_date`
+pub const PROFITSHARING_BILLS: &str = "/v3/profitsharing/bills";
+/// 查询剩余待分金额，后接`/{transaction_id}/amounts`
+pub const PROFITSHARING_TRANSACTIONS: &str = "/v3/profitsharing/transactions";
+/// 合单查询订单，后接`/{combine_out_trade_no}`，关闭订单再接`/close`
+pub const COMBINE_TRANSACTIONS_OUT_TRADE_NO: &str = "/v3/combine-transactions/out-trade-no";
+/// 发起转账
+pub const FUND_APP_TRANSFER_BILLS: &str = "/v3/fund-app/mch-transfer/transfer-bills";
+/// 按商户单号申请转账电子回单，查询时后接`/{out_bill_no}`
+pub const FUND_APP_TRANSFER_ELECSIGN_OUT_BILL_NO: &str =
+    "/v3/fund-app/mch-transfer/elecsign/out-bill-no";
+/// 创建支付分订单，查询时带查询参数，完结订单后接`/{out_order_no}/complete`，修改订单金额后接`/{out_order_no}/modify`
+pub const PAYSCORE_SERVICEORDER: &str = "/v3/payscore/serviceorder";
+/// 支付分商户预授权，查询和解除授权后接`/authorization-code/{authorization_code}`
+pub const PAYSCORE_PERMISSIONS: &str = "/v3/payscore/permissions";
@@ -1,9 +1,28 @@
+use crate::macros::string_enum;
+use crate::request::DryRunRequest;
+use crate::response::ErrorDetail;
+
 #[derive(Debug, thiserror::Error)]
 pub enum PayError {
     #[error("http error: {0}")]
     RequestError(#[from] reqwest::Error),
     #[error("pay error: {0}")]
     WechatError(String),
+    #[error("pay error: {}", api_error_message(.code, .message, .detail.as_deref(), .request_id.as_deref()))]
+    Api {
+        /// 应答状态码，HTTP 200但应答体为错误码时同样为200，未经过网络时为None
+        status: Option<u16>,
+        ///【错误码】 如PARAM_ERROR，`wechat_error_code`解析为`WechatErrorCode`
+        code: String,
+        ///【错误信息】
+        message: String,
+        ///【错误详情】 参数校验失败时指出出错的字段和传入的值
+        detail: Option<Box<ErrorDetail>>,
+        /// 应答头中的Request-ID，向微信支付反馈问题时提供
+        request_id: Option<String>,
+    },
+    #[error("io error: {0}")]
+    IoError(#[from] std::io::Error),
     #[error("json error: {0}")]
     JsonError(#[from] serde_json::Error),
     #[error("Decrypt error: {0}")]
@@ -14,4 +33,232 @@ pub enum PayError {
     VerifyError(String),
     #[error("weixin not found error")]
     WeixinNotFound,
+    #[error("weixin:// launch url not found in h5 page {0}, the page structure may have changed")]
+    WeixinLaunchUrlNotFound(String),
+    #[error("request timed out after {0:?}")]
+    Timeout(std::time::Duration),
+    #[error("response too large: exceeds {0} bytes")]
+    ResponseTooLarge(usize),
+    #[error("auth failed: {0}")]
+    AuthFailed(String),
+    #[error("invalid private key: {0}")]
+    InvalidPrivateKey(String),
+    #[error("invalid params: {0}")]
+    InvalidParams(String),
+    #[cfg(feature = "qrcode")]
+    #[error("qrcode error: {0}")]
+    QrCodeError(String),
+    #[error("dry run: {0}")]
+    DryRun(Box<DryRunRequest>),
+    #[error("payment mismatch: {field} expected {expected}, got {actual}")]
+    PaymentMismatch {
+        /// 不一致的字段，如out_trade_no、amount.total、trade_state
+        field: &'static str,
+        /// 商户订单中的值
+        expected: String,
+        /// 支付通知中的值
+        actual: String,
+    },
+    #[error("response parse error: {source}, status: {status}, body: {body}")]
+    ResponseParseError {
+        /// 应答状态码
+        status: u16,
+        /// 应答原文，超过`MAX_ERROR_BODY_LEN`个字符时截断
+        body: String,
+        source: serde_json::Error,
+    },
+}
+
+/// 错误应答的展示格式：`错误码: 错误信息`，带有detail时追加`, field amount.total 必须大于等于1 (value: 0)`，
+/// 带有Request-ID时追加` (request_id: ...)`
+pub(crate) fn api_error_message(
+    code: &str,
+    message: &str,
+    detail: Option<&ErrorDetail>,
+    request_id: Option<&str>,
+) -> String {
+    let mut text = format!("{}: {}", code, message);
+    if let Some(detail) = detail {
+        text.push_str(&format!(", {}", detail));
+    }
+    if let Some(request_id) = request_id {
+        text.push_str(&format!(" (request_id: {})", request_id));
+    }
+    text
+}
+
+/// 解析失败时错误中保留的应答原文长度，按字符计
+pub const MAX_ERROR_BODY_LEN: usize = 1024;
+
+impl PayError {
+    /// 应答反序列化失败，附带状态码和截断后的应答原文
+    pub(crate) fn response_parse(status: u16, body: &str, source: serde_json::Error) -> Self {
+        let body = match body.char_indices().nth(MAX_ERROR_BODY_LEN) {
+            Some((index, _)) => format!("{}...", &body[..index]),
+            None => body.to_string(),
+        };
+        PayError::ResponseParseError {
+            status,
+            body,
+            source,
+        }
+    }
+}
+
+string_enum! {
+    /// 微信支付错误码，取自错误应答的code字段
+    /// APIv2风格的写法(如SYSTEMERROR、ORDERNOTEXIST)与APIv3归为同一个值，Display输出APIv3写法
+    pub enum WechatErrorCode {
+        /// 系统错误，可使用相同参数重试
+        SystemError => "SYSTEM_ERROR" | "SYSTEMERROR",
+        /// 频率超限，降低频率后重试
+        FrequencyLimited => "FREQUENCY_LIMITED" | "FREQUENCY_LIMIT",
+        /// 订单不存在
+        OrderNotExist => "ORDER_NOT_EXIST" | "ORDERNOTEXIST",
+        /// 订单已关闭
+        OrderClosed => "ORDER_CLOSED" | "ORDERCLOSED",
+        /// 订单已支付，如关闭已支付的订单
+        OrderPaid => "ORDER_PAID" | "ORDERPAID",
+        /// 资源已存在，如重复的商户单号
+        ResourceAlreadyExists => "RESOURCE_ALREADY_EXISTS",
+        /// 资源不存在
+        ResourceNotExists => "RESOURCE_NOT_EXISTS",
+        /// 参数错误
+        ParamError => "PARAM_ERROR",
+        /// 请求不符合业务规则
+        InvalidRequest => "INVALID_REQUEST",
+        /// 签名错误
+        SignError => "SIGN_ERROR",
+        /// 商户无权限
+        NoAuth => "NO_AUTH" | "NOAUTH",
+        /// 余额不足
+        NotEnough => "NOT_ENOUGH" | "NOTENOUGH",
+        /// 用户支付中，需要输入密码
+        UserPaying => "USER_PAYING" | "USERPAYING",
+    }
+}
+
+impl WechatErrorCode {
+    /// 是否可以使用相同参数重试
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            WechatErrorCode::SystemError | WechatErrorCode::FrequencyLimited
+        )
+    }
+}
+
+impl PayError {
+    /// 微信支付返回的错误码，取自`PayError::Api`的code字段，其他错误返回None
+    pub fn wechat_error_code(&self) -> Option<WechatErrorCode> {
+        match self {
+            PayError::Api { code, .. } if !code.is_empty() => {
+                Some(WechatErrorCode::from(code.as_str()))
+            }
+            _ => None,
+        }
+    }
+
+    /// 是否可以使用相同参数重试，如SYSTEM_ERROR、FREQUENCY_LIMITED
+    pub fn is_retryable(&self) -> bool {
+        self.wechat_error_code()
+            .is_some_and(|error_code| error_code.is_retryable())
+    }
+
+    /// 参数校验失败时的出错字段和传入的值
+    pub fn error_detail(&self) -> Option<&ErrorDetail> {
+        match self {
+            PayError::Api { detail, .. } => detail.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// 错误应答头中的Request-ID
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            PayError::Api { request_id, .. } => request_id.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PayError, WechatErrorCode};
+
+    #[test]
+    fn test_wechat_error_code() {
+        assert_eq!(
+            WechatErrorCode::from("SYSTEMERROR"),
+            WechatErrorCode::SystemError
+        );
+        assert_eq!(
+            WechatErrorCode::from("ORDERNOTEXIST"),
+            WechatErrorCode::OrderNotExist
+        );
+        assert_eq!(
+            WechatErrorCode::from("ORDERPAID"),
+            WechatErrorCode::OrderPaid
+        );
+        assert_eq!(WechatErrorCode::OrderPaid.to_string(), "ORDER_PAID");
+        assert_eq!(WechatErrorCode::SystemError.to_string(), "SYSTEM_ERROR");
+        assert!(WechatErrorCode::from("SYSTEM_ERROR").is_retryable());
+        assert!(WechatErrorCode::from("FREQUENCY_LIMITED").is_retryable());
+        assert!(!WechatErrorCode::from("RESOURCE_ALREADY_EXISTS").is_retryable());
+        let other = WechatErrorCode::from("NEW_CODE");
+        assert_eq!(other, WechatErrorCode::Other("NEW_CODE".to_string()));
+        assert_eq!(other.to_string(), "NEW_CODE");
+    }
+
+    #[test]
+    fn test_response_parse_error() {
+        let source = serde_json::from_str::<serde_json::Value>("<html>").unwrap_err();
+        let error = PayError::response_parse(502, "<html>bad gateway</html>", source);
+        assert!(
+            matches!(&error, PayError::ResponseParseError { status: 502, body, .. } if body == "<html>bad gateway</html>")
+        );
+        assert!(error.to_string().contains("status: 502"));
+
+        let source = serde_json::from_str::<serde_json::Value>("<html>").unwrap_err();
+        let body = "错".repeat(super::MAX_ERROR_BODY_LEN + 10);
+        let error = PayError::response_parse(200, &body, source);
+        let PayError::ResponseParseError { body, .. } = error else {
+            panic!("unexpected error");
+        };
+        assert_eq!(body.chars().count(), super::MAX_ERROR_BODY_LEN + 3);
+        assert!(body.ends_with("..."));
+    }
+
+    #[test]
+    fn test_pay_error_wechat_error_code() {
+        let error = PayError::Api {
+            status: Some(429),
+            code: "FREQUENCY_LIMITED".to_string(),
+            message: "频率超限: 请稍后再试".to_string(),
+            detail: None,
+            request_id: Some("08F4".to_string()),
+        };
+        assert_eq!(
+            error.wechat_error_code(),
+            Some(WechatErrorCode::FrequencyLimited)
+        );
+        assert!(error.is_retryable());
+        assert_eq!(error.request_id(), Some("08F4"));
+        assert_eq!(
+            error.to_string(),
+            "pay error: FREQUENCY_LIMITED: 频率超限: 请稍后再试 (request_id: 08F4)"
+        );
+        // 错误码只取自code字段，不再从错误信息中解析
+        let error = PayError::WechatError("FREQUENCY_LIMITED: 频率超限".to_string());
+        assert_eq!(error.wechat_error_code(), None);
+        assert!(!error.is_retryable());
+        assert_eq!(PayError::WeixinNotFound.wechat_error_code(), None);
+        // 读取应答体失败属于传输错误，不是微信支付的错误应答
+        let error = PayError::from(std::io::Error::new(
+            std::io::ErrorKind::ConnectionReset,
+            "CONNECTION_RESET: peer closed",
+        ));
+        assert!(matches!(error, PayError::IoError(_)));
+        assert_eq!(error.wechat_error_code(), None);
+    }
 }
@@ -1,11 +1,22 @@
 #![doc = include_str!("../README.md")]
 pub mod async_impl;
+pub(crate) mod cert;
+pub(crate) mod client;
+pub mod clock;
+pub mod endpoints;
 pub mod error;
 pub(crate) mod macros;
+#[cfg(test)]
+pub(crate) mod mock_server;
+pub mod mode;
 pub mod model;
+pub mod notify;
+pub mod pagination;
 pub mod pay;
 pub mod pay_type;
 pub mod request;
 pub mod response;
 pub mod sign;
+#[cfg(feature = "chrono")]
+pub mod time;
 pub mod util;
@@ -12,7 +12,30 @@ macro_rules! error {
 #[macro_export]
 macro_rules! error {
     ($name:expr) => {};
-    ($name:expr $(,$arg:tt)+ $(,)?) => {};
+    ($name:expr $(,$arg:tt)+ $(,)?) => {
+        $(let _ = &$arg;)+
+    };
+}
+
+#[cfg(feature = "debug-print")]
+#[macro_export]
+macro_rules! info {
+    ($name:expr)=>{
+        tracing::info!($name);
+    };
+    ($name:expr $(,$arg:tt)+) => {
+        tracing::info!($name, $($arg),*);
+    };
+}
+
+// 日志需开启debug-print，未开启时info!等宏不输出，调用方可通过应答的request_id()自行记录
+#[cfg(not(feature = "debug-print"))]
+#[macro_export]
+macro_rules! info {
+    ($name:expr) => {};
+    ($name:expr $(,$arg:tt)+ $(,)?) => {
+        $(let _ = &$arg;)+
+    };
 }
 
 #[cfg(feature = "debug-print")]
@@ -30,5 +53,85 @@ macro_rules! debug {
 #[macro_export]
 macro_rules! debug {
     ($name:expr) => {};
-    ($name:expr $(,$arg:tt)+ $(,)?) => {};
+    // 引用参数，避免只用于日志的变量在关闭debug-print时产生未使用警告
+    ($name:expr $(,$arg:tt)+ $(,)?) => {
+        $(let _ = &$arg;)+
+    };
 }
+
+/// 以字符串收发的枚举，生成`as_str`、`Display`、`From<&str>`、`From<String>`和serde实现
+/// 每个值对应一个字符串，`|`后为同义写法；未收录的值保存在`Other`中，原样序列化
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident => $value:literal $(| $alias:literal)*,
+            )+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant,
+            )+
+            /// 未收录的值，保留原始值
+            Other(String),
+        }
+
+        impl $name {
+            /// 微信支付使用的字符串值
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $value,)+
+                    $name::Other(value) => value,
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                match value {
+                    $($value $(| $alias)* => $name::$variant,)+
+                    other => $name::Other(other.to_string()),
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                $name::from(value.as_str())
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let value = <String as serde::Deserialize>::deserialize(deserializer)?;
+                Ok($name::from(value.as_str()))
+            }
+        }
+    };
+}
+
+pub(crate) use string_enum;
@@ -0,0 +1,159 @@
+//! 测试用的wiremock模拟服务，商户签名正确的请求按预设应答返回，用于离线测试请求流程
+//! 签名由`SignedBy`使用商户公钥独立验签，不经过`WechatPay`的签名实现
+//! 同步模式下模拟服务运行在独立的tokio运行时中，测试线程直接调用阻塞接口
+
+use crate::pay::WechatPay;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::sha2::{Digest, Sha256};
+use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+use wiremock::{Match, Mock, MockServer, Request, Respond};
+
+/// 执行wiremock的异步调用，同步模式下在共享的运行时中阻塞等待
+#[cfg(feature = "async")]
+macro_rules! run {
+    ($future:expr) => {
+        $future.await
+    };
+}
+
+#[cfg(not(feature = "async"))]
+macro_rules! run {
+    ($future:expr) => {
+        $crate::mock_server::runtime().block_on($future)
+    };
+}
+
+pub(crate) use run;
+
+#[cfg(not(feature = "async"))]
+pub(crate) fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: std::sync::OnceLock<tokio::runtime::Runtime> = std::sync::OnceLock::new();
+    RUNTIME.get_or_init(|| tokio::runtime::Runtime::new().unwrap())
+}
+
+/// 测试用的商户私钥
+pub(crate) const PRIVATE_KEY: &str = include_str!("testdata/apiclient_key.pem");
+
+/// 校验Authorization签名，签名串使用服务端收到的method、路径、查询参数和body
+pub(crate) struct SignedBy(RsaPublicKey);
+
+impl SignedBy {
+    /// 使用PRIVATE_KEY对应的商户公钥验签
+    pub(crate) fn merchant() -> Self {
+        let private_key = RsaPrivateKey::from_pkcs8_pem(PRIVATE_KEY).unwrap();
+        Self(RsaPublicKey::from(&private_key))
+    }
+}
+
+impl Match for SignedBy {
+    fn matches(&self, request: &Request) -> bool {
+        let Some(authorization) = request
+            .headers
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok())
+        else {
+            return false;
+        };
+        let field = |name: &str| {
+            authorization
+                .split(&format!("{}=\"", name))
+                .nth(1)
+                .and_then(|value| value.split('"').next())
+                .unwrap_or_default()
+        };
+        let message = format!(
+            "{}\n{}\n{}\n{}\n{}\n",
+            request.method,
+            path_and_query(request),
+            field("timestamp"),
+            field("nonce_str"),
+            String::from_utf8_lossy(&request_body(request))
+        );
+        let Ok(signature) = STANDARD.decode(field("signature")) else {
+            return false;
+        };
+        self.0
+            .verify(
+                Pkcs1v15Sign::new::<Sha256>(),
+                &Sha256::digest(message.as_bytes()),
+                &signature,
+            )
+            .is_ok()
+    }
+}
+
+/// 参与验签的请求体，gzip压缩的请求体按解压后的JSON验签，图片上传按meta部分的JSON验签
+fn request_body(request: &Request) -> Vec<u8> {
+    if header(request, "Content-Type").is_some_and(|value| value.starts_with("multipart/form-data"))
+    {
+        let body = String::from_utf8_lossy(&request.body);
+        return body
+            .split("name=\"meta\"")
+            .nth(1)
+            .and_then(|part| part.split_once("\r\n\r\n"))
+            .and_then(|(_, rest)| rest.split_once("\r\n"))
+            .map(|(meta, _)| meta.as_bytes().to_vec())
+            .unwrap_or_default();
+    }
+    #[cfg(feature = "gzip")]
+    if header(request, "Content-Encoding") == Some("gzip") {
+        use std::io::Read;
+        let mut body = vec![];
+        if flate2::read::GzDecoder::new(request.body.as_slice())
+            .read_to_end(&mut body)
+            .is_ok()
+        {
+            return body;
+        }
+    }
+    request.body.clone()
+}
+
+/// 启动模拟服务，商户签名正确的请求交给responder生成应答，其他请求返回404
+pub(crate) async fn start(responder: impl Respond + 'static) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(SignedBy::merchant())
+        .respond_with(responder)
+        .mount(&server)
+        .await;
+    server
+}
+
+/// 收到的全部请求，包括未匹配的请求
+pub(crate) async fn requests(server: &MockServer) -> Vec<Request> {
+    server.received_requests().await.unwrap_or_default()
+}
+
+/// 指定路径被请求的次数
+pub(crate) async fn hits(server: &MockServer, path: &str) -> usize {
+    requests(server)
+        .await
+        .iter()
+        .filter(|request| request.url.path() == path)
+        .count()
+}
+
+/// 请求的路径和查询参数，即参与签名的URL
+pub(crate) fn path_and_query(request: &Request) -> String {
+    match request.url.query() {
+        Some(query) => format!("{}?{}", request.url.path(), query),
+        None => request.url.path().to_string(),
+    }
+}
+
+/// 请求头的值，不是合法字符串时返回None
+pub(crate) fn header<'a>(request: &'a Request, name: &str) -> Option<&'a str> {
+    request
+        .headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// 请求本地服务的测试商户，appid为wx_appid、商户号为1900000001，证书序列号、APIv3密钥和回调地址为空
+pub(crate) fn test_pay(server: &MockServer) -> WechatPay {
+    WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "", "", "")
+        .unwrap()
+        .with_base_url(server.uri())
+}
@@ -0,0 +1,43 @@
+//! 编译模式标记
+//!
+//! 网络接口由`maybe_async`在编译期生成同步或异步版本：开启`async` feature时接口返回`Future`，需要`.await`；
+//! 否则为阻塞调用，直接返回结果。可以通过[`Current`]在代码中判断当前模式
+
+/// 同步模式（默认，`blocking` feature）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Blocking;
+
+/// 异步模式（`async` feature）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Async;
+
+/// 编译模式
+pub trait Mode {
+    /// 网络接口是否为异步
+    const IS_ASYNC: bool;
+}
+
+impl Mode for Blocking {
+    const IS_ASYNC: bool = false;
+}
+
+impl Mode for Async {
+    const IS_ASYNC: bool = true;
+}
+
+/// 当前编译模式
+#[cfg(feature = "async")]
+pub type Current = Async;
+/// 当前编译模式
+#[cfg(not(feature = "async"))]
+pub type Current = Blocking;
+
+#[cfg(test)]
+mod tests {
+    use super::{Current, Mode};
+
+    #[test]
+    fn test_current_mode() {
+        assert_eq!(Current::IS_ASYNC, cfg!(feature = "async"));
+    }
+}
@@ -1,20 +1,27 @@
-use serde::{Deserialize, Serialize};
+use crate::endpoints;
+use crate::error::PayError;
+use crate::macros::string_enum;
+use crate::response::{PromotionDetailResponse, ResponseTrait};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::fmt::{Display, Formatter};
 
 pub trait ParamsTrait {
     fn to_json(&self) -> String;
+    /// 发送前校验参数，不合法时在本地直接返回错误
+    fn validate(&self) -> Result<(), PayError> {
+        Ok(())
+    }
+    /// 参数中指定的回调地址，发送前校验为不带查询参数的https地址
+    fn notify_url(&self) -> Option<&str> {
+        None
+    }
 }
 
-#[derive(Serialize, Debug, Clone)]
-pub enum Currency {
-    CNY,
-}
-
-impl Display for Currency {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Currency::CNY => write!(f, "CNY"),
-        }
+string_enum! {
+    #[derive(Default)]
+    pub enum Currency {
+        #[default]
+        CNY => "CNY",
     }
 }
 
@@ -22,15 +29,108 @@ unsafe impl Send for Currency {}
 
 unsafe impl Sync for Currency {}
 
+string_enum! {
+    /// 退款状态
+    pub enum RefundStatus {
+        /// 退款成功
+        Success => "SUCCESS",
+        /// 退款关闭
+        Closed => "CLOSED",
+        /// 退款处理中
+        Processing => "PROCESSING",
+        /// 退款异常，原路退款的银行卡作废或冻结，需要到商户平台手动处理或发起异常退款
+        Abnormal => "ABNORMAL",
+    }
+}
+
+string_enum! {
+    /// 退款渠道
+    pub enum RefundChannel {
+        /// 原路退款
+        Original => "ORIGINAL",
+        /// 退回到余额
+        Balance => "BALANCE",
+        /// 原账户异常退到其他余额账户
+        OtherBalance => "OTHER_BALANCE",
+        /// 原银行卡异常退到其他银行卡
+        OtherBankcard => "OTHER_BANKCARD",
+    }
+}
+
+/// 下单接口的订单金额，序列化为`{"total": 100}`对象
+/// 反序列化同时兼容对象和以分为单位的整数，商户保存的订单中可能只存了金额
+/// 标记为non_exhaustive，跨境币种等新增字段不影响调用方，使用`AmountInfo::new`或`From<i32>`构造
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "AmountInfoRepr")]
+#[non_exhaustive]
 pub struct AmountInfo {
     ///【标价金额】 订单总金额，单位为分。
     pub total: i32,
+    ///【标价币种】 境外商户号的订单币种，不设置时按人民币下单
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<Currency>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AmountInfoRepr {
+    Cents(i32),
+    Object {
+        total: i32,
+        #[serde(default)]
+        currency: Option<Currency>,
+    },
+}
+
+impl From<AmountInfoRepr> for AmountInfo {
+    fn from(value: AmountInfoRepr) -> Self {
+        match value {
+            AmountInfoRepr::Cents(total) => Self::from(total),
+            AmountInfoRepr::Object { total, currency } => Self { total, currency },
+        }
+    }
+}
+
+impl AmountInfo {
+    /// 订单金额下限，单位为分
+    pub const MIN_TOTAL: i32 = 1;
+    /// 订单金额上限，单位为分(100万元)
+    pub const MAX_TOTAL: i32 = 100_000_000;
+
+    /// 创建订单金额，金额需在[MIN_TOTAL, MAX_TOTAL]之间
+    pub fn new(total: i32) -> Result<Self, PayError> {
+        let amount = Self::from(total);
+        amount.validate()?;
+        Ok(amount)
+    }
+
+    /// 设置订单币种，跨境商户按外币标价下单，例如`Currency::from("USD")`
+    pub fn with_currency(mut self, currency: impl Into<Currency>) -> Self {
+        self.currency = Some(currency.into());
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), PayError> {
+        if (Self::MIN_TOTAL..=Self::MAX_TOTAL).contains(&self.total) {
+            Ok(())
+        } else {
+            Err(PayError::InvalidParams(format!(
+                "amount total must be between {} and {}, got {}",
+                Self::MIN_TOTAL,
+                Self::MAX_TOTAL,
+                self.total
+            )))
+        }
+    }
 }
 
+/// 不做校验，金额在下单前由`ParamsTrait::validate`校验
 impl From<i32> for AmountInfo {
     fn from(value: i32) -> Self {
-        Self { total: value }
+        Self {
+            total: value,
+            currency: None,
+        }
     }
 }
 
@@ -38,24 +138,280 @@ unsafe impl Send for AmountInfo {}
 
 unsafe impl Sync for AmountInfo {}
 
+/// 商户订单号，6-32位的数字、大小写字母或_-|*，同一商户号下唯一
+/// 通过`try_into()`从字符串转换并校验格式，避免与商品描述等参数传反
+/// 下单参数的构造函数同时接受`TradeNo`和字符串，字符串在发送前由`ParamsTrait::validate`校验
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct TradeNo(String);
+
+impl TradeNo {
+    /// 商户订单号最小长度
+    pub const MIN_LEN: usize = 6;
+    /// 商户订单号最大长度
+    pub const MAX_LEN: usize = 32;
+
+    /// 使用`util::random_trade_no`生成新的商户订单号
+    pub fn generate() -> Self {
+        Self(crate::util::random_trade_no())
+    }
+
+    /// 校验商户订单号格式，不合法时返回`PayError::InvalidParams`
+    pub fn validate(out_trade_no: &str) -> Result<(), PayError> {
+        let valid_len = (Self::MIN_LEN..=Self::MAX_LEN).contains(&out_trade_no.len());
+        let valid_chars = out_trade_no
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '|' | '*'));
+        if valid_len && valid_chars {
+            Ok(())
+        } else {
+            Err(PayError::InvalidParams(format!(
+                "out_trade_no must be {}-{} letters, digits or _-|*, got {}",
+                Self::MIN_LEN,
+                Self::MAX_LEN,
+                out_trade_no
+            )))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<&str> for TradeNo {
+    type Error = PayError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::validate(value)?;
+        Ok(Self(value.to_string()))
+    }
+}
+
+impl TryFrom<String> for TradeNo {
+    type Error = PayError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::validate(&value)?;
+        Ok(Self(value))
+    }
+}
+
+impl AsRef<str> for TradeNo {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for TradeNo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for TradeNo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        TradeNo::try_from(value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// 订单查询条件，商户订单号和微信支付订单号二选一，由`query_order`拼接对应的查询地址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderQuery {
+    ///【商户订单号】
+    OutTradeNo(String),
+    ///【微信支付订单号】
+    TransactionId(String),
+}
+
+impl OrderQuery {
+    /// 按商户订单号查询，字符串可先用`try_into()`转换为`TradeNo`校验格式
+    pub fn out_trade_no(out_trade_no: &TradeNo) -> Self {
+        OrderQuery::OutTradeNo(out_trade_no.as_str().to_string())
+    }
+
+    pub fn transaction_id(transaction_id: impl AsRef<str>) -> Self {
+        OrderQuery::TransactionId(transaction_id.as_ref().to_string())
+    }
+
+    /// 查询地址的路径部分，不含mchid查询参数
+    pub(crate) fn path(&self) -> String {
+        match self {
+            OrderQuery::OutTradeNo(out_trade_no) => {
+                format!("{}/{}", endpoints::TRANSACTIONS_OUT_TRADE_NO, out_trade_no)
+            }
+            OrderQuery::TransactionId(transaction_id) => {
+                format!("{}/{}", endpoints::TRANSACTIONS_ID, transaction_id)
+            }
+        }
+    }
+}
+
+impl From<TradeNo> for OrderQuery {
+    fn from(value: TradeNo) -> Self {
+        OrderQuery::OutTradeNo(value.0)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueryAmount {
+    ///【总金额】 订单总金额，单位为分。
+    pub total: i32,
+    ///【用户支付金额】 用户支付金额，单位为分。订单未支付时不返回。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payer_total: Option<i32>,
+    ///【货币类型】 CNY：人民币，境内商户号仅支持人民币。
+    #[serde(default)]
+    pub currency: Currency,
+    ///【用户支付币种】 用户支付币种。订单未支付时不返回。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payer_currency: Option<Currency>,
+    ///【汇率信息】 跨境订单的标价币种与支付币种之间的汇率，境内订单不返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exchange_rate: Option<ExchangeRate>,
+}
+
+/// 跨境订单的汇率信息
 #[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExchangeRate {
+    ///【汇率类型】 SETTLEMENT_RATE：标价币种与支付币种之间的兑换汇率
+    #[serde(rename = "type")]
+    pub rate_type: String,
+    ///【汇率值】 汇率乘以10^8后取整，例如1 USD = 6.5 CNY返回650000000
+    pub rate: i64,
+}
+
+impl ExchangeRate {
+    /// 汇率值的放大倍数
+    pub const RATE_SCALE: i64 = 100_000_000;
+
+    /// 换算为实际汇率
+    pub fn as_f64(&self) -> f64 {
+        self.rate as f64 / Self::RATE_SCALE as f64
+    }
+}
+
+/// 下单的支付者，openid、sp_openid、sub_openid三选一
+/// 服务商模式下传openid时按是否设置sub_appid自动转换为sub_openid或sp_openid，也可以直接指定
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct PayerInfo {
     ///【用户标识】 用户在直连商户appid下的唯一标识。
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub openid: String,
+    ///【用户服务标识】 服务商模式下用户在服务商appid下的唯一标识
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sp_openid: Option<String>,
+    ///【用户子标识】 服务商模式下用户在子商户appid下的唯一标识，需要设置sub_appid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub_openid: Option<String>,
 }
 
-impl From<&str> for PayerInfo {
-    fn from(value: &str) -> Self {
+impl PayerInfo {
+    /// openid最大长度
+    pub const MAX_OPENID_LEN: usize = 128;
+
+    /// 去掉首尾空白后作为openid
+    pub fn new(openid: impl AsRef<str>) -> Self {
+        Self {
+            openid: openid.as_ref().trim().to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// 服务商模式下指定用户在服务商appid下的标识
+    pub fn sp_openid(sp_openid: impl AsRef<str>) -> Self {
         Self {
-            openid: value.to_string(),
+            sp_openid: Some(sp_openid.as_ref().trim().to_string()),
+            ..Default::default()
         }
     }
+
+    /// 服务商模式下指定用户在子商户appid下的标识
+    pub fn sub_openid(sub_openid: impl AsRef<str>) -> Self {
+        Self {
+            sub_openid: Some(sub_openid.as_ref().trim().to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// 校验只设置了一种用户标识，且为1-128位、不含空白字符
+    pub fn validate(&self) -> Result<(), PayError> {
+        let openids = [
+            (
+                "openid",
+                Some(self.openid.as_str()).filter(|openid| !openid.is_empty()),
+            ),
+            ("sp_openid", self.sp_openid.as_deref()),
+            ("sub_openid", self.sub_openid.as_deref()),
+        ];
+        let mut present = openids
+            .iter()
+            .filter_map(|(name, value)| value.map(|value| (*name, value)));
+        let (Some((name, value)), None) = (present.next(), present.next()) else {
+            return Err(PayError::InvalidParams(
+                "payer requires exactly one of openid, sp_openid or sub_openid".to_string(),
+            ));
+        };
+        if value.is_empty()
+            || value.len() > Self::MAX_OPENID_LEN
+            || value.chars().any(|c| c.is_whitespace() || c.is_control())
+        {
+            return Err(PayError::InvalidParams(format!(
+                "payer {} must be 1-{} characters without whitespace, got {:?}",
+                name,
+                Self::MAX_OPENID_LEN,
+                value
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl From<&str> for PayerInfo {
+    fn from(value: &str) -> Self {
+        PayerInfo::new(value)
+    }
+}
+
+impl From<String> for PayerInfo {
+    fn from(value: String) -> Self {
+        PayerInfo::new(value)
+    }
 }
 
 unsafe impl Send for PayerInfo {}
 
 unsafe impl Sync for PayerInfo {}
 
+/// 查询订单、支付通知中的支付者
+/// 直连模式返回openid，服务商模式返回sp_openid，并在传了sub_appid时返回sub_openid
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Payer {
+    ///【用户标识】 用户在直连商户appid下的唯一标识
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub openid: Option<String>,
+    ///【用户服务标识】 用户在服务商appid下的唯一标识
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sp_openid: Option<String>,
+    ///【用户子标识】 用户在子商户appid下的唯一标识
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_openid: Option<String>,
+}
+
+impl Payer {
+    /// 用户在下单appid下的标识，依次取openid、sub_openid、sp_openid
+    pub fn openid(&self) -> Option<&str> {
+        self.openid
+            .as_deref()
+            .or(self.sub_openid.as_deref())
+            .or(self.sp_openid.as_deref())
+    }
+}
+
 #[derive(Serialize, Debug, Clone)]
 pub struct GoodsDetail {
     ///【商户侧商品编码】 由半角的大小写字母、数字、中划线、下划线中的一种或几种组成。
@@ -110,6 +466,32 @@ pub struct StoreInfo {
     pub address: Option<String>,
 }
 
+impl StoreInfo {
+    pub fn new<S: AsRef<str>>(id: S) -> Self {
+        Self {
+            id: id.as_ref().to_string(),
+            name: None,
+            area_code: None,
+            address: None,
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_area_code(mut self, area_code: impl Into<String>) -> Self {
+        self.area_code = Some(area_code.into());
+        self
+    }
+
+    pub fn with_address(mut self, address: impl Into<String>) -> Self {
+        self.address = Some(address.into());
+        self
+    }
+}
+
 #[derive(Serialize, Debug, Clone)]
 pub struct SceneInfo {
     ///【用户终端IP】 用户的客户端IP，支持IPv4和IPv6两种格式的IP地址。
@@ -122,6 +504,21 @@ pub struct SceneInfo {
     pub store_info: Option<StoreInfo>,
 }
 
+/// 账单压缩类型，不传时返回账单原文
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TarType {
+    /// 返回gzip压缩的账单，下载内容需自行解压
+    Gzip,
+}
+
+impl Display for TarType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TarType::Gzip => write!(f, "GZIP"),
+        }
+    }
+}
+
 #[derive(Serialize, Debug, Clone)]
 pub enum H5Type {
     Ios,
@@ -187,6 +584,72 @@ impl H5SceneInfo {
             store_info: None,
         }
     }
+
+    /// H5场景信息构建器，未设置的字段不参与序列化
+    pub fn builder<S: AsRef<str>>(payer_client_ip: S, h5_type: H5Type) -> H5SceneInfoBuilder {
+        H5SceneInfoBuilder {
+            scene_info: Self {
+                payer_client_ip: payer_client_ip.as_ref().to_string(),
+                h5_info: H5Info {
+                    h5_type: h5_type.to_string(),
+                    app_name: None,
+                    app_url: None,
+                    bundle_id: None,
+                    package_name: None,
+                },
+                device_id: None,
+                store_info: None,
+            },
+        }
+    }
+}
+
+/// H5场景信息的构建器
+#[derive(Debug, Clone)]
+pub struct H5SceneInfoBuilder {
+    scene_info: H5SceneInfo,
+}
+
+impl H5SceneInfoBuilder {
+    ///【应用名称】
+    pub fn app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.scene_info.h5_info.app_name = Some(app_name.into());
+        self
+    }
+
+    ///【网站URL】
+    pub fn app_url(mut self, app_url: impl Into<String>) -> Self {
+        self.scene_info.h5_info.app_url = Some(app_url.into());
+        self
+    }
+
+    ///【iOS平台BundleID】
+    pub fn bundle_id(mut self, bundle_id: impl Into<String>) -> Self {
+        self.scene_info.h5_info.bundle_id = Some(bundle_id.into());
+        self
+    }
+
+    ///【Android平台PackageName】
+    pub fn package_name(mut self, package_name: impl Into<String>) -> Self {
+        self.scene_info.h5_info.package_name = Some(package_name.into());
+        self
+    }
+
+    ///【商户端设备号】 门店号或收银设备ID
+    pub fn device_id(mut self, device_id: impl Into<String>) -> Self {
+        self.scene_info.device_id = Some(device_id.into());
+        self
+    }
+
+    ///【商户门店信息】
+    pub fn store_info(mut self, store_info: StoreInfo) -> Self {
+        self.scene_info.store_info = Some(store_info);
+        self
+    }
+
+    pub fn build(self) -> H5SceneInfo {
+        self.scene_info
+    }
 }
 
 unsafe impl Send for SceneInfo {}
@@ -203,6 +666,10 @@ impl ParamsTrait for SceneInfo {
 pub struct JsapiParams {
     ///【商品描述】 商品描述
     pub description: String,
+    ///【通知地址】 异步接收微信支付结果通知的回调地址，通知URL必须为外网可访问的URL，不能携带参数。 公网域名必须为HTTPS，如果是走专线接入，使用专线NAT IP或者私有回调域名可使用HTTP
+    /// 设置后覆盖WechatPay上配置的notify_url
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_url: Option<String>,
     ///【商户订单号】 商户系统内部订单号，只能是数字、大小写字母_-*且在同一个商户号下唯一。
     pub out_trade_no: String,
     ///【订单金额】 订单金额信息
@@ -212,27 +679,50 @@ pub struct JsapiParams {
     ///【附加数据】 附加数据，在查询API和支付通知中原样返回，可作为自定义参数使用，实际情况下只有支付完成状态才会返回该字段。
     #[serde(skip_serializing_if = "Option::is_none")]
     pub attach: Option<String>,
+    ///【订单优惠标记】 商品标记，代金券或立减优惠功能的参数。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goods_tag: Option<String>,
     ///【优惠功能】 优惠功能
     #[serde(skip_serializing_if = "Option::is_none")]
     pub detail: Option<OrderDetail>,
     ///【交易结束时间】 订单失效时间，遵循rfc3339标准格式，格式为yyyy-MM-DDTHH:mm:ss+TIMEZONE，yyyy-MM-DD表示年月日，T出现在字符串中，表示time元素的开头，HH:mm:ss表示时分秒，TIMEZONE表示时区（+08:00表示东八区时间，领先UTC8小时，即北京时间）。例如：2015-05-20T13:29:35+08:00表示，北京时间2015年5月20日13点29分35秒。
     #[serde(skip_serializing_if = "Option::is_none")]
     pub time_expire: Option<String>,
+    ///【电子发票入口开放标识】 传入true时，支付成功消息和支付详情页将出现开票入口。需要在微信支付商户平台或微信公众平台开通电子发票功能，传此字段才可生效。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub support_fapiao: Option<bool>,
     ///【场景信息】 支付场景描述
     #[serde(skip_serializing_if = "Option::is_none")]
     pub scene_info: Option<SceneInfo>,
+    ///【结算信息】 结算信息，需要分账时设置profit_sharing为true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settle_info: Option<SettleInfo>,
 }
 
 impl ParamsTrait for JsapiParams {
     fn to_json(&self) -> String {
         serde_json::to_string(self).unwrap()
     }
+
+    fn validate(&self) -> Result<(), PayError> {
+        TradeNo::validate(&self.out_trade_no)?;
+        self.payer.validate()?;
+        self.amount.validate()
+    }
+
+    fn notify_url(&self) -> Option<&str> {
+        self.notify_url.as_deref()
+    }
 }
 
 #[derive(Serialize, Debug, Clone)]
 pub struct MicroParams {
     ///【商品描述】 商品描述
     pub description: String,
+    ///【通知地址】 异步接收微信支付结果通知的回调地址，通知URL必须为外网可访问的URL，不能携带参数。 公网域名必须为HTTPS，如果是走专线接入，使用专线NAT IP或者私有回调域名可使用HTTP
+    /// 设置后覆盖WechatPay上配置的notify_url
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_url: Option<String>,
     ///【商户订单号】 商户系统内部订单号，只能是数字、大小写字母_-*且在同一个商户号下唯一。
     pub out_trade_no: String,
     ///【订单金额】 订单金额信息
@@ -242,39 +732,82 @@ pub struct MicroParams {
     ///【附加数据】 附加数据，在查询API和支付通知中原样返回，可作为自定义参数使用，实际情况下只有支付完成状态才会返回该字段。
     #[serde(skip_serializing_if = "Option::is_none")]
     pub attach: Option<String>,
+    ///【订单优惠标记】 商品标记，代金券或立减优惠功能的参数。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goods_tag: Option<String>,
     ///【优惠功能】 优惠功能
     #[serde(skip_serializing_if = "Option::is_none")]
     pub detail: Option<OrderDetail>,
     ///【交易结束时间】 订单失效时间，遵循rfc3339标准格式，格式为yyyy-MM-DDTHH:mm:ss+TIMEZONE，yyyy-MM-DD表示年月日，T出现在字符串中，表示time元素的开头，HH:mm:ss表示时分秒，TIMEZONE表示时区（+08:00表示东八区时间，领先UTC8小时，即北京时间）。例如：2015-05-20T13:29:35+08:00表示，北京时间2015年5月20日13点29分35秒。
     #[serde(skip_serializing_if = "Option::is_none")]
     pub time_expire: Option<String>,
+    ///【电子发票入口开放标识】 传入true时，支付成功消息和支付详情页将出现开票入口。需要在微信支付商户平台或微信公众平台开通电子发票功能，传此字段才可生效。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub support_fapiao: Option<bool>,
     ///【场景信息】 支付场景描述
     #[serde(skip_serializing_if = "Option::is_none")]
     pub scene_info: Option<SceneInfo>,
+    ///【结算信息】 结算信息，需要分账时设置profit_sharing为true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settle_info: Option<SettleInfo>,
 }
 
 impl ParamsTrait for MicroParams {
     fn to_json(&self) -> String {
         serde_json::to_string(self).unwrap()
     }
+
+    fn validate(&self) -> Result<(), PayError> {
+        TradeNo::validate(&self.out_trade_no)?;
+        self.payer.validate()?;
+        self.amount.validate()
+    }
+
+    fn notify_url(&self) -> Option<&str> {
+        self.notify_url.as_deref()
+    }
 }
 
 impl MicroParams {
     pub fn new<S: AsRef<str>>(
         description: S,
-        out_trade_no: S,
+        out_trade_no: impl AsRef<str>,
         amount: AmountInfo,
         payer: PayerInfo,
     ) -> Self {
         Self {
             description: description.as_ref().to_string(),
+            notify_url: None,
             out_trade_no: out_trade_no.as_ref().to_string(),
             amount,
             payer,
             time_expire: None,
             attach: None,
+            goods_tag: None,
             detail: None,
+            support_fapiao: None,
             scene_info: None,
+            settle_info: None,
+        }
+    }
+}
+
+/// 小程序下单与JSAPI下单使用同一个接口和参数
+impl From<MicroParams> for JsapiParams {
+    fn from(value: MicroParams) -> Self {
+        Self {
+            description: value.description,
+            notify_url: value.notify_url,
+            out_trade_no: value.out_trade_no,
+            amount: value.amount,
+            payer: value.payer,
+            time_expire: value.time_expire,
+            attach: value.attach,
+            goods_tag: value.goods_tag,
+            detail: value.detail,
+            support_fapiao: value.support_fapiao,
+            scene_info: value.scene_info,
+            settle_info: value.settle_info,
         }
     }
 }
@@ -282,30 +815,43 @@ impl MicroParams {
 impl JsapiParams {
     pub fn new<S: AsRef<str>>(
         description: S,
-        out_trade_no: S,
+        out_trade_no: impl AsRef<str>,
         amount: AmountInfo,
         payer: PayerInfo,
     ) -> Self {
         Self {
             description: description.as_ref().to_string(),
+            notify_url: None,
             out_trade_no: out_trade_no.as_ref().to_string(),
             amount,
             payer,
             time_expire: None,
             attach: None,
+            goods_tag: None,
             detail: None,
+            support_fapiao: None,
             scene_info: None,
+            settle_info: None,
         }
     }
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, Default)]
 pub struct SettleInfo {
     ///【是否指定分账】 是否指定分账，
     #[serde(skip_serializing_if = "Option::is_none")]
     pub profit_sharing: Option<bool>,
 }
 
+impl SettleInfo {
+    /// 指定分账，支付成功后资金冻结，需调用分账接口解冻或分账
+    pub fn profit_sharing() -> Self {
+        Self {
+            profit_sharing: Some(true),
+        }
+    }
+}
+
 unsafe impl Send for SettleInfo {}
 
 unsafe impl Sync for SettleInfo {}
@@ -315,7 +861,9 @@ pub struct NativeParams {
     ///【商品描述】 商品描述
     pub description: String,
     ///【通知地址】 异步接收微信支付结果通知的回调地址，通知URL必须为外网可访问的URL，不能携带参数。 公网域名必须为HTTPS，如果是走专线接入，使用专线NAT IP或者私有回调域名可使用HTTP
-    /// pub notify_url: String,
+    /// 设置后覆盖WechatPay上配置的notify_url
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_url: Option<String>,
     ///【商户订单号】 商户系统内部订单号，只能是数字、大小写字母_-*且在同一个商户号下唯一。
     pub out_trade_no: String,
     ///【订单金额】 订单金额信息
@@ -344,6 +892,15 @@ impl ParamsTrait for NativeParams {
     fn to_json(&self) -> String {
         serde_json::to_string(self).unwrap()
     }
+
+    fn validate(&self) -> Result<(), PayError> {
+        TradeNo::validate(&self.out_trade_no)?;
+        self.amount.validate()
+    }
+
+    fn notify_url(&self) -> Option<&str> {
+        self.notify_url.as_deref()
+    }
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -351,7 +908,9 @@ pub struct AppParams {
     ///【商品描述】 商品描述
     pub description: String,
     ///【通知地址】 异步接收微信支付结果通知的回调地址，通知URL必须为外网可访问的URL，不能携带参数。 公网域名必须为HTTPS，如果是走专线接入，使用专线NAT IP或者私有回调域名可使用HTTP
-    /// pub notify_url: String,
+    /// 设置后覆盖WechatPay上配置的notify_url
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_url: Option<String>,
     ///【商户订单号】 商户系统内部订单号，只能是数字、大小写字母_-*且在同一个商户号下唯一。
     pub out_trade_no: String,
     ///【订单金额】 订单金额信息
@@ -383,12 +942,26 @@ impl ParamsTrait for AppParams {
     fn to_json(&self) -> String {
         serde_json::to_string(self).unwrap()
     }
+
+    fn validate(&self) -> Result<(), PayError> {
+        TradeNo::validate(&self.out_trade_no)?;
+        self.amount.validate()
+    }
+
+    fn notify_url(&self) -> Option<&str> {
+        self.notify_url.as_deref()
+    }
 }
 
 impl AppParams {
-    pub fn new<S: AsRef<str>>(description: S, out_trade_no: S, amount: AmountInfo) -> Self {
+    pub fn new<S: AsRef<str>>(
+        description: S,
+        out_trade_no: impl AsRef<str>,
+        amount: AmountInfo,
+    ) -> Self {
         Self {
             description: description.as_ref().to_string(),
+            notify_url: None,
             out_trade_no: out_trade_no.as_ref().to_string(),
             amount,
             time_expire: None,
@@ -407,7 +980,9 @@ pub struct H5Params {
     ///【商品描述】 商品描述
     pub description: String,
     ///【通知地址】 异步接收微信支付结果通知的回调地址，通知URL必须为外网可访问的URL，不能携带参数。 公网域名必须为HTTPS，如果是走专线接入，使用专线NAT IP或者私有回调域名可使用HTTP
-    /// pub notify_url: String,
+    /// 设置后覆盖WechatPay上配置的notify_url
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_url: Option<String>,
     ///【商户订单号】 商户系统内部订单号，只能是数字、大小写字母_-*且在同一个商户号下唯一。
     pub out_trade_no: String,
     ///【订单金额】 订单金额信息
@@ -435,17 +1010,27 @@ impl ParamsTrait for H5Params {
     fn to_json(&self) -> String {
         serde_json::to_string(self).unwrap()
     }
+
+    fn validate(&self) -> Result<(), PayError> {
+        TradeNo::validate(&self.out_trade_no)?;
+        self.amount.validate()
+    }
+
+    fn notify_url(&self) -> Option<&str> {
+        self.notify_url.as_deref()
+    }
 }
 
 impl H5Params {
     pub fn new<S: AsRef<str>>(
         description: S,
-        out_trade_no: S,
+        out_trade_no: impl AsRef<str>,
         amount: AmountInfo,
         scene_info: H5SceneInfo,
     ) -> Self {
         Self {
             description: description.as_ref().to_string(),
+            notify_url: None,
             out_trade_no: out_trade_no.as_ref().to_string(),
             amount,
             time_expire: None,
@@ -459,9 +1044,14 @@ impl H5Params {
 }
 
 impl NativeParams {
-    pub fn new<S: AsRef<str>>(description: S, out_trade_no: S, amount: AmountInfo) -> Self {
+    pub fn new<S: AsRef<str>>(
+        description: S,
+        out_trade_no: impl AsRef<str>,
+        amount: AmountInfo,
+    ) -> Self {
         Self {
             description: description.as_ref().to_string(),
+            notify_url: None,
             out_trade_no: out_trade_no.as_ref().to_string(),
             amount,
             time_expire: None,
@@ -491,6 +1081,37 @@ pub struct WechatPayNotifySource {
     pub nonce: String,
 }
 
+/// 微信支付使用的加密算法
+pub const AEAD_AES_256_GCM: &str = "AEAD_AES_256_GCM";
+
+/// AEAD_AES_256_GCM加密的资源，如回调通知的resource、平台证书的encrypt_certificate
+/// 附加数据随资源类型不同(certificate、transaction、refund等)，解密时nonce和associated_data都取自资源本身
+pub trait AeadResource {
+    fn algorithm(&self) -> &str;
+    fn ciphertext(&self) -> &str;
+    fn nonce(&self) -> &str;
+    /// 附加数据，可能为空
+    fn associated_data(&self) -> &str;
+}
+
+impl AeadResource for WechatPayNotifySource {
+    fn algorithm(&self) -> &str {
+        self.algorithm.as_str()
+    }
+
+    fn ciphertext(&self) -> &str {
+        self.ciphertext.as_str()
+    }
+
+    fn nonce(&self) -> &str {
+        self.nonce.as_str()
+    }
+
+    fn associated_data(&self) -> &str {
+        self.associated_data.as_deref().unwrap_or_default()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct WechatPayNotify {
     pub id: String,
@@ -501,6 +1122,14 @@ pub struct WechatPayNotify {
     pub summary: String,
 }
 
+#[cfg(feature = "chrono")]
+impl WechatPayNotify {
+    /// 解析后的通知创建时间，格式不符合预期时返回None
+    pub fn created_at(&self) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+        crate::time::parse_option(Some(self.create_time.as_str()))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct WechatPayDecodeData {
     pub mchid: String,
@@ -513,11 +1142,214 @@ pub struct WechatPayDecodeData {
     pub bank_type: String,
     pub attach: String,
     pub success_time: String,
-    pub payer: PayerInfo,
-    pub amount: AmountInfo,
+    #[serde(default)]
+    pub payer: Payer,
+    pub amount: QueryAmount,
+    ///【优惠功能】 享受优惠时返回，列出订单使用的代金券
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub promotion_detail: Option<Vec<PromotionDetailResponse>>,
+    #[serde(skip)]
+    raw: serde_json::Value,
 }
 
-#[derive(Serialize, Debug, Clone)]
+impl WechatPayDecodeData {
+    /// 订单使用的代金券，没有优惠时为空
+    pub fn promotions(&self) -> &[PromotionDetailResponse] {
+        self.promotion_detail.as_deref().unwrap_or_default()
+    }
+
+    /// 原始JSON，可读取尚未建模的新字段
+    pub fn raw(&self) -> &serde_json::Value {
+        &self.raw
+    }
+
+    /// 将解密后的支付通知与商户保存的订单核对，防止通知被篡改或关联到错误的订单
+    /// out_trade_no、订单总金额(分)都一致且trade_state为SUCCESS时返回`PaymentConfirmation`
+    /// 否则返回`PayError::PaymentMismatch`，其中携带不一致的字段
+    pub fn confirm(
+        &self,
+        out_trade_no: impl AsRef<str>,
+        total: i32,
+    ) -> Result<PaymentConfirmation, PayError> {
+        let mismatch = |field, expected: String, actual: String| PayError::PaymentMismatch {
+            field,
+            expected,
+            actual,
+        };
+        if self.out_trade_no != out_trade_no.as_ref() {
+            return Err(mismatch(
+                "out_trade_no",
+                out_trade_no.as_ref().to_string(),
+                self.out_trade_no.clone(),
+            ));
+        }
+        if self.amount.total != total {
+            return Err(mismatch(
+                "amount.total",
+                total.to_string(),
+                self.amount.total.to_string(),
+            ));
+        }
+        if self.trade_state != "SUCCESS" {
+            return Err(mismatch(
+                "trade_state",
+                "SUCCESS".to_string(),
+                self.trade_state.clone(),
+            ));
+        }
+        Ok(PaymentConfirmation {
+            out_trade_no: self.out_trade_no.clone(),
+            transaction_id: self.transaction_id.clone(),
+            total: self.amount.total,
+            payer_total: self.amount.payer_total,
+            openid: self.payer.openid.clone(),
+            success_time: self.success_time.clone(),
+        })
+    }
+}
+
+/// 支付通知与商户订单核对一致后的支付结果，由`WechatPayDecodeData::confirm`返回
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PaymentConfirmation {
+    ///【商户订单号】
+    pub out_trade_no: String,
+    ///【微信支付订单号】
+    pub transaction_id: String,
+    ///【总金额】 单位为分
+    pub total: i32,
+    ///【用户支付金额】 单位为分
+    pub payer_total: Option<i32>,
+    ///【用户标识】
+    pub openid: Option<String>,
+    ///【支付完成时间】
+    pub success_time: String,
+}
+
+/// 退款回调金额信息
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RefundNotifyAmount {
+    ///【订单金额】 单位为分
+    pub total: i64,
+    ///【退款金额】 单位为分
+    pub refund: i64,
+    ///【用户支付金额】 单位为分
+    pub payer_total: i64,
+    ///【用户退款金额】 单位为分，不包含所有优惠券金额
+    pub payer_refund: i64,
+}
+
+/// 退款回调解密后的数据，事件类型为REFUND.SUCCESS、REFUND.ABNORMAL、REFUND.CLOSED
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RefundNotify {
+    ///【直连商户号】
+    pub mchid: String,
+    ///【商户订单号】
+    pub out_trade_no: String,
+    ///【微信支付订单号】
+    pub transaction_id: String,
+    ///【商户退款单号】
+    pub out_refund_no: String,
+    ///【微信支付退款单号】
+    pub refund_id: String,
+    ///【退款状态】 SUCCESS、CLOSED、ABNORMAL
+    pub refund_status: RefundStatus,
+    ///【退款成功时间】 退款状态为SUCCESS时返回
+    pub success_time: Option<String>,
+    ///【退款入账账户】 如"招商银行信用卡0403"、"支付用户零钱"
+    pub user_received_account: String,
+    ///【金额信息】
+    pub amount: RefundNotifyAmount,
+}
+
+impl ResponseTrait for RefundNotify {}
+
+/// 商家转账回调解密后的数据，事件类型为MCHTRANSFER.BILL.FINISHED
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TransferNotify {
+    ///【商户号】
+    pub mch_id: String,
+    ///【商户单号】
+    pub out_bill_no: String,
+    ///【微信转账单号】
+    pub transfer_bill_no: String,
+    ///【单据状态】 SUCCESS、FAIL、CANCELLED
+    pub state: TransferBillState,
+    ///【转账金额】 单位为分
+    pub transfer_amount: i64,
+    ///【收款用户OpenID】
+    pub openid: String,
+    ///【失败原因】 单据状态为FAIL时返回
+    pub fail_reason: Option<String>,
+    ///【单据创建时间】 遵循rfc3339标准格式
+    pub create_time: String,
+    ///【最后一次状态变更时间】 遵循rfc3339标准格式
+    pub update_time: String,
+}
+
+impl ResponseTrait for TransferNotify {}
+
+/// 分账回调中的分账接收方
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProfitSharingNotifyReceiver {
+    ///【分账接收方类型】 MERCHANT_ID、PERSONAL_OPENID
+    #[serde(rename = "type")]
+    pub receiver_type: String,
+    ///【分账接收方账号】
+    pub account: String,
+    ///【分账动账金额】 单位为分
+    pub amount: i64,
+    ///【分账/回退描述】
+    pub description: String,
+}
+
+/// 分账动账回调解密后的数据，事件类型为PROFITSHARING.SUCCESS、PROFITSHARING.RETURN
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProfitSharingNotify {
+    ///【直连商户号】 直连模式下返回
+    pub mchid: Option<String>,
+    ///【服务商商户号】 服务商模式下返回
+    pub sp_mchid: Option<String>,
+    ///【子商户号】 服务商模式下返回
+    pub sub_mchid: Option<String>,
+    ///【微信支付订单号】
+    pub transaction_id: String,
+    ///【微信分账/回退单号】
+    pub order_id: String,
+    ///【商户分账/回退单号】
+    pub out_order_no: String,
+    ///【分账接收方】
+    pub receiver: ProfitSharingNotifyReceiver,
+    ///【成功时间】 遵循rfc3339标准格式
+    pub success_time: String,
+}
+
+impl ResponseTrait for ProfitSharingNotify {}
+
+#[cfg(feature = "chrono")]
+impl RefundNotify {
+    /// 解析后的退款成功时间，退款未成功或格式不符合预期时返回None
+    pub fn success_at(&self) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+        crate::time::parse_option(self.success_time.as_deref())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl WechatPayDecodeData {
+    /// 解析后的支付完成时间，格式不符合预期时返回None，仍可读取success_time原始字符串
+    pub fn success_at(&self) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+        crate::time::parse_option(Some(self.success_time.as_str()))
+    }
+}
+
+impl ResponseTrait for WechatPayDecodeData {
+    const KEEP_RAW: bool = true;
+
+    fn set_raw(&mut self, raw: serde_json::Value) {
+        self.raw = raw;
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
 pub struct RefundsParams {
     /// 【微信支付订单号】 微信支付侧订单的唯一标识，订单支付成功后，查询订单和支付成功回调通知会返回该参数。
     /// transaction_id和out_trade_no必须二选一进行传参。
@@ -627,4 +1459,1992 @@ impl ParamsTrait for RefundsParams {
     fn to_json(&self) -> String {
         serde_json::to_string(self).unwrap()
     }
+
+    fn notify_url(&self) -> Option<&str> {
+        self.notify_url.as_deref()
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FapiaoBuyerInformation {
+    ///【购买方类型】 INDIVIDUAL: 个人 ORGANIZATION: 单位
+    #[serde(rename = "type")]
+    pub buyer_type: String,
+    ///【名称】
+    pub name: String,
+    ///【纳税人识别号】 购买方为单位时必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub taxpayer_id: Option<String>,
+    ///【地址】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    ///【电话】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub telephone: Option<String>,
+    ///【开户银行】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bank_name: Option<String>,
+    ///【银行账号】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bank_account: Option<String>,
+    ///【手机号】 使用平台证书公钥加密
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    ///【邮箱地址】 使用平台证书公钥加密
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    ///【总价税合计】 单位为分，微信支付场景下用于校验开票金额
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<i64>,
+    ///【商户订单号】 微信支付场景下必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub out_trade_no: Option<String>,
+}
+
+impl FapiaoBuyerInformation {
+    pub fn new<S: AsRef<str>>(buyer_type: S, name: S) -> Self {
+        Self {
+            buyer_type: buyer_type.as_ref().to_string(),
+            name: name.as_ref().to_string(),
+            taxpayer_id: None,
+            address: None,
+            telephone: None,
+            bank_name: None,
+            bank_account: None,
+            phone: None,
+            email: None,
+            amount: None,
+            out_trade_no: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FapiaoItem {
+    ///【税局侧规定的货物或应税劳务、服务税收分类编码】
+    pub tax_code: String,
+    ///【货物或应税劳务、服务名称】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goods_name: Option<String>,
+    ///【规格型号】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub specification: Option<String>,
+    ///【单位】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    ///【数量】 单位为10^-8，如数量为1时传100000000
+    pub quantity: i64,
+    ///【单行金额合计】 单位为分，含税
+    pub total_amount: i64,
+    ///【税率】 万分数，如13%传1300
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax_rate: Option<i64>,
+    ///【税收优惠政策标识】 NO_FAVORABLE: 无优惠 OUTSIDE_VAT: 不征税 FREE: 免税 ...
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax_prefer_mark: Option<String>,
+    ///【是否折扣行】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discount: Option<bool>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FapiaoInformationParams {
+    ///【商户发票单号】
+    pub fapiao_id: String,
+    ///【总价税合计】 单位为分
+    pub total_amount: i64,
+    ///【是否以清单形式开具发票】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub need_list: Option<bool>,
+    ///【备注】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remark: Option<String>,
+    ///【发票行信息】
+    pub items: Vec<FapiaoItem>,
+}
+
+/// 开具电子发票
+#[derive(Serialize, Debug, Clone)]
+pub struct FapiaoApplicationParams {
+    ///【开票场景】 WITH_WECHATPAY: 微信支付场景 WITHOUT_WECHATPAY: 非微信支付场景
+    pub scene: String,
+    ///【发票申请单号】 商户系统内部唯一
+    pub fapiao_apply_id: String,
+    ///【购买方信息】
+    pub buyer_information: FapiaoBuyerInformation,
+    ///【需要开具的发票信息】
+    pub fapiao_information: Vec<FapiaoInformationParams>,
+}
+
+impl FapiaoApplicationParams {
+    pub fn new<S: AsRef<str>>(
+        scene: S,
+        fapiao_apply_id: S,
+        buyer_information: FapiaoBuyerInformation,
+        fapiao_information: Vec<FapiaoInformationParams>,
+    ) -> Self {
+        Self {
+            scene: scene.as_ref().to_string(),
+            fapiao_apply_id: fapiao_apply_id.as_ref().to_string(),
+            buyer_information,
+            fapiao_information,
+        }
+    }
+}
+
+impl ParamsTrait for FapiaoApplicationParams {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProfitSharingReceiverParams {
+    ///【分账接收方类型】 MERCHANT_ID: 商户号 PERSONAL_OPENID: 个人openid
+    #[serde(rename = "type")]
+    pub receiver_type: String,
+    ///【分账接收方账号】
+    pub account: String,
+    ///【分账个人接收方姓名】 使用平台证书公钥加密，接收方类型为商户号时需与商户全称一致
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    ///【分账金额】 单位为分
+    pub amount: i64,
+    ///【分账描述】
+    pub description: String,
+}
+
+/// 分账接收方的分账份额
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfitSharingShare {
+    /// 固定金额，单位为分
+    Fixed(i64),
+    /// 按比例分账，万分比，如1500表示15%
+    BasisPoints(u32),
+}
+
+/// 分账方案，按固定金额或比例计算各接收方的分账金额
+/// 比例部分按最大余数法取整：先向下取整，剩余的分按舍去部分从大到小依次补1分，舍去部分相同时按添加顺序，
+/// 保证比例之和为100%时金额之和恰好等于可分账金额
+#[derive(Debug, Clone)]
+pub struct ProfitSharingPlan {
+    /// 可分账金额，单位为分，一般为订单金额或剩余待分金额
+    amount: i64,
+    receivers: Vec<(ProfitSharingReceiverParams, ProfitSharingShare)>,
+}
+
+impl ProfitSharingPlan {
+    pub fn new(amount: i64) -> Self {
+        Self {
+            amount,
+            receivers: vec![],
+        }
+    }
+
+    /// 添加固定金额的接收方，单位为分
+    pub fn with_fixed<S: AsRef<str>>(
+        self,
+        receiver_type: S,
+        account: S,
+        description: S,
+        amount: i64,
+    ) -> Self {
+        self.with_receiver(
+            receiver_type,
+            account,
+            description,
+            ProfitSharingShare::Fixed(amount),
+        )
+    }
+
+    /// 添加按比例分账的接收方，万分比，如1500表示15%
+    pub fn with_percentage<S: AsRef<str>>(
+        self,
+        receiver_type: S,
+        account: S,
+        description: S,
+        basis_points: u32,
+    ) -> Self {
+        self.with_receiver(
+            receiver_type,
+            account,
+            description,
+            ProfitSharingShare::BasisPoints(basis_points),
+        )
+    }
+
+    fn with_receiver<S: AsRef<str>>(
+        mut self,
+        receiver_type: S,
+        account: S,
+        description: S,
+        share: ProfitSharingShare,
+    ) -> Self {
+        self.receivers.push((
+            ProfitSharingReceiverParams {
+                receiver_type: receiver_type.as_ref().to_string(),
+                account: account.as_ref().to_string(),
+                name: None,
+                amount: 0,
+                description: description.as_ref().to_string(),
+            },
+            share,
+        ));
+        self
+    }
+
+    /// 计算各接收方的分账金额，金额之和超过可分账金额或某个接收方金额不足1分时返回`PayError::InvalidParams`
+    pub fn build(self) -> Result<Vec<ProfitSharingReceiverParams>, PayError> {
+        if self.amount <= 0 {
+            return Err(PayError::InvalidParams(format!(
+                "profit sharing amount must be greater than 0, got {}",
+                self.amount
+            )));
+        }
+        let total_basis_points: u64 = self
+            .receivers
+            .iter()
+            .filter_map(|(_, share)| match share {
+                ProfitSharingShare::BasisPoints(basis_points) => Some(*basis_points as u64),
+                ProfitSharingShare::Fixed(_) => None,
+            })
+            .sum();
+        if total_basis_points > 10000 {
+            return Err(PayError::InvalidParams(format!(
+                "profit sharing percentage exceeds 100%: {} basis points",
+                total_basis_points
+            )));
+        }
+        let amount = self.amount as i128;
+        let mut receivers = self.receivers;
+        // 比例部分向下取整后的金额和舍去部分
+        let mut remainders = vec![];
+        let mut floor_sum = 0;
+        for (index, (receiver, share)) in receivers.iter_mut().enumerate() {
+            match *share {
+                ProfitSharingShare::Fixed(fixed) => receiver.amount = fixed,
+                ProfitSharingShare::BasisPoints(basis_points) => {
+                    let exact = amount * basis_points as i128;
+                    receiver.amount = (exact / 10000) as i64;
+                    floor_sum += exact / 10000;
+                    remainders.push((exact % 10000, index));
+                }
+            }
+        }
+        let target = amount * total_basis_points as i128 / 10000;
+        remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        for (_, index) in remainders.iter().take((target - floor_sum) as usize) {
+            receivers[*index].0.amount += 1;
+        }
+        let receivers: Vec<ProfitSharingReceiverParams> = receivers
+            .into_iter()
+            .map(|(receiver, _)| receiver)
+            .collect();
+        if let Some(receiver) = receivers.iter().find(|receiver| receiver.amount <= 0) {
+            return Err(PayError::InvalidParams(format!(
+                "profit sharing amount for {} must be at least 1 cent",
+                receiver.account
+            )));
+        }
+        let total: i64 = receivers.iter().map(|receiver| receiver.amount).sum();
+        if total > self.amount {
+            return Err(PayError::InvalidParams(format!(
+                "profit sharing total {} exceeds amount {}",
+                total, self.amount
+            )));
+        }
+        Ok(receivers)
+    }
+}
+
+/// 请求分账
+#[derive(Serialize, Debug, Clone)]
+pub struct ProfitSharingOrderParams {
+    ///【应用ID】 未设置时使用WechatPay的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    ///【微信订单号】
+    pub transaction_id: String,
+    ///【商户分账单号】 商户系统内部唯一
+    pub out_order_no: String,
+    ///【分账接收方列表】
+    pub receivers: Vec<ProfitSharingReceiverParams>,
+    ///【是否解冻剩余未分资金】 true时剩余资金解冻给本商户，false时可继续分账
+    pub unfreeze_unsplit: bool,
+}
+
+impl ProfitSharingOrderParams {
+    pub fn new<S: AsRef<str>>(
+        transaction_id: S,
+        out_order_no: S,
+        receivers: Vec<ProfitSharingReceiverParams>,
+        unfreeze_unsplit: bool,
+    ) -> Self {
+        Self {
+            appid: None,
+            transaction_id: transaction_id.as_ref().to_string(),
+            out_order_no: out_order_no.as_ref().to_string(),
+            receivers,
+            unfreeze_unsplit,
+        }
+    }
+}
+
+impl ParamsTrait for ProfitSharingOrderParams {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// 解冻剩余资金
+#[derive(Serialize, Debug, Clone)]
+pub struct ProfitSharingUnfreezeParams {
+    ///【微信订单号】
+    pub transaction_id: String,
+    ///【商户分账单号】
+    pub out_order_no: String,
+    ///【分账描述】
+    pub description: String,
+}
+
+impl ProfitSharingUnfreezeParams {
+    pub fn new<S: AsRef<str>>(transaction_id: S, out_order_no: S, description: S) -> Self {
+        Self {
+            transaction_id: transaction_id.as_ref().to_string(),
+            out_order_no: out_order_no.as_ref().to_string(),
+            description: description.as_ref().to_string(),
+        }
+    }
+}
+
+impl ParamsTrait for ProfitSharingUnfreezeParams {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// 请求分账回退，order_id与out_order_no二选一
+#[derive(Serialize, Debug, Clone)]
+pub struct ProfitSharingReturnParams {
+    ///【微信分账单号】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+    ///【商户分账单号】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub out_order_no: Option<String>,
+    ///【商户回退单号】 商户系统内部唯一
+    pub out_return_no: String,
+    ///【回退商户号】 只能对原分账请求中成功分给商户接收方进行回退
+    pub return_mchid: String,
+    ///【回退金额】 单位为分
+    pub amount: i64,
+    ///【回退描述】
+    pub description: String,
+}
+
+impl ProfitSharingReturnParams {
+    pub fn new<S: AsRef<str>>(
+        out_order_no: S,
+        out_return_no: S,
+        return_mchid: S,
+        amount: i64,
+        description: S,
+    ) -> Self {
+        Self {
+            order_id: None,
+            out_order_no: Some(out_order_no.as_ref().to_string()),
+            out_return_no: out_return_no.as_ref().to_string(),
+            return_mchid: return_mchid.as_ref().to_string(),
+            amount,
+            description: description.as_ref().to_string(),
+        }
+    }
+}
+
+impl ParamsTrait for ProfitSharingReturnParams {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    fn validate(&self) -> Result<(), PayError> {
+        if self.order_id.is_none() && self.out_order_no.is_none() {
+            return Err(PayError::InvalidParams(
+                "order_id or out_order_no is required".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 合单关闭订单中的子单
+#[derive(Serialize, Debug, Clone)]
+pub struct CombineCloseSubOrder {
+    ///【子单商户号】
+    pub mchid: String,
+    ///【子单商户订单号】
+    pub out_trade_no: String,
+    ///【二级商户号】 服务商模式下必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_mchid: Option<String>,
+    ///【子商户应用ID】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_appid: Option<String>,
+}
+
+impl CombineCloseSubOrder {
+    pub fn new<S: AsRef<str>>(mchid: S, out_trade_no: S) -> Self {
+        Self {
+            mchid: mchid.as_ref().to_string(),
+            out_trade_no: out_trade_no.as_ref().to_string(),
+            sub_mchid: None,
+            sub_appid: None,
+        }
+    }
+}
+
+/// 合单关闭订单
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct CombineCloseParams {
+    ///【合单商户appid】
+    pub combine_appid: String,
+    ///【子单信息】 最多50单
+    pub sub_orders: Vec<CombineCloseSubOrder>,
+}
+
+impl ParamsTrait for CombineCloseParams {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// 关闭订单
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct CloseOrderParams {
+    ///【直连商户号】
+    pub mchid: String,
+}
+
+impl ParamsTrait for CloseOrderParams {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// 代金券批次的发放规则
+#[derive(Serialize, Debug, Clone)]
+pub struct CouponStockUseRule {
+    ///【发放总上限】 最多发放的券数量
+    pub max_coupons: i64,
+    ///【总预算】 单位为分，等于面额×发放总上限
+    pub max_amount: i64,
+    ///【单天预算发放上限】 单位为分
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_amount_by_day: Option<i64>,
+    ///【单个用户可领个数】
+    pub max_coupons_per_user: i64,
+    ///【是否开启自然人限制】
+    pub natural_person_limit: bool,
+    ///【是否开启防刷拦截】
+    pub prevent_api_abuse: bool,
+}
+
+/// 固定面额满减券
+#[derive(Serialize, Debug, Clone)]
+pub struct FixedNormalCoupon {
+    ///【面额】 单位为分
+    pub coupon_amount: i64,
+    ///【门槛】 消费门槛，单位为分
+    pub transaction_minimum: i64,
+}
+
+/// 代金券核销规则
+#[derive(Serialize, Debug, Clone)]
+pub struct CouponUseRule {
+    ///【固定面额满减券使用规则】
+    pub fixed_normal_coupon: FixedNormalCoupon,
+    ///【订单优惠标记】 与下单时的goods_tag对应
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goods_tag: Option<Vec<String>>,
+    ///【可核销商户号】
+    pub available_merchants: Vec<String>,
+}
+
+/// 代金券样式
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CouponStockPatternInfo {
+    ///【使用说明】
+    pub description: String,
+    ///【商户logo】 营销图片上传返回的media_url
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merchant_logo: Option<String>,
+    ///【品牌名称】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merchant_name: Option<String>,
+    ///【背景颜色】 如COLOR020
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+    ///【券详情图片】 营销图片上传返回的media_url
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coupon_image: Option<String>,
+}
+
+/// 创建代金券批次
+#[derive(Serialize, Debug, Clone)]
+pub struct CouponStockParams {
+    ///【批次名称】
+    pub stock_name: String,
+    ///【批次备注】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    ///【归属商户号】
+    pub belong_merchant: String,
+    ///【可用时间-开始时间】 遵循rfc3339标准格式
+    pub available_begin_time: String,
+    ///【可用时间-结束时间】 遵循rfc3339标准格式
+    pub available_end_time: String,
+    ///【发放规则】
+    pub stock_use_rule: CouponStockUseRule,
+    ///【样式设置】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern_info: Option<CouponStockPatternInfo>,
+    ///【核销规则】
+    pub coupon_use_rule: CouponUseRule,
+    ///【营销经费】 true时为免充值代金券
+    pub no_cash: bool,
+    ///【批次类型】 NORMAL: 固定面额满减券批次
+    pub stock_type: String,
+    ///【商户单据号】 商户创建批次的凭据号，重试时使用原单据号
+    pub out_request_no: String,
+}
+
+impl CouponStockParams {
+    pub fn new<S: AsRef<str>>(
+        stock_name: S,
+        belong_merchant: S,
+        available_begin_time: S,
+        available_end_time: S,
+        stock_use_rule: CouponStockUseRule,
+        coupon_use_rule: CouponUseRule,
+        out_request_no: S,
+    ) -> Self {
+        Self {
+            stock_name: stock_name.as_ref().to_string(),
+            comment: None,
+            belong_merchant: belong_merchant.as_ref().to_string(),
+            available_begin_time: available_begin_time.as_ref().to_string(),
+            available_end_time: available_end_time.as_ref().to_string(),
+            stock_use_rule,
+            pattern_info: None,
+            coupon_use_rule,
+            no_cash: false,
+            stock_type: "NORMAL".to_string(),
+            out_request_no: out_request_no.as_ref().to_string(),
+        }
+    }
+}
+
+impl ParamsTrait for CouponStockParams {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+string_enum! {
+    /// 转账单据状态
+    pub enum TransferBillState {
+        /// 转账已受理
+        Accepted => "ACCEPTED",
+        /// 转账锁定资金中
+        Processing => "PROCESSING",
+        /// 待收款用户确认，可拉起小程序确认收款
+        WaitUserConfirm => "WAIT_USER_CONFIRM",
+        /// 转账中
+        Transfering => "TRANSFERING",
+        /// 转账成功
+        Success => "SUCCESS",
+        /// 转账失败
+        Fail => "FAIL",
+        /// 撤销中
+        Canceling => "CANCELING",
+        /// 已撤销
+        Cancelled => "CANCELLED",
+    }
+}
+
+/// 转账场景：现金营销
+#[deprecated(note = "使用TransferScene::CashMarketing")]
+pub const TRANSFER_SCENE_CASH_MARKETING: &str = "1000";
+/// 转账场景：佣金报酬
+#[deprecated(note = "使用TransferScene::Commission")]
+pub const TRANSFER_SCENE_COMMISSION: &str = "1005";
+
+/// 报备信息类型：活动名称，现金营销场景
+pub const REPORT_INFO_ACTIVITY_NAME: &str = "活动名称";
+/// 报备信息类型：奖励说明，现金营销场景
+pub const REPORT_INFO_REWARD_DESCRIPTION: &str = "奖励说明";
+/// 报备信息类型：岗位类型，佣金报酬场景
+pub const REPORT_INFO_JOB_TYPE: &str = "岗位类型";
+/// 报备信息类型：报酬说明，佣金报酬场景
+pub const REPORT_INFO_REMUNERATION_DESCRIPTION: &str = "报酬说明";
+
+string_enum! {
+    /// 转账场景，序列化为transfer_scene_id
+    /// 已收录的场景在发送前按`report_info_types`校验报备信息，未收录的场景保留原始id，不做校验
+    pub enum TransferScene {
+        /// 1000 现金营销，报备活动名称、奖励说明
+        CashMarketing => "1000",
+        /// 1005 佣金报酬，报备岗位类型、报酬说明
+        Commission => "1005",
+    }
+}
+
+impl TransferScene {
+    /// 转账场景ID
+    pub fn id(&self) -> &str {
+        self.as_str()
+    }
+
+    /// 场景需要报备的信息类型，未收录的场景返回None
+    pub fn report_info_types(&self) -> Option<&'static [&'static str]> {
+        match self {
+            TransferScene::CashMarketing => {
+                Some(&[REPORT_INFO_ACTIVITY_NAME, REPORT_INFO_REWARD_DESCRIPTION])
+            }
+            TransferScene::Commission => {
+                Some(&[REPORT_INFO_JOB_TYPE, REPORT_INFO_REMUNERATION_DESCRIPTION])
+            }
+            TransferScene::Other(_) => None,
+        }
+    }
+}
+
+/// 转账场景需要报备的信息类型，未收录的场景返回None，不做校验
+#[deprecated(note = "使用TransferScene::from(id).report_info_types()")]
+pub fn transfer_scene_report_info_types(
+    transfer_scene_id: &str,
+) -> Option<&'static [&'static str]> {
+    TransferScene::from(transfer_scene_id).report_info_types()
+}
+
+/// 转账场景报备信息
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TransferSceneReportInfo {
+    ///【信息类型】 不同转账场景需要报备的信息类型不同，见`TransferScene::report_info_types`
+    pub info_type: String,
+    ///【信息内容】
+    pub info_content: String,
+}
+
+impl TransferSceneReportInfo {
+    pub fn new<S: AsRef<str>>(info_type: S, info_content: S) -> Self {
+        Self {
+            info_type: info_type.as_ref().to_string(),
+            info_content: info_content.as_ref().to_string(),
+        }
+    }
+
+    /// 现金营销场景的报备信息：活动名称、奖励说明
+    pub fn cash_marketing<S: AsRef<str>>(activity_name: S, reward_description: S) -> Vec<Self> {
+        vec![
+            Self::new(REPORT_INFO_ACTIVITY_NAME, activity_name.as_ref()),
+            Self::new(REPORT_INFO_REWARD_DESCRIPTION, reward_description.as_ref()),
+        ]
+    }
+
+    /// 佣金报酬场景的报备信息：岗位类型、报酬说明
+    pub fn commission<S: AsRef<str>>(job_type: S, remuneration_description: S) -> Vec<Self> {
+        vec![
+            Self::new(REPORT_INFO_JOB_TYPE, job_type.as_ref()),
+            Self::new(
+                REPORT_INFO_REMUNERATION_DESCRIPTION,
+                remuneration_description.as_ref(),
+            ),
+        ]
+    }
+
+    /// 按转账场景校验报备信息，信息类型需与场景要求一一对应，内容不能为空
+    pub fn validate(transfer_scene_id: &str, infos: &[Self]) -> Result<(), PayError> {
+        if let Some(info) = infos.iter().find(|info| info.info_content.is_empty()) {
+            return Err(PayError::InvalidParams(format!(
+                "transfer_scene_report_infos: {} content is empty",
+                info.info_type
+            )));
+        }
+        let Some(types) = TransferScene::from(transfer_scene_id).report_info_types() else {
+            return Ok(());
+        };
+        let mut actual = infos
+            .iter()
+            .map(|info| info.info_type.as_str())
+            .collect::<Vec<_>>();
+        let mut expected = types.to_vec();
+        actual.sort_unstable();
+        expected.sort_unstable();
+        if actual != expected {
+            return Err(PayError::InvalidParams(format!(
+                "transfer_scene_report_infos: scene {} requires {:?}",
+                transfer_scene_id, types
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 发起转账
+#[derive(Serialize, Debug, Clone)]
+pub struct TransferBillsParams {
+    ///【商户AppID】 未设置时使用WechatPay的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    ///【商户单号】 商户系统内部的商家单号
+    pub out_bill_no: String,
+    ///【转账场景ID】 如1000现金营销
+    pub transfer_scene_id: String,
+    ///【收款用户OpenID】
+    pub openid: String,
+    ///【收款用户姓名】 需使用平台证书公钥加密，转账金额>=2000元时必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_name: Option<String>,
+    ///【转账金额】 单位为分
+    pub transfer_amount: i64,
+    ///【转账备注】 用户收款时可见
+    pub transfer_remark: String,
+    ///【通知地址】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_url: Option<String>,
+    ///【用户收款感知】 用户收款时展示的原因，不传时按场景默认
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_recv_perception: Option<String>,
+    ///【转账场景报备信息】
+    pub transfer_scene_report_infos: Vec<TransferSceneReportInfo>,
+}
+
+impl TransferBillsParams {
+    /// transfer_scene可传`TransferScene`或场景id字符串
+    pub fn new<S: AsRef<str>>(
+        out_bill_no: S,
+        transfer_scene: impl Into<TransferScene>,
+        openid: S,
+        transfer_amount: i64,
+        transfer_remark: S,
+        transfer_scene_report_infos: Vec<TransferSceneReportInfo>,
+    ) -> Self {
+        Self {
+            appid: None,
+            out_bill_no: out_bill_no.as_ref().to_string(),
+            transfer_scene_id: transfer_scene.into().to_string(),
+            openid: openid.as_ref().to_string(),
+            user_name: None,
+            transfer_amount,
+            transfer_remark: transfer_remark.as_ref().to_string(),
+            notify_url: None,
+            user_recv_perception: None,
+            transfer_scene_report_infos,
+        }
+    }
+}
+
+impl TransferBillsParams {
+    /// 转账备注最大长度，按字符计
+    pub const MAX_REMARK_LEN: usize = 32;
+    /// 商户单号最大长度
+    pub const MAX_OUT_BILL_NO_LEN: usize = 32;
+
+    /// 校验参数的构建器，build时校验金额、备注、商户单号和报备信息
+    /// 场景可传`TransferScene`或场景ID，报备信息需与场景要求的信息类型一致
+    pub fn builder<S: AsRef<str>>(
+        out_bill_no: S,
+        transfer_scene: impl Into<TransferScene>,
+        openid: S,
+    ) -> TransferBillsParamsBuilder {
+        TransferBillsParamsBuilder {
+            params: Self::new(
+                out_bill_no.as_ref(),
+                transfer_scene.into().id(),
+                openid.as_ref(),
+                0,
+                "",
+                vec![],
+            ),
+        }
+    }
+
+    /// 转账场景
+    pub fn transfer_scene(&self) -> TransferScene {
+        TransferScene::from(self.transfer_scene_id.as_str())
+    }
+}
+
+impl ParamsTrait for TransferBillsParams {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    fn validate(&self) -> Result<(), PayError> {
+        if self.out_bill_no.is_empty()
+            || self.out_bill_no.len() > Self::MAX_OUT_BILL_NO_LEN
+            || !self.out_bill_no.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(PayError::InvalidParams(format!(
+                "out_bill_no must be 1-{} letters or digits",
+                Self::MAX_OUT_BILL_NO_LEN
+            )));
+        }
+        if self.transfer_amount <= 0 {
+            return Err(PayError::InvalidParams(format!(
+                "transfer_amount must be greater than 0, got {}",
+                self.transfer_amount
+            )));
+        }
+        let remark_len = self.transfer_remark.chars().count();
+        if remark_len == 0 || remark_len > Self::MAX_REMARK_LEN {
+            return Err(PayError::InvalidParams(format!(
+                "transfer_remark must be 1-{} characters",
+                Self::MAX_REMARK_LEN
+            )));
+        }
+        TransferSceneReportInfo::validate(
+            &self.transfer_scene_id,
+            &self.transfer_scene_report_infos,
+        )
+    }
+
+    fn notify_url(&self) -> Option<&str> {
+        self.notify_url.as_deref()
+    }
+}
+
+/// 发起转账参数的构建器
+/// 商户单号(out_bill_no)需在商户系统内唯一，同一单号重复请求不会重复转账，网络异常重试时必须使用原单号
+#[derive(Debug, Clone)]
+pub struct TransferBillsParamsBuilder {
+    params: TransferBillsParams,
+}
+
+impl TransferBillsParamsBuilder {
+    ///【转账金额】 单位为分，必须大于0
+    pub fn transfer_amount(mut self, transfer_amount: i64) -> Self {
+        self.params.transfer_amount = transfer_amount;
+        self
+    }
+
+    ///【转账备注】 不超过32个字符
+    pub fn transfer_remark(mut self, transfer_remark: impl Into<String>) -> Self {
+        self.params.transfer_remark = transfer_remark.into();
+        self
+    }
+
+    ///【转账场景报备信息】
+    pub fn transfer_scene_report_infos(mut self, infos: Vec<TransferSceneReportInfo>) -> Self {
+        self.params.transfer_scene_report_infos = infos;
+        self
+    }
+
+    ///【收款用户姓名】 需使用平台证书公钥加密
+    pub fn user_name(mut self, user_name: impl Into<String>) -> Self {
+        self.params.user_name = Some(user_name.into());
+        self
+    }
+
+    ///【通知地址】
+    pub fn notify_url(mut self, notify_url: impl Into<String>) -> Self {
+        self.params.notify_url = Some(notify_url.into());
+        self
+    }
+
+    ///【用户收款感知】
+    pub fn user_recv_perception(mut self, user_recv_perception: impl Into<String>) -> Self {
+        self.params.user_recv_perception = Some(user_recv_perception.into());
+        self
+    }
+
+    /// 校验参数，不合法时返回`PayError::InvalidParams`
+    pub fn build(self) -> Result<TransferBillsParams, PayError> {
+        self.params.validate()?;
+        Ok(self.params)
+    }
+}
+
+/// 申请转账电子回单
+#[derive(Serialize, Debug, Clone)]
+pub struct TransferReceiptParams {
+    ///【商户单号】 发起转账时的商户单号
+    pub out_bill_no: String,
+}
+
+impl TransferReceiptParams {
+    pub fn new<S: AsRef<str>>(out_bill_no: S) -> Self {
+        Self {
+            out_bill_no: out_bill_no.as_ref().to_string(),
+        }
+    }
+}
+
+impl ParamsTrait for TransferReceiptParams {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+string_enum! {
+    /// 电子回单状态
+    pub enum TransferReceiptState {
+        /// 回单生成中
+        Generating => "GENERATING",
+        /// 回单已生成，可通过download_url下载
+        Finished => "FINISHED",
+        /// 回单生成失败，原因见fail_reason
+        Failed => "FAILED",
+    }
+}
+
+string_enum! {
+    /// 支付分服务订单状态
+    pub enum PayScoreState {
+        /// 商户已创建服务订单
+        Created => "CREATED",
+        /// 服务订单进行中，用户已确认
+        Doing => "DOING",
+        /// 服务订单完成，需结合state_description判断是否已收款
+        Done => "DONE",
+        /// 商户取消服务订单
+        Revoked => "REVOKED",
+        /// 服务订单已失效，用户未确认
+        Expired => "EXPIRED",
+    }
+}
+
+impl PayScoreState {
+    /// 是否为终态，终态的服务订单不能再完结或取消
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            PayScoreState::Done | PayScoreState::Revoked | PayScoreState::Expired
+        )
+    }
+}
+
+/// 支付分后付费项目
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PayScorePostPayment {
+    ///【付费名称】
+    pub name: String,
+    ///【付费金额】 单位为分，创建订单时可不填，完结订单时必填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<i64>,
+    ///【付费说明】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    ///【付费数量】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
+
+impl PayScorePostPayment {
+    pub fn new<S: AsRef<str>>(name: S, amount: i64) -> Self {
+        Self {
+            name: name.as_ref().to_string(),
+            amount: Some(amount),
+            description: None,
+            count: None,
+        }
+    }
+}
+
+/// 支付分商户优惠
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PayScorePostDiscount {
+    ///【优惠名称】
+    pub name: String,
+    ///【优惠说明】
+    pub description: String,
+    ///【优惠金额】 单位为分
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<i64>,
+    ///【优惠数量】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
+
+string_enum! {
+    /// 支付分风险金名称，需与服务ID开通的风险金类型一致，未收录的值下单时校验不通过
+    pub enum RiskFundName {
+        /// 押金
+        Deposit => "DEPOSIT",
+        /// 预付款
+        Advance => "ADVANCE",
+        /// 保证金
+        CashDeposit => "CASH_DEPOSIT",
+        /// 预估订单费用
+        EstimateOrderCost => "ESTIMATE_ORDER_COST",
+    }
+}
+
+/// 支付分订单风险金，使用`PayScoreRiskFund::new`构造
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
+pub struct PayScoreRiskFund {
+    ///【风险金名称】 DEPOSIT: 押金 ADVANCE: 预付款 CASH_DEPOSIT: 保证金 ESTIMATE_ORDER_COST: 预估订单费用
+    pub name: RiskFundName,
+    ///【风险金额】 单位为分，不能超过服务ID对应的风险金额上限
+    pub amount: i64,
+    ///【风险说明】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl PayScoreRiskFund {
+    pub fn new(name: impl Into<RiskFundName>, amount: i64) -> Self {
+        Self {
+            name: name.into(),
+            amount,
+            description: None,
+        }
+    }
+
+    /// 校验风险金名称为已知类型且金额大于0，名称错误时微信只返回笼统的参数错误
+    pub fn validate(&self) -> Result<(), PayError> {
+        if let RiskFundName::Other(name) = &self.name {
+            return Err(PayError::InvalidParams(format!(
+                "unknown risk_fund name {}, expected DEPOSIT, ADVANCE, CASH_DEPOSIT or ESTIMATE_ORDER_COST",
+                name
+            )));
+        }
+        if self.amount <= 0 {
+            return Err(PayError::InvalidParams(
+                "risk_fund amount must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 支付分服务时间段
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PayScoreTimeRange {
+    ///【服务开始时间】 格式为yyyyMMddHHmmss，或OnAccept表示用户确认订单成功时间
+    pub start_time: String,
+    ///【服务开始时间备注】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_time_remark: Option<String>,
+    ///【预计服务结束时间】 格式为yyyyMMddHHmmss
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<String>,
+    ///【预计服务结束时间备注】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_time_remark: Option<String>,
+}
+
+/// 支付分服务位置
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PayScoreLocation {
+    ///【服务开始地点】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_location: Option<String>,
+    ///【预计服务结束位置】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_location: Option<String>,
+}
+
+/// 创建支付分订单
+#[derive(Serialize, Debug, Clone)]
+pub struct PayScoreParams {
+    ///【应用ID】 未设置时使用WechatPay的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    ///【服务ID】
+    pub service_id: String,
+    ///【商户服务订单号】 商户系统内部唯一
+    pub out_order_no: String,
+    ///【服务信息】 用于介绍本订单所提供的服务
+    pub service_introduction: String,
+    ///【后付费项目】
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub post_payments: Vec<PayScorePostPayment>,
+    ///【后付费商户优惠】
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub post_discounts: Vec<PayScorePostDiscount>,
+    ///【服务时间段】
+    pub time_range: PayScoreTimeRange,
+    ///【服务位置】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<PayScoreLocation>,
+    ///【订单风险金】
+    pub risk_fund: PayScoreRiskFund,
+    ///【商户数据包】 回调和查询时原样返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attach: Option<String>,
+    ///【商户回调地址】 未设置时使用WechatPay的notify_url
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_url: Option<String>,
+    ///【用户标识】 需要用户确认时可不填
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub openid: Option<String>,
+    ///【是否需要用户确认】 false时为免确认模式，需要填写openid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub need_user_confirm: Option<bool>,
+}
+
+impl PayScoreParams {
+    pub fn new<S: AsRef<str>>(
+        service_id: S,
+        out_order_no: S,
+        service_introduction: S,
+        time_range: PayScoreTimeRange,
+        risk_fund: PayScoreRiskFund,
+    ) -> Self {
+        Self {
+            appid: None,
+            service_id: service_id.as_ref().to_string(),
+            out_order_no: out_order_no.as_ref().to_string(),
+            service_introduction: service_introduction.as_ref().to_string(),
+            post_payments: vec![],
+            post_discounts: vec![],
+            time_range,
+            location: None,
+            risk_fund,
+            attach: None,
+            notify_url: None,
+            openid: None,
+            need_user_confirm: None,
+        }
+    }
+}
+
+impl ParamsTrait for PayScoreParams {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    fn validate(&self) -> Result<(), PayError> {
+        if self.notify_url.as_deref().unwrap_or_default().is_empty() {
+            return Err(PayError::InvalidParams(
+                "notify_url is required for pay score orders".to_string(),
+            ));
+        }
+        self.risk_fund.validate()?;
+        if self.need_user_confirm == Some(false) && self.openid.is_none() {
+            return Err(PayError::InvalidParams(
+                "openid is required when need_user_confirm is false".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn notify_url(&self) -> Option<&str> {
+        self.notify_url.as_deref()
+    }
+}
+
+/// 完结支付分订单
+#[derive(Serialize, Debug, Clone)]
+pub struct PayScoreCompleteParams {
+    ///【应用ID】 未设置时使用WechatPay的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    ///【服务ID】
+    pub service_id: String,
+    ///【后付费项目】 金额必填
+    pub post_payments: Vec<PayScorePostPayment>,
+    ///【后付费商户优惠】
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub post_discounts: Vec<PayScorePostDiscount>,
+    ///【总金额】 单位为分，等于后付费项目金额之和减去商户优惠金额之和
+    pub total_amount: i64,
+    ///【实际服务时间段】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_range: Option<PayScoreTimeRange>,
+    ///【实际服务位置】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<PayScoreLocation>,
+    ///【是否分账】
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profit_sharing: Option<bool>,
+}
+
+impl PayScoreCompleteParams {
+    /// total_amount按后付费项目金额之和减去商户优惠金额之和计算
+    pub fn new<S: AsRef<str>>(
+        service_id: S,
+        post_payments: Vec<PayScorePostPayment>,
+        post_discounts: Vec<PayScorePostDiscount>,
+    ) -> Self {
+        let payments: i64 = post_payments.iter().filter_map(|p| p.amount).sum();
+        let discounts: i64 = post_discounts.iter().filter_map(|d| d.amount).sum();
+        Self {
+            appid: None,
+            service_id: service_id.as_ref().to_string(),
+            post_payments,
+            post_discounts,
+            total_amount: payments - discounts,
+            time_range: None,
+            location: None,
+            profit_sharing: None,
+        }
+    }
+}
+
+impl ParamsTrait for PayScoreCompleteParams {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    fn validate(&self) -> Result<(), PayError> {
+        validate_post_payments(&self.post_payments, self.total_amount, "complete")
+    }
+}
+
+/// 完结和修改订单金额时，后付费项目和金额必填，总金额不能为负
+fn validate_post_payments(
+    post_payments: &[PayScorePostPayment],
+    total_amount: i64,
+    action: &str,
+) -> Result<(), PayError> {
+    if post_payments.is_empty() {
+        return Err(PayError::InvalidParams(format!(
+            "post_payments is required to {} a pay score order",
+            action
+        )));
+    }
+    if post_payments.iter().any(|p| p.amount.is_none()) {
+        return Err(PayError::InvalidParams(format!(
+            "post_payments amount is required to {} a pay score order",
+            action
+        )));
+    }
+    if total_amount < 0 {
+        return Err(PayError::InvalidParams(
+            "total_amount must not be negative".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// 修改支付分订单金额，只能在完结订单后、用户付款前修改
+#[derive(Serialize, Debug, Clone)]
+pub struct PayScoreModifyParams {
+    ///【应用ID】 未设置时使用WechatPay的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    ///【服务ID】
+    pub service_id: String,
+    ///【后付费项目】 金额必填
+    pub post_payments: Vec<PayScorePostPayment>,
+    ///【后付费商户优惠】
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub post_discounts: Vec<PayScorePostDiscount>,
+    ///【总金额】 单位为分，等于后付费项目金额之和减去商户优惠金额之和，不能超过完结时的总金额
+    pub total_amount: i64,
+    ///【修改原因】 最长50个字符
+    pub reason: String,
+}
+
+impl PayScoreModifyParams {
+    /// 修改原因最大长度
+    pub const MAX_REASON_LEN: usize = 50;
+
+    /// total_amount按后付费项目金额之和减去商户优惠金额之和计算
+    pub fn new<S: AsRef<str>>(
+        service_id: S,
+        post_payments: Vec<PayScorePostPayment>,
+        post_discounts: Vec<PayScorePostDiscount>,
+        reason: S,
+    ) -> Self {
+        let payments: i64 = post_payments.iter().filter_map(|p| p.amount).sum();
+        let discounts: i64 = post_discounts.iter().filter_map(|d| d.amount).sum();
+        Self {
+            appid: None,
+            service_id: service_id.as_ref().to_string(),
+            post_payments,
+            post_discounts,
+            total_amount: payments - discounts,
+            reason: reason.as_ref().to_string(),
+        }
+    }
+}
+
+impl ParamsTrait for PayScoreModifyParams {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    fn validate(&self) -> Result<(), PayError> {
+        validate_post_payments(&self.post_payments, self.total_amount, "modify")?;
+        if self.reason.is_empty() || self.reason.chars().count() > Self::MAX_REASON_LEN {
+            return Err(PayError::InvalidParams(format!(
+                "reason must be 1-{} characters",
+                Self::MAX_REASON_LEN
+            )));
+        }
+        Ok(())
+    }
+}
+
+string_enum! {
+    /// 支付分授权状态
+    pub enum PayScoreAuthorizationState {
+        /// 用户已授权，可以创建免确认的支付分订单
+        Available => "AVAILABLE",
+        /// 用户未授权
+        Unavailable => "UNAVAILABLE",
+        /// 用户已解除授权
+        UnbindUser => "UNBINDUSER",
+    }
+}
+
+/// 商户预授权
+#[derive(Serialize, Debug, Clone)]
+pub struct PayScorePermissionParams {
+    ///【服务ID】
+    pub service_id: String,
+    ///【应用ID】 未设置时使用WechatPay的appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+    ///【授权协议号】 商户系统内部唯一，用于查询和解除授权
+    pub authorization_code: String,
+    ///【商户接收授权回调通知的地址】 未设置时使用WechatPay的notify_url
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_url: Option<String>,
+}
+
+impl PayScorePermissionParams {
+    pub fn new<S: AsRef<str>>(service_id: S, authorization_code: S) -> Self {
+        Self {
+            service_id: service_id.as_ref().to_string(),
+            appid: None,
+            authorization_code: authorization_code.as_ref().to_string(),
+            notify_url: None,
+        }
+    }
+}
+
+impl ParamsTrait for PayScorePermissionParams {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    fn validate(&self) -> Result<(), PayError> {
+        if self.authorization_code.is_empty() {
+            return Err(PayError::InvalidParams(
+                "authorization_code is required".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn notify_url(&self) -> Option<&str> {
+        self.notify_url.as_deref()
+    }
+}
+
+/// 解除用户授权关系
+#[derive(Serialize, Debug, Clone)]
+pub struct PayScoreTerminateParams {
+    ///【服务ID】
+    pub service_id: String,
+    ///【撤销原因】
+    pub reason: String,
+}
+
+impl ParamsTrait for PayScoreTerminateParams {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::PayError;
+    use crate::model::{
+        AmountInfo, Currency, H5SceneInfo, H5Type, JsapiParams, MicroParams, NativeParams,
+        ParamsTrait, PayScoreCompleteParams, PayScoreParams, PayScorePostDiscount,
+        PayScorePostPayment, PayScoreRiskFund, PayScoreState, PayScoreTimeRange, Payer, PayerInfo,
+        ProfitSharingPlan, QueryAmount, RefundChannel, RefundStatus, RefundsParams, RiskFundName,
+        SettleInfo, StoreInfo, TradeNo, TransferBillsParams, TransferScene,
+        TransferSceneReportInfo, WechatPayDecodeData,
+    };
+
+    #[test]
+    fn test_confirm_payment() {
+        let data: WechatPayDecodeData = serde_json::from_str(
+            r#"{"mchid":"1230000109","appid":"wxd678efh567hg6787","out_trade_no":"1217752501201407033233368018","transaction_id":"1217752501201407033233368018","trade_type":"JSAPI","trade_state":"SUCCESS","trade_state_desc":"支付成功","bank_type":"CMC","attach":"","success_time":"2018-06-08T10:34:56+08:00","payer":{"openid":"oUpF8uMuAJO_M2pxb1Q9zNjWeS6o"},"amount":{"total":100,"payer_total":100,"currency":"CNY","payer_currency":"CNY"}}"#,
+        )
+        .unwrap();
+        let confirmation = data.confirm("1217752501201407033233368018", 100).unwrap();
+        assert_eq!(confirmation.transaction_id, "1217752501201407033233368018");
+        assert_eq!(confirmation.payer_total, Some(100));
+        assert_eq!(
+            confirmation.openid.as_deref(),
+            Some("oUpF8uMuAJO_M2pxb1Q9zNjWeS6o")
+        );
+
+        let error = data.confirm("1217752501201407033233368018", 1).unwrap_err();
+        assert!(matches!(
+            error,
+            PayError::PaymentMismatch { field: "amount.total", expected, actual }
+                if expected == "1" && actual == "100"
+        ));
+        let error = data.confirm("other", 100).unwrap_err();
+        assert!(matches!(
+            error,
+            PayError::PaymentMismatch {
+                field: "out_trade_no",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_refund_enums() {
+        let status: RefundStatus = serde_json::from_str(r#""ABNORMAL""#).unwrap();
+        assert_eq!(status, RefundStatus::Abnormal);
+        let status: RefundStatus = serde_json::from_str(r#""REFUNDING""#).unwrap();
+        assert_eq!(status, RefundStatus::Other("REFUNDING".to_string()));
+        assert_eq!(serde_json::to_string(&status).unwrap(), r#""REFUNDING""#);
+        let channel: RefundChannel = serde_json::from_str(r#""OTHER_BANKCARD""#).unwrap();
+        assert_eq!(channel, RefundChannel::OtherBankcard);
+        assert_eq!(RefundChannel::Original.to_string(), "ORIGINAL");
+    }
+
+    #[test]
+    fn test_transfer_scene() {
+        assert_eq!(TransferScene::from("1000"), TransferScene::CashMarketing);
+        assert_eq!(TransferScene::Commission.id(), "1005");
+        assert_eq!(
+            TransferScene::from("1009"),
+            TransferScene::Other("1009".to_string())
+        );
+        assert!(TransferScene::from("1009").report_info_types().is_none());
+        let scene: TransferScene = serde_json::from_str(r#""1005""#).unwrap();
+        assert_eq!(scene, TransferScene::Commission);
+
+        let params = TransferBillsParams::builder(
+            "plfk2020042013",
+            TransferScene::Commission,
+            "o-MYE42l80oelYMDE34nYD456Xoy",
+        )
+        .transfer_amount(400)
+        .transfer_remark("7月份配送费")
+        .transfer_scene_report_infos(TransferSceneReportInfo::commission(
+            "外卖骑手",
+            "7月份配送费",
+        ))
+        .build()
+        .unwrap();
+        assert_eq!(params.transfer_scene_id, "1005");
+        assert_eq!(params.transfer_scene(), TransferScene::Commission);
+
+        // 报备信息与场景不符时build失败，不会发到微信
+        let result = TransferBillsParams::builder(
+            "plfk2020042013",
+            TransferScene::CashMarketing,
+            "o-MYE42l80oelYMDE34nYD456Xoy",
+        )
+        .transfer_amount(400)
+        .transfer_remark("7月份配送费")
+        .transfer_scene_report_infos(TransferSceneReportInfo::commission(
+            "外卖骑手",
+            "7月份配送费",
+        ))
+        .build();
+        assert!(
+            matches!(result, Err(PayError::InvalidParams(message)) if message.contains("活动名称"))
+        );
+    }
+
+    #[test]
+    fn test_transfer_scene_report_infos() {
+        let params = TransferBillsParams::new(
+            "plfk2020042013",
+            TransferScene::CashMarketing,
+            "o-MYE42l80oelYMDE34nYD456Xoy",
+            400000,
+            "新会员开通有礼",
+            TransferSceneReportInfo::cash_marketing("新会员有礼", "注册会员抽奖一等奖"),
+        );
+        assert!(params.validate().is_ok());
+        let body: serde_json::Value = serde_json::from_str(&params.to_json()).unwrap();
+        assert_eq!(
+            body["transfer_scene_report_infos"][0]["info_type"],
+            "活动名称"
+        );
+        assert!(body.get("appid").is_none());
+
+        // 报备信息与场景不符
+        let mut params = params;
+        params.transfer_scene_report_infos =
+            TransferSceneReportInfo::commission("外卖骑手", "7月份配送费");
+        assert!(matches!(params.validate(), Err(PayError::InvalidParams(_))));
+        // 缺少报备信息
+        params.transfer_scene_report_infos.truncate(1);
+        params.transfer_scene_id = "1005".to_string();
+        assert!(params.validate().is_err());
+        // 内容为空
+        params.transfer_scene_report_infos = TransferSceneReportInfo::commission("外卖骑手", "");
+        assert!(params.validate().is_err());
+        // 未收录的场景只检查内容
+        params.transfer_scene_id = "1009".to_string();
+        params.transfer_scene_report_infos =
+            vec![TransferSceneReportInfo::new("采购商品名称", "办公用品")];
+        assert!(params.validate().is_ok());
+    }
+
+    #[test]
+    fn test_profit_sharing_plan() {
+        // 三等分，余下的1分补给第一个接收方
+        let receivers = ProfitSharingPlan::new(100)
+            .with_percentage("MERCHANT_ID", "a", "分给a", 3334)
+            .with_percentage("MERCHANT_ID", "b", "分给b", 3333)
+            .with_percentage("MERCHANT_ID", "c", "分给c", 3333)
+            .build()
+            .unwrap();
+        let amounts: Vec<i64> = receivers.iter().map(|receiver| receiver.amount).collect();
+        assert_eq!(amounts, vec![34, 33, 33]);
+
+        // 舍去部分相同时按添加顺序补齐，金额之和等于可分账金额
+        let receivers = ProfitSharingPlan::new(101)
+            .with_percentage("MERCHANT_ID", "a", "分给a", 5000)
+            .with_percentage("MERCHANT_ID", "b", "分给b", 5000)
+            .build()
+            .unwrap();
+        let amounts: Vec<i64> = receivers.iter().map(|receiver| receiver.amount).collect();
+        assert_eq!(amounts, vec![51, 50]);
+
+        let receivers = ProfitSharingPlan::new(1000)
+            .with_fixed("PERSONAL_OPENID", "openid", "固定", 100)
+            .with_percentage("MERCHANT_ID", "a", "比例", 1500)
+            .build()
+            .unwrap();
+        assert_eq!(receivers[0].amount, 100);
+        assert_eq!(receivers[1].amount, 150);
+        assert_eq!(receivers[1].receiver_type, "MERCHANT_ID");
+
+        assert!(
+            ProfitSharingPlan::new(1000)
+                .with_fixed("MERCHANT_ID", "a", "固定", 900)
+                .with_percentage("MERCHANT_ID", "b", "比例", 2000)
+                .build()
+                .is_err()
+        );
+        assert!(
+            ProfitSharingPlan::new(100)
+                .with_percentage("MERCHANT_ID", "a", "比例", 6000)
+                .with_percentage("MERCHANT_ID", "b", "比例", 5000)
+                .build()
+                .is_err()
+        );
+        // 金额不足1分
+        assert!(
+            ProfitSharingPlan::new(10)
+                .with_percentage("MERCHANT_ID", "a", "比例", 100)
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_goods_tag_serialize() {
+        let mut params = JsapiParams::new(
+            "测试支付1分",
+            "1243243",
+            1.into(),
+            "o4GgauInH_RCEdvrrNGrntXDuXXX".into(),
+        );
+        let json: serde_json::Value = serde_json::from_str(&params.to_json()).unwrap();
+        assert!(json.get("goods_tag").is_none());
+        params.goods_tag = Some("WXG".to_string());
+        let json: serde_json::Value = serde_json::from_str(&params.to_json()).unwrap();
+        assert_eq!(json["goods_tag"], "WXG");
+        let mut micro = MicroParams::new(
+            "测试支付1分",
+            "1243243",
+            1.into(),
+            "o4GgauInH_RCEdvrrNGrntXDuXXX".into(),
+        );
+        micro.goods_tag = Some("WXG".to_string());
+        assert_eq!(JsapiParams::from(micro).goods_tag.as_deref(), Some("WXG"));
+    }
+
+    #[test]
+    fn test_transfer_bills_builder() {
+        let builder = TransferBillsParams::builder(
+            "plfk2020042013",
+            TransferScene::CashMarketing,
+            "o-MYE42l80oelYMDE34nYD456Xoy",
+        )
+        .transfer_remark("新会员开通有礼")
+        .transfer_scene_report_infos(TransferSceneReportInfo::cash_marketing(
+            "新会员有礼",
+            "注册会员抽奖一等奖",
+        ));
+        let params = builder.clone().transfer_amount(400000).build().unwrap();
+        assert_eq!(params.transfer_amount, 400000);
+        assert!(builder.clone().transfer_amount(0).build().is_err());
+        assert!(builder.clone().transfer_amount(-1).build().is_err());
+        let builder = builder.transfer_amount(400000);
+        assert!(
+            builder
+                .clone()
+                .transfer_remark("一".repeat(32))
+                .build()
+                .is_ok()
+        );
+        assert!(
+            builder
+                .clone()
+                .transfer_remark("一".repeat(33))
+                .build()
+                .is_err()
+        );
+        assert!(builder.clone().transfer_remark("").build().is_err());
+        let error = TransferBillsParams::builder("plfk-2020", "1000", "openid")
+            .transfer_amount(1)
+            .transfer_remark("remark")
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(error, PayError::InvalidParams(message) if message.contains("out_bill_no"))
+        );
+    }
+
+    #[test]
+    fn test_payer_deserialize() {
+        let payer: Payer = serde_json::from_str(r#"{"openid":"open_id"}"#).unwrap();
+        assert_eq!(payer.openid(), Some("open_id"));
+        let payer: Payer = serde_json::from_str(r#"{"sp_openid":"sp_open_id"}"#).unwrap();
+        assert_eq!(payer.openid, None);
+        assert_eq!(payer.openid(), Some("sp_open_id"));
+        let payer: Payer =
+            serde_json::from_str(r#"{"sp_openid":"sp_open_id","sub_openid":"sub_open_id"}"#)
+                .unwrap();
+        assert_eq!(payer.openid(), Some("sub_open_id"));
+    }
+
+    #[test]
+    fn test_query_amount_deserialize() {
+        let amount: QueryAmount = serde_json::from_str(
+            r#"{"total":100,"payer_total":90,"currency":"CNY","payer_currency":"USD"}"#,
+        )
+        .unwrap();
+        assert_eq!(amount.total, 100);
+        assert_eq!(amount.payer_total, Some(90));
+        assert_eq!(amount.currency, Currency::CNY);
+        assert_eq!(
+            amount.payer_currency,
+            Some(Currency::Other("USD".to_string()))
+        );
+
+        let amount: QueryAmount = serde_json::from_str(r#"{"total":100}"#).unwrap();
+        assert_eq!(amount.currency, Currency::CNY);
+        assert_eq!(amount.payer_total, None);
+        assert!(amount.exchange_rate.is_none());
+    }
+
+    #[test]
+    fn test_cross_border_amount() {
+        // 不设置币种时与境内下单的请求体一致
+        let params = NativeParams::new("测试支付1分", "1243243", 100.into());
+        let value = serde_json::to_value(&params).unwrap();
+        assert_eq!(value["amount"], serde_json::json!({"total": 100}));
+
+        let amount = AmountInfo::new(100).unwrap().with_currency("USD");
+        let params = NativeParams::new("测试支付1分", "1243243", amount);
+        let value = serde_json::to_value(&params).unwrap();
+        assert_eq!(
+            value["amount"],
+            serde_json::json!({"total": 100, "currency": "USD"})
+        );
+        let amount: AmountInfo = serde_json::from_value(value["amount"].clone()).unwrap();
+        assert_eq!(amount.currency, Some(Currency::Other("USD".to_string())));
+
+        let amount: QueryAmount = serde_json::from_str(
+            r#"{"total":100,"payer_total":650,"currency":"USD","payer_currency":"CNY","exchange_rate":{"type":"SETTLEMENT_RATE","rate":650000000}}"#,
+        )
+        .unwrap();
+        assert_eq!(amount.currency, Currency::Other("USD".to_string()));
+        assert_eq!(amount.payer_currency, Some(Currency::CNY));
+        let rate = amount.exchange_rate.as_ref().unwrap();
+        assert_eq!(rate.rate_type, "SETTLEMENT_RATE");
+        assert_eq!(rate.rate, 650_000_000);
+        assert_eq!(rate.as_f64(), 6.5);
+        assert_eq!(
+            serde_json::to_value(&amount).unwrap()["exchange_rate"],
+            serde_json::json!({"type": "SETTLEMENT_RATE", "rate": 650000000})
+        );
+    }
+
+    #[test]
+    fn test_amount_shapes() {
+        // 下单：amount为对象
+        let params = NativeParams::new("测试支付1分", "1243243", 100.into());
+        let value = serde_json::to_value(&params).unwrap();
+        assert_eq!(value["amount"], serde_json::json!({"total": 100}));
+        let amount: AmountInfo = serde_json::from_str(r#"{"total":100}"#).unwrap();
+        assert_eq!(amount.total, 100);
+        let amount: AmountInfo = serde_json::from_str("100").unwrap();
+        assert_eq!(amount.total, 100);
+        assert!(serde_json::from_str::<AmountInfo>(r#""100""#).is_err());
+
+        // 查询订单、支付通知：amount为带币种的对象，未支付时没有payer_total
+        let amount: QueryAmount = serde_json::from_str(r#"{"total":100}"#).unwrap();
+        assert_eq!(amount.currency, Currency::CNY);
+        assert_eq!(
+            serde_json::to_value(&amount).unwrap(),
+            serde_json::json!({"total": 100, "currency": "CNY"})
+        );
+
+        // 退款：amount为退款金额、原订单金额和币种
+        let params = RefundsParams::new("1217752501201407033233368018", 888, 88, None, None);
+        let value = serde_json::to_value(&params).unwrap();
+        assert_eq!(
+            value["amount"],
+            serde_json::json!({"refund": 88, "total": 888, "currency": "CNY"})
+        );
+
+        // 转账、支付分：金额为以分为单位的整数
+        let params = TransferBillsParams::new(
+            "plfk2020042013",
+            TransferScene::CashMarketing,
+            "o-MYE42l80oelYMDE34nYD456Xoy",
+            400,
+            "新会员有礼",
+            vec![],
+        );
+        let value = serde_json::to_value(&params).unwrap();
+        assert_eq!(value["transfer_amount"], serde_json::json!(400));
+        let risk_fund = PayScoreRiskFund::new("DEPOSIT", 10000);
+        assert_eq!(
+            serde_json::to_value(&risk_fund).unwrap()["amount"],
+            serde_json::json!(10000)
+        );
+    }
+
+    #[test]
+    fn test_trade_no() {
+        let trade_no: TradeNo = "1217752501201407033233368018".try_into().unwrap();
+        assert_eq!(trade_no.as_str(), "1217752501201407033233368018");
+        assert!(TradeNo::try_from("P2015_08-06|12*34").is_ok());
+        for value in ["12345", "Image形象店-深圳腾大", "order 1", &"1".repeat(33)] {
+            assert!(
+                matches!(TradeNo::try_from(value), Err(PayError::InvalidParams(_))),
+                "{}",
+                value
+            );
+        }
+        assert_eq!(TradeNo::generate().as_str().len(), 32);
+        assert!(serde_json::from_str::<TradeNo>(r#""12345""#).is_err());
+
+        let params = NativeParams::new("Image形象店-深圳腾大-QQ公仔", trade_no, 100.into());
+        assert_eq!(params.out_trade_no, "1217752501201407033233368018");
+        assert!(params.validate().is_ok());
+        // 描述与订单号传反时在发送前报错
+        let params = NativeParams::new(
+            "1217752501201407033233368018",
+            "Image形象店-深圳腾大-QQ公仔",
+            100.into(),
+        );
+        assert!(matches!(params.validate(), Err(PayError::InvalidParams(_))));
+    }
+
+    #[test]
+    fn test_amount_boundary() {
+        assert!(AmountInfo::new(1).is_ok());
+        assert!(AmountInfo::new(AmountInfo::MAX_TOTAL).is_ok());
+        for total in [0, -1, AmountInfo::MAX_TOTAL + 1, i32::MIN] {
+            assert!(matches!(
+                AmountInfo::new(total),
+                Err(PayError::InvalidParams(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_settle_info() {
+        let mut params = JsapiParams::new("测试支付1分", "1243243", 1.into(), "open_id".into());
+        let value = serde_json::to_value(&params).unwrap();
+        assert!(value.get("settle_info").is_none());
+        params.settle_info = Some(SettleInfo::profit_sharing());
+        let value = serde_json::to_value(&params).unwrap();
+        assert_eq!(
+            value["settle_info"],
+            serde_json::json!({"profit_sharing": true})
+        );
+
+        let mut params = MicroParams::new("测试支付1分", "1243243", 1.into(), "open_id".into());
+        params.settle_info = Some(SettleInfo::default());
+        let params: JsapiParams = params.into();
+        let value = serde_json::to_value(&params).unwrap();
+        assert_eq!(value["settle_info"], serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_params_validate_amount() {
+        assert!(
+            NativeParams::new("测试支付1分", "1243243", 1.into())
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            NativeParams::new("测试支付1分", "1243243", 0.into())
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_payscore_complete_params() {
+        let discount = PayScorePostDiscount {
+            name: "满减".to_string(),
+            description: "满40减1".to_string(),
+            amount: Some(100),
+            count: None,
+        };
+        let params = PayScoreCompleteParams::new(
+            "500001",
+            vec![
+                PayScorePostPayment::new("就餐费用", 4000),
+                PayScorePostPayment::new("服务费", 500),
+            ],
+            vec![discount],
+        );
+        assert_eq!(params.total_amount, 4400);
+        assert!(params.validate().is_ok());
+
+        let mut params = PayScoreCompleteParams::new("500001", vec![], vec![]);
+        assert!(params.validate().is_err());
+        params.post_payments.push(PayScorePostPayment {
+            name: "就餐费用".to_string(),
+            amount: None,
+            description: None,
+            count: None,
+        });
+        assert!(matches!(params.validate(), Err(PayError::InvalidParams(_))));
+    }
+
+    #[test]
+    fn test_payscore_state() {
+        assert_eq!(PayScoreState::from("DOING"), PayScoreState::Doing);
+        assert!(!PayScoreState::Created.is_final());
+        assert!(PayScoreState::Expired.is_final());
+        let state: PayScoreState = serde_json::from_str(r#""PAUSED""#).unwrap();
+        assert_eq!(state, PayScoreState::Other("PAUSED".to_string()));
+    }
+
+    #[test]
+    fn test_risk_fund() {
+        let risk_fund = PayScoreRiskFund::new(RiskFundName::CashDeposit, 10000);
+        assert_eq!(
+            serde_json::to_value(&risk_fund).unwrap(),
+            serde_json::json!({"name": "CASH_DEPOSIT", "amount": 10000})
+        );
+        assert!(risk_fund.validate().is_ok());
+        assert_eq!(
+            PayScoreRiskFund::new("ESTIMATE_ORDER_COST", 1).name,
+            RiskFundName::EstimateOrderCost
+        );
+        let fund: PayScoreRiskFund =
+            serde_json::from_str(r#"{"name":"ADVANCE","amount":500}"#).unwrap();
+        assert_eq!(fund.name, RiskFundName::Advance);
+
+        let result = PayScoreRiskFund::new("DEPOSITS", 10000).validate();
+        assert!(
+            matches!(result, Err(PayError::InvalidParams(message)) if message.contains("DEPOSITS"))
+        );
+        assert!(matches!(
+            PayScoreRiskFund::new(RiskFundName::Deposit, 0).validate(),
+            Err(PayError::InvalidParams(_))
+        ));
+        let mut params = PayScoreParams::new(
+            "500001",
+            "1234323JKHDFE1243252",
+            "某某酒店",
+            PayScoreTimeRange::default(),
+            PayScoreRiskFund::new("deposit", 10000),
+        );
+        params.notify_url = Some("https://mydomain.com/notify".to_string());
+        assert!(matches!(params.validate(), Err(PayError::InvalidParams(_))));
+        params.risk_fund = PayScoreRiskFund::new(RiskFundName::Deposit, 10000);
+        assert!(params.validate().is_ok());
+    }
+
+    #[test]
+    fn test_payer_info() {
+        let payer: PayerInfo = " oUpF8uMuAJO_M2pxb1Q9zNjWeS6o\n".into();
+        assert_eq!(payer.openid, "oUpF8uMuAJO_M2pxb1Q9zNjWeS6o");
+        assert!(payer.validate().is_ok());
+        assert_eq!(
+            serde_json::to_value(&payer).unwrap(),
+            serde_json::json!({"openid": "oUpF8uMuAJO_M2pxb1Q9zNjWeS6o"})
+        );
+        assert!(
+            PayerInfo::sub_openid("oUpF8uMuAJO_M2pxb1Q9zNjWeS6o")
+                .validate()
+                .is_ok()
+        );
+        for payer in [
+            PayerInfo::new(""),
+            PayerInfo::new("   "),
+            PayerInfo::new("open id"),
+            PayerInfo::new("o".repeat(PayerInfo::MAX_OPENID_LEN + 1)),
+            PayerInfo::sp_openid(""),
+            PayerInfo {
+                openid: "open_id".to_string(),
+                sp_openid: Some("sp_open_id".to_string()),
+                sub_openid: None,
+            },
+        ] {
+            assert!(
+                matches!(payer.validate(), Err(PayError::InvalidParams(_))),
+                "{:?}",
+                payer
+            );
+        }
+
+        let params = JsapiParams::new("测试支付1分", "1243243", 1.into(), "".into());
+        assert!(
+            matches!(params.validate(), Err(PayError::InvalidParams(message)) if message.contains("openid"))
+        );
+        let params = MicroParams::new("测试支付1分", "1243243", 1.into(), "open id".into());
+        assert!(
+            matches!(params.validate(), Err(PayError::InvalidParams(message)) if message.contains("openid"))
+        );
+    }
+
+    #[test]
+    fn test_h5_scene_info_builder() {
+        let scene_info = H5SceneInfo::builder("127.0.0.1", H5Type::Wap)
+            .app_name("腾讯充值")
+            .app_url("https://pay.qq.com")
+            .device_id("013467007045764")
+            .store_info(
+                StoreInfo::new("0001")
+                    .with_name("腾讯大厦分店")
+                    .with_address("广东省深圳市南山区科技中一道10000号"),
+            )
+            .build();
+        assert_eq!(
+            serde_json::to_value(&scene_info).unwrap(),
+            serde_json::json!({
+                "payer_client_ip": "127.0.0.1",
+                "h5_info": {"type": "Wap", "app_name": "腾讯充值", "app_url": "https://pay.qq.com"},
+                "device_id": "013467007045764",
+                "store_info": {
+                    "id": "0001",
+                    "name": "腾讯大厦分店",
+                    "address": "广东省深圳市南山区科技中一道10000号"
+                }
+            })
+        );
+
+        let scene_info = H5SceneInfo::builder("127.0.0.1", H5Type::Ios)
+            .bundle_id("com.tencent.wzryiOS")
+            .build();
+        assert_eq!(
+            serde_json::to_value(&scene_info).unwrap(),
+            serde_json::json!({
+                "payer_client_ip": "127.0.0.1",
+                "h5_info": {"type": "iOS", "bundle_id": "com.tencent.wzryiOS"}
+            })
+        );
+    }
 }
@@ -0,0 +1,330 @@
+use crate::error::PayError;
+use crate::model::{
+    ProfitSharingNotify, RefundNotify, TransferNotify, WechatPayDecodeData, WechatPayNotify,
+};
+use crate::pay::{PayNotifyTrait, resource_from_value, resource_value};
+use crate::response::ResponseTrait;
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+
+pub const WECHATPAY_TIMESTAMP: &str = "wechatpay-timestamp";
+pub const WECHATPAY_NONCE: &str = "wechatpay-nonce";
+pub const WECHATPAY_SIGNATURE: &str = "wechatpay-signature";
+pub const WECHATPAY_SERIAL: &str = "wechatpay-serial";
+
+/// 按事件类型解密后的回调，同一个回调地址接收多种事件时使用
+#[derive(Debug, Clone)]
+pub enum NotificationEvent {
+    /// 支付回调，事件类型TRANSACTION.*
+    Payment(Box<WechatPayDecodeData>),
+    /// 退款回调，事件类型REFUND.*
+    Refund(RefundNotify),
+    /// 商家转账回调，事件类型MCHTRANSFER.*
+    Transfer(TransferNotify),
+    /// 分账动账回调，事件类型PROFITSHARING.*
+    ProfitSharing(ProfitSharingNotify),
+    /// 未建模的事件，raw为解密后的原始JSON
+    Other { event_type: String, raw: Value },
+}
+
+impl NotificationEvent {
+    /// 按事件类型前缀把解密后的JSON解析为对应的结构
+    pub fn parse(event_type: &str, raw: Value) -> Result<Self, PayError> {
+        let prefix = event_type.split('.').next().unwrap_or_default();
+        let event = match prefix {
+            "TRANSACTION" => NotificationEvent::Payment(Box::new(resource_from_value(raw)?)),
+            "REFUND" => NotificationEvent::Refund(resource_from_value(raw)?),
+            "MCHTRANSFER" => NotificationEvent::Transfer(resource_from_value(raw)?),
+            "PROFITSHARING" => NotificationEvent::ProfitSharing(resource_from_value(raw)?),
+            _ => NotificationEvent::Other {
+                event_type: event_type.to_string(),
+                raw,
+            },
+        };
+        Ok(event)
+    }
+}
+
+/// 回调通知请求，与web框架无关
+/// 从回调的header和原始body构建，负责验签和解密
+#[derive(Debug, Clone)]
+pub struct NotificationRequest {
+    ///【应答时间戳】 Wechatpay-Timestamp
+    pub timestamp: String,
+    ///【应答随机串】 Wechatpay-Nonce
+    pub nonce: String,
+    ///【应答签名】 Wechatpay-Signature
+    pub signature: String,
+    ///【平台证书序列号】 Wechatpay-Serial
+    pub serial: String,
+    ///【回调原始报文】 验签必须使用未经修改的原始body
+    pub body: String,
+}
+
+impl NotificationRequest {
+    pub fn new<S: AsRef<str>>(timestamp: S, nonce: S, signature: S, serial: S, body: S) -> Self {
+        Self {
+            timestamp: timestamp.as_ref().to_string(),
+            nonce: nonce.as_ref().to_string(),
+            signature: signature.as_ref().to_string(),
+            serial: serial.as_ref().to_string(),
+            body: body.as_ref().to_string(),
+        }
+    }
+
+    /// 从回调的header和原始body构建
+    pub fn from_parts(headers: &HeaderMap, body: impl AsRef<[u8]>) -> Result<Self, PayError> {
+        Self::from_header_fn(
+            |name| {
+                headers
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| value.to_string())
+            },
+            body,
+        )
+    }
+
+    /// 从任意header读取方式构建，便于适配不同web框架的header类型
+    pub fn from_header_fn<F>(header: F, body: impl AsRef<[u8]>) -> Result<Self, PayError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let get = |name: &str| {
+            header(name).ok_or_else(|| PayError::VerifyError(format!("missing header: {}", name)))
+        };
+        let body = String::from_utf8(body.as_ref().to_vec())
+            .map_err(|e| PayError::VerifyError(format!("body is not utf-8: {}", e)))?;
+        Ok(Self {
+            timestamp: get(WECHATPAY_TIMESTAMP)?,
+            nonce: get(WECHATPAY_NONCE)?,
+            signature: get(WECHATPAY_SIGNATURE)?,
+            serial: get(WECHATPAY_SERIAL)?,
+            body,
+        })
+    }
+
+    /// 使用平台公钥验签
+    pub fn verify<P: PayNotifyTrait>(&self, pay: &P, pub_key: &str) -> Result<(), PayError> {
+        pay.verify_signature(
+            pub_key,
+            self.timestamp.as_str(),
+            self.nonce.as_str(),
+            self.signature.as_str(),
+            self.body.as_str(),
+        )
+    }
+
+    /// 解析回调报文，不做验签
+    pub fn notify(&self) -> Result<WechatPayNotify, PayError> {
+        Ok(serde_json::from_str(&self.body)?)
+    }
+
+    /// 回调事件类型，如TRANSACTION.SUCCESS、REFUND.SUCCESS，用于在解密前选择对应的数据结构
+    pub fn event_type(&self) -> Result<String, PayError> {
+        Ok(self.notify()?.event_type)
+    }
+
+    /// 验签并解密支付回调
+    pub fn decrypt<P: PayNotifyTrait>(
+        &self,
+        pay: &P,
+        pub_key: &str,
+    ) -> Result<WechatPayDecodeData, PayError> {
+        self.decrypt_as(pay, pub_key)
+    }
+
+    /// 验签并解密退款回调
+    pub fn decrypt_refund<P: PayNotifyTrait>(
+        &self,
+        pay: &P,
+        pub_key: &str,
+    ) -> Result<RefundNotify, PayError> {
+        self.decrypt_as(pay, pub_key)
+    }
+
+    /// 验签并解密回调，按event_type分发为`NotificationEvent`，只验签、解密一次
+    pub fn decrypt_event<P: PayNotifyTrait>(
+        &self,
+        pay: &P,
+        pub_key: &str,
+    ) -> Result<NotificationEvent, PayError> {
+        self.verify(pay, pub_key)?;
+        let notify = self.notify()?;
+        let plaintext = pay.decrypt_aead(&notify.resource)?;
+        let raw = resource_value(&plaintext)?;
+        NotificationEvent::parse(notify.event_type.as_str(), raw)
+    }
+
+    /// 验签并解密回调，按事件类型解析为对应的结构
+    /// 退款、转账、分账等回调使用相同的加密格式，resource解密后的字段不同
+    pub fn decrypt_as<T: ResponseTrait, P: PayNotifyTrait>(
+        &self,
+        pay: &P,
+        pub_key: &str,
+    ) -> Result<T, PayError> {
+        self.verify(pay, pub_key)?;
+        let notify = self.notify()?;
+        pay.decrypt_aead_as(&notify.resource)
+    }
+}
+
+#[cfg(feature = "axum")]
+mod axum_impl {
+    use super::NotificationRequest;
+    use axum::body::Bytes;
+    use axum::extract::{FromRequest, Request};
+    use axum::http::StatusCode;
+
+    impl<S> FromRequest<S> for NotificationRequest
+    where
+        S: Send + Sync,
+    {
+        type Rejection = (StatusCode, String);
+
+        async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+            let headers = req.headers().clone();
+            let body = Bytes::from_request(req, state)
+                .await
+                .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+            NotificationRequest::from_parts(&headers, body)
+                .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+        }
+    }
+}
+
+#[cfg(feature = "actix")]
+mod actix_impl {
+    use super::NotificationRequest;
+    use actix_web::dev::Payload;
+    use actix_web::web::Bytes;
+    use actix_web::{FromRequest, HttpRequest};
+    use std::future::Future;
+    use std::pin::Pin;
+
+    impl FromRequest for NotificationRequest {
+        type Error = actix_web::Error;
+        type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+        fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+            let req = req.clone();
+            let body = Bytes::from_request(&req, payload);
+            Box::pin(async move {
+                let body = body.await?;
+                NotificationRequest::from_header_fn(
+                    |name| {
+                        req.headers()
+                            .get(name)
+                            .and_then(|value| value.to_str().ok())
+                            .map(|value| value.to_string())
+                    },
+                    body,
+                )
+                .map_err(actix_web::error::ErrorBadRequest)
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NotificationRequest;
+    use crate::mock_server::PRIVATE_KEY;
+    use crate::model::{WechatPayNotify, WechatPayNotifySource};
+    use crate::pay::WechatPay;
+    use crate::{sign, util};
+    use aes_gcm::aead::{Aead, Payload};
+    use aes_gcm::{Aes256Gcm, aead::KeyInit};
+    use reqwest::header::HeaderMap;
+    use rsa::pkcs8::{DecodePrivateKey, EncodePublicKey, LineEnding};
+    use rsa::{RsaPrivateKey, RsaPublicKey};
+
+    const V3_KEY: &str = "0123456789abcdef0123456789abcdef";
+
+    fn public_key() -> String {
+        let private_key = RsaPrivateKey::from_pkcs8_pem(PRIVATE_KEY).unwrap();
+        RsaPublicKey::from(&private_key)
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap()
+    }
+
+    fn notify_body() -> String {
+        let plaintext = r#"{"mchid":"1900000001","appid":"wx0000000000000001","out_trade_no":"1243243","transaction_id":"4200000000000000000000000001","trade_type":"JSAPI","trade_state":"SUCCESS","trade_state_desc":"支付成功","bank_type":"OTHERS","attach":"","success_time":"2024-01-12T10:36:13+08:00","payer":{"openid":"open_id"},"amount":{"total":1},"promotion_detail":[]}"#;
+        let nonce = "gZiqzlfayUu2";
+        let cipher = Aes256Gcm::new(V3_KEY.as_bytes().into());
+        let ciphertext = cipher
+            .encrypt(
+                nonce.as_bytes().into(),
+                Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: b"transaction",
+                },
+            )
+            .unwrap();
+        let notify = WechatPayNotify {
+            id: "29a61973-babf-599a-966d-6bcdcf17360c".to_string(),
+            create_time: "2024-01-12T21:39:44+08:00".to_string(),
+            event_type: "TRANSACTION.SUCCESS".to_string(),
+            resource_type: "encrypt-resource".to_string(),
+            resource: WechatPayNotifySource {
+                algorithm: "AEAD_AES_256_GCM".to_string(),
+                ciphertext: util::base64_encode(ciphertext),
+                associated_data: Some("transaction".to_string()),
+                original_type: "transaction".to_string(),
+                nonce: nonce.to_string(),
+            },
+            summary: "支付成功".to_string(),
+        };
+        serde_json::to_string(&notify).unwrap()
+    }
+
+    fn headers(body: &str) -> HeaderMap {
+        let timestamp = "1705066785";
+        let nonce = "Jh9oPZelCJIQeQ47kz4stzvDKpLEUhCX";
+        let signature = sign::sha256_sign(
+            PRIVATE_KEY,
+            format!("{}\n{}\n{}\n", timestamp, nonce, body).as_str(),
+        )
+        .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("Wechatpay-Timestamp", timestamp.parse().unwrap());
+        headers.insert("Wechatpay-Nonce", nonce.parse().unwrap());
+        headers.insert("Wechatpay-Signature", signature.parse().unwrap());
+        headers.insert(
+            "Wechatpay-Serial",
+            "5157F09EFDC096DE15EBE81A47057A72".parse().unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_notification_decrypt() {
+        let wechat_pay = WechatPay::try_new("", "", PRIVATE_KEY, "", V3_KEY, "").unwrap();
+        let body = notify_body();
+        let request = NotificationRequest::from_parts(&headers(&body), body.as_bytes()).unwrap();
+        let data = request.decrypt(&wechat_pay, &public_key()).unwrap();
+        assert_eq!(data.out_trade_no, "1243243");
+        assert_eq!(data.amount.total, 1);
+        assert_eq!(data.payer.openid(), Some("open_id"));
+        assert!(data.promotions().is_empty());
+        // 原始JSON保留全部字段
+        assert!(data.raw()["promotion_detail"].is_array());
+    }
+
+    #[test]
+    fn test_notification_tampered_body() {
+        let wechat_pay = WechatPay::try_new("", "", PRIVATE_KEY, "", V3_KEY, "").unwrap();
+        let body = notify_body();
+        let request =
+            NotificationRequest::from_parts(&headers(&body), format!("{} ", body)).unwrap();
+        assert!(request.decrypt(&wechat_pay, &public_key()).is_err());
+    }
+
+    #[test]
+    fn test_notification_missing_header() {
+        let body = notify_body();
+        let mut headers = headers(&body);
+        headers.remove("Wechatpay-Signature");
+        assert!(NotificationRequest::from_parts(&headers, body).is_err());
+    }
+}
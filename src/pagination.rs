@@ -0,0 +1,279 @@
+use crate::error::PayError;
+use crate::pay::WechatPay;
+use crate::response::ResponseTrait;
+use crate::util;
+#[cfg(not(feature = "async"))]
+use std::collections::VecDeque;
+#[cfg(not(feature = "async"))]
+use std::marker::PhantomData;
+
+/// offset/limit分页的列表应答，每个列表接口的应答实现该trait后即可自动翻页
+pub trait Paginated: ResponseTrait {
+    type Item;
+    /// 本页数据
+    fn items(self) -> Vec<Self::Item>;
+    /// 【总数量】 接口未返回时按本页是否取满判断是否还有下一页
+    fn total_count(&self) -> Option<u32>;
+}
+
+/// 分页请求的游标
+#[derive(Debug, Clone)]
+pub(crate) struct PageCursor {
+    url: String,
+    offset: u32,
+    limit: u32,
+    done: bool,
+}
+
+impl PageCursor {
+    pub(crate) fn new(url: impl Into<String>, limit: u32) -> Self {
+        Self {
+            url: url.into(),
+            offset: 0,
+            limit,
+            done: false,
+        }
+    }
+
+    fn page_url(&self) -> String {
+        util::with_query(
+            &self.url,
+            &[
+                ("offset", self.offset.to_string().as_str()),
+                ("limit", self.limit.to_string().as_str()),
+            ],
+        )
+    }
+
+    /// 记录本页结果，返回本页数据
+    fn advance<P: Paginated>(&mut self, page: P) -> Vec<P::Item> {
+        let total_count = page.total_count();
+        let items = page.items();
+        self.offset += items.len() as u32;
+        self.done = items.is_empty()
+            || (items.len() as u32) < self.limit
+            || total_count.is_some_and(|total_count| self.offset >= total_count);
+        items
+    }
+}
+
+/// 自动翻页的迭代器，逐条返回列表数据，请求失败时返回错误并结束
+#[cfg(not(feature = "async"))]
+pub struct Pages<'a, P: Paginated> {
+    pay: &'a WechatPay,
+    cursor: PageCursor,
+    buffer: VecDeque<P::Item>,
+    _page: PhantomData<P>,
+}
+
+#[cfg(not(feature = "async"))]
+impl<P: Paginated> Iterator for Pages<'_, P> {
+    type Item = Result<P::Item, PayError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(item) = self.buffer.pop_front() {
+            return Some(Ok(item));
+        }
+        if self.cursor.done {
+            return None;
+        }
+        match self.pay.get_pay::<P>(self.cursor.page_url().as_str()) {
+            Ok(page) => {
+                self.buffer.extend(self.cursor.advance(page));
+                self.buffer.pop_front().map(Ok)
+            }
+            Err(e) => {
+                self.cursor.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// 自动翻页的Stream，逐条返回列表数据，请求失败时返回错误并结束
+#[cfg(feature = "async")]
+pub type Pages<'a, P> =
+    futures_util::stream::BoxStream<'a, Result<<P as Paginated>::Item, PayError>>;
+
+impl WechatPay {
+    /// 对任意offset/limit分页的GET接口自动翻页，url不需要带offset和limit参数
+    #[cfg(not(feature = "async"))]
+    pub fn paginate<P: Paginated>(&self, url: impl Into<String>, limit: u32) -> Pages<'_, P> {
+        Pages {
+            pay: self,
+            cursor: PageCursor::new(url, limit),
+            buffer: VecDeque::new(),
+            _page: PhantomData,
+        }
+    }
+
+    /// 对任意offset/limit分页的GET接口自动翻页，url不需要带offset和limit参数
+    #[cfg(feature = "async")]
+    pub fn paginate<P>(&self, url: impl Into<String>, limit: u32) -> Pages<'_, P>
+    where
+        P: Paginated + Send + 'static,
+        P::Item: Send + 'static,
+    {
+        use futures_util::StreamExt;
+        use futures_util::stream;
+
+        stream::unfold(PageCursor::new(url, limit), move |mut cursor| async move {
+            if cursor.done {
+                return None;
+            }
+            let page = match self.get_pay::<P>(cursor.page_url().as_str()).await {
+                Ok(page) => Ok(cursor.advance(page)),
+                Err(e) => {
+                    cursor.done = true;
+                    Err(e)
+                }
+            };
+            Some((page, cursor))
+        })
+        .flat_map(|page| {
+            let items = match page {
+                Ok(items) => items.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            stream::iter(items)
+        })
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::PayError;
+    use crate::mock_server::{self, run, test_pay};
+    use wiremock::{MockServer, Request, ResponseTemplate};
+
+    fn complaint(id: u32) -> serde_json::Value {
+        serde_json::json!({
+            "complaint_id": format!("2000000{}", id),
+            "complaint_time": "2024-01-12T10:36:13+08:00",
+            "complaint_detail": "反馈一个重复扣费的问题",
+            "complaint_state": "PENDING",
+            "complaint_order_info": [],
+        })
+    }
+
+    /// 共5条投诉，按offset/limit返回
+    async fn start_server() -> MockServer {
+        mock_server::start(|request: &Request| {
+            let query = |name: &str| {
+                request
+                    .url
+                    .query_pairs()
+                    .find(|(key, _)| key == name)
+                    .and_then(|(_, value)| value.parse::<u32>().ok())
+                    .unwrap()
+            };
+            let (offset, limit) = (query("offset"), query("limit"));
+            let data = (offset..(offset + limit).min(5))
+                .map(complaint)
+                .collect::<Vec<_>>();
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": data,
+                "limit": limit,
+                "offset": offset,
+                "total_count": 5,
+            }))
+        })
+        .await
+    }
+
+    #[cfg(not(feature = "async"))]
+    fn collect<T>(pages: impl Iterator<Item = Result<T, PayError>>) -> Result<Vec<T>, PayError> {
+        pages.collect()
+    }
+
+    #[cfg(feature = "async")]
+    async fn collect<T>(
+        pages: futures_util::stream::BoxStream<'_, Result<T, PayError>>,
+    ) -> Result<Vec<T>, PayError> {
+        use futures_util::TryStreamExt;
+        pages.try_collect().await
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_list_complaints_pages_until_exhausted() {
+        let server = run!(start_server());
+        let wechat_pay = test_pay(&server);
+        let complaints = collect(wechat_pay.list_complaints("2024-01-01", "2024-01-30", 2))
+            .await
+            .unwrap();
+        let ids = complaints
+            .iter()
+            .map(|complaint| complaint.complaint_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec!["20000000", "20000001", "20000002", "20000003", "20000004"]
+        );
+        assert_eq!(
+            run!(mock_server::hits(
+                &server,
+                "/v3/merchant-service/complaints-v2"
+            )),
+            3
+        );
+        let requests = run!(mock_server::requests(&server));
+        assert_eq!(
+            requests[2].url.query(),
+            Some("begin_date=2024-01-01&end_date=2024-01-30&offset=4&limit=2")
+        );
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_pages_stop_on_error() {
+        let server = run!(mock_server::start(
+            ResponseTemplate::new(200).set_body_string("not json")
+        ));
+        let wechat_pay = test_pay(&server);
+        let error = collect(wechat_pay.list_complaints("2024-01-01", "2024-01-30", 2)).await;
+        assert!(error.is_err());
+        assert_eq!(
+            run!(mock_server::hits(
+                &server,
+                "/v3/merchant-service/complaints-v2"
+            )),
+            1
+        );
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_pages_return_wechat_error() {
+        let server = run!(mock_server::start(|request: &Request| {
+            if request
+                .url
+                .query()
+                .is_some_and(|query| query.contains("offset=0"))
+            {
+                return ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "data": [complaint(0), complaint(1)],
+                    "limit": 2,
+                    "offset": 0,
+                    "total_count": 5,
+                }));
+            }
+            ResponseTemplate::new(429)
+                .set_body_string(r#"{"code":"FREQUENCY_LIMITED","message":"频率超限"}"#)
+        }));
+        let wechat_pay = test_pay(&server);
+        // 被限频时返回微信支付错误码，而不是分页结构的解析错误
+        let error = collect(wechat_pay.list_complaints("2024-01-01", "2024-01-30", 2))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.wechat_error_code(),
+            Some(crate::error::WechatErrorCode::FrequencyLimited)
+        );
+        assert_eq!(
+            run!(mock_server::hits(
+                &server,
+                "/v3/merchant-service/complaints-v2"
+            )),
+            2
+        );
+    }
+}
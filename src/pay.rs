@@ -1,25 +1,90 @@
+use crate::cert::PlatformCertificates;
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+use crate::client;
+use crate::client::{Client, HttpConfig};
+use crate::clock::{Clock, SystemClock};
+#[cfg(feature = "debug-print")]
+use crate::debug;
+use crate::endpoints;
 use crate::error::PayError;
-use crate::model::WechatPayDecodeData;
-use crate::request::HttpMethod;
-use crate::response::SignData;
-use crate::{debug, sign, util};
+use crate::model::{AEAD_AES_256_GCM, AeadResource, WechatPayDecodeData};
+use crate::pay_type::PayType;
+use crate::request::{HttpMethod, InjectFields};
+use crate::response::{
+    Certificate, CertificateResponse, MerchantTransferData, ResponseTrait, SignData,
+    TransferBillsResponse,
+};
+use crate::sign::{PrivateKey, SignatureType};
+use crate::util;
 use aes_gcm::aead::{AeadMut, Payload};
-use aes_gcm::{aead::KeyInit, Aes256Gcm};
-use reqwest::header::{HeaderMap, ACCEPT, AUTHORIZATION, CONTENT_TYPE, USER_AGENT};
-use rsa::pkcs8::DecodePublicKey;
-use rsa::sha2::{Digest, Sha256};
-use rsa::{Pkcs1v15Sign, RsaPublicKey};
-use uuid::Uuid;
-
-#[derive(Debug)]
+use aes_gcm::{Aes256Gcm, aead::KeyInit};
+use chrono::{DateTime, Utc};
+use reqwest::header::{
+    ACCEPT, ACCEPT_LANGUAGE, AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderValue, USER_AGENT,
+};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+/// 默认的User-Agent
+pub const DEFAULT_USER_AGENT: &str = concat!("wechat-pay-rust-sdk/", env!("CARGO_PKG_VERSION"));
+
+/// 默认的Accept-Language，错误应答的message为中文
+pub const DEFAULT_ACCEPT_LANGUAGE: &str = "zh-CN";
+
+/// 签名时间戳与微信支付服务器时间相差超过该值(秒)且返回401时，认为本机时钟偏差导致签名失败，校正后重试一次
+pub const MAX_CLOCK_SKEW_SECS: i64 = 60;
+
+/// 默认的应答体大小上限
+pub const DEFAULT_MAX_RESPONSE_SIZE: usize = 4 * 1024 * 1024;
+
+/// 微信支付客户端
+/// 克隆开销很小，克隆出的实例共享连接池、商户私钥和平台证书缓存，可以直接在多个线程、任务间克隆使用，无需再包一层Arc
+#[derive(Debug, Clone)]
 pub struct WechatPay {
     pub appid: String,
     pub mch_id: String,
-    pub private_key: String,
+    ///【商户私钥】 PEM格式，通过`with_private_key`修改
+    private_key: String,
     pub serial_no: String,
     pub v3_key: String,
     pub notify_url: String,
     pub base_url: String,
+    ///【签名算法】 默认SHA256-RSA2048，通过`try_with_signature_type`修改
+    signature_type: SignatureType,
+    ///【备用域名】 请求主域名连接失败时依次尝试
+    pub fallback_hosts: Vec<String>,
+    ///【子商户号】 服务商模式下的子商户号，设置后下单走服务商接口
+    pub sub_mch_id: Option<String>,
+    ///【子商户应用ID】 服务商模式下子商户的appid
+    pub sub_appid: Option<String>,
+    ///【应答验签】 开启后按Wechatpay-Serial选择平台证书校验应答签名
+    pub response_verify: bool,
+    ///【dry-run】 开启后不发送请求，返回携带完整请求信息的`PayError::DryRun`
+    pub dry_run: bool,
+    ///【日志输出敏感信息】 默认关闭，debug日志中的签名、随机串只显示前缀
+    pub log_sensitive: bool,
+    ///【附加header】 每个请求都会携带，不参与签名
+    pub default_headers: HeaderMap,
+    ///【应答体大小上限】 单位字节，默认4MB
+    pub max_response_size: usize,
+    ///【User-Agent】 默认`wechat-pay-rust-sdk/{version}`
+    pub user_agent: String,
+    ///【Accept-Language】 错误应答message使用的语言，默认`zh-CN`，可设置为`en`
+    pub accept_language: String,
+    ///【请求体gzip压缩阈值】 默认None不压缩，设置后不小于该字节数的请求体以gzip压缩发送
+    #[cfg(feature = "gzip")]
+    pub request_gzip: Option<usize>,
+    /// 按signature_type解析的商户私钥，修改私钥或签名算法时重新解析
+    /// `new`不返回错误，私钥解析失败时保存错误信息，签名时返回`PayError::InvalidPrivateKey`
+    pub(crate) signing_key: Arc<Result<PrivateKey, String>>,
+    pub(crate) platform_certs: Arc<PlatformCertificates>,
+    pub(crate) clock: Arc<dyn Clock>,
+    /// 检测到的时钟偏差，单位秒，克隆出的实例共享
+    pub(crate) clock_skew: Arc<AtomicI64>,
+    pub(crate) http_config: HttpConfig,
+    pub(crate) client: Client,
 }
 
 unsafe impl Send for WechatPay {}
@@ -44,14 +109,8 @@ pub trait PayNotifyTrait: WechatPayTrait {
             nonce.as_ref(),
             body.as_ref()
         );
-        let pub_key = RsaPublicKey::from_public_key_pem(pub_key)
-            .map_err(|e| PayError::VerifyError(format!("public key parser error: {}", e)))?;
-        let hashed = Sha256::new().chain_update(message).finalize();
-        let signature = util::base64_decode(signature.as_ref())?;
-        let scheme = Pkcs1v15Sign::new::<Sha256>();
-        pub_key
-            .verify(scheme, &hashed, signature.as_slice())
-            .map_err(|e| PayError::VerifyError(e.to_string()))
+        self.signature_type()
+            .verify(pub_key, &message, signature.as_ref())
     }
     fn decrypt_paydata<S>(
         &self,
@@ -61,10 +120,45 @@ pub trait PayNotifyTrait: WechatPayTrait {
     ) -> Result<WechatPayDecodeData, PayError>
     where
         S: AsRef<str>,
+    {
+        self.decrypt_resource(ciphertext, nonce, associated_data)
+    }
+    /// 解密回调通知的resource，按事件类型解析为对应的结构
+    fn decrypt_resource<T, S>(
+        &self,
+        ciphertext: S,
+        nonce: S,
+        associated_data: S,
+    ) -> Result<T, PayError>
+    where
+        T: ResponseTrait,
+        S: AsRef<str>,
     {
         let plaintext = self.decrypt_bytes(ciphertext, nonce, associated_data)?;
-        let data: WechatPayDecodeData = serde_json::from_slice(&plaintext)?;
-        Ok(data)
+        parse_resource(&plaintext)
+    }
+    /// 解密AEAD资源，algorithm、nonce和associated_data取自资源本身，不支持的算法返回DecryptError
+    fn decrypt_aead<R: AeadResource + ?Sized>(&self, resource: &R) -> Result<Vec<u8>, PayError> {
+        if resource.algorithm() != AEAD_AES_256_GCM {
+            return Err(PayError::DecryptError(format!(
+                "unsupported algorithm: {}",
+                resource.algorithm()
+            )));
+        }
+        self.decrypt_bytes(
+            resource.ciphertext(),
+            resource.nonce(),
+            resource.associated_data(),
+        )
+    }
+    /// 解密AEAD资源并解析为对应的结构
+    fn decrypt_aead_as<T, R>(&self, resource: &R) -> Result<T, PayError>
+    where
+        T: ResponseTrait,
+        R: AeadResource + ?Sized,
+    {
+        let plaintext = self.decrypt_aead(resource)?;
+        parse_resource(&plaintext)
     }
     fn decrypt_bytes<S>(
         &self,
@@ -95,6 +189,25 @@ pub trait PayNotifyTrait: WechatPayTrait {
     }
 }
 
+/// 解析解密后的资源，保留原始JSON
+fn parse_resource<T: ResponseTrait>(plaintext: &[u8]) -> Result<T, PayError> {
+    resource_from_value(resource_value(plaintext)?)
+}
+
+/// 解密后的资源解析为JSON，与应答一样经过`util::from_json_str`
+pub(crate) fn resource_value(plaintext: &[u8]) -> Result<Value, PayError> {
+    let plaintext = std::str::from_utf8(plaintext)
+        .map_err(|e| PayError::DecryptError(format!("resource is not utf-8: {}", e)))?;
+    Ok(util::from_json_str(plaintext)?)
+}
+
+/// 资源JSON解析为对应的结构，保留原始JSON
+pub(crate) fn resource_from_value<T: ResponseTrait>(raw: Value) -> Result<T, PayError> {
+    let mut data = T::deserialize(&raw)?;
+    data.set_raw(raw);
+    Ok(data)
+}
+
 pub trait WechatPayTrait {
     fn appid(&self) -> String;
     fn mch_id(&self) -> String;
@@ -103,15 +216,22 @@ pub trait WechatPayTrait {
     fn v3_key(&self) -> String;
     fn notify_url(&self) -> String;
     fn base_url(&self) -> String;
-    fn rsa_sign(&self, content: impl AsRef<str>) -> String;
+    fn rsa_sign(&self, content: impl AsRef<str>) -> Result<String, PayError>;
+    fn signature_type(&self) -> SignatureType {
+        SignatureType::default()
+    }
+    /// 读取当前时间的时钟，默认为系统时钟，实现方可返回注入的时钟
+    fn clock(&self) -> &dyn Clock {
+        &SystemClock
+    }
     fn now_timestamp(&self) -> String {
-        chrono::Local::now().timestamp().to_string()
+        self.clock().timestamp().to_string()
     }
     fn nonce_str(&self) -> String {
-        Uuid::new_v4().to_string().replace("-", "").to_uppercase()
+        util::nonce_str(util::NONCE_STR_LEN)
     }
 
-    fn mut_sign_data<S>(&self, prefix: S, prepay_id: S) -> SignData
+    fn mut_sign_data<S>(&self, prefix: S, prepay_id: S) -> Result<SignData, PayError>
     where
         S: AsRef<str>,
     {
@@ -123,15 +243,24 @@ pub trait WechatPayTrait {
             prefix = prefix.as_ref(),
             prepay_id = prepay_id.as_ref()
         );
-        let signed_str = self.rsa_sign(format!("{app_id}\n{now_time}\n{nonce_str}\n{ext_str}\n"));
-        SignData {
+        let signed_str =
+            self.rsa_sign(format!("{app_id}\n{now_time}\n{nonce_str}\n{ext_str}\n"))?;
+        Ok(SignData {
             app_id,
-            sign_type: "RSA".into(),
+            sign_type: self.signature_type().sign_type().into(),
             package: ext_str,
             nonce_str,
             timestamp: now_time,
             pay_sign: signed_str,
-        }
+        })
+    }
+
+    /// 使用当前商户号和appid生成小程序`wx.requestMerchantTransfer`的参数
+    fn merchant_transfer_data(
+        &self,
+        response: &TransferBillsResponse,
+    ) -> Result<MerchantTransferData, PayError> {
+        response.merchant_transfer_data(self.mch_id(), self.appid())
     }
 }
 
@@ -161,18 +290,367 @@ impl WechatPayTrait for WechatPay {
         self.base_url.clone()
     }
 
-    fn rsa_sign(&self, content: impl AsRef<str>) -> String {
-        let private_key = self.private_key.as_ref();
-        sign::sha256_sign(private_key, content.as_ref())
+    fn rsa_sign(&self, content: impl AsRef<str>) -> Result<String, PayError> {
+        match self.signing_key.as_ref() {
+            Ok(signing_key) => signing_key.sign(content.as_ref()),
+            Err(e) => Err(PayError::InvalidPrivateKey(e.clone())),
+        }
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// 使用注入的时钟，并加上检测到的时钟偏差
+    fn now_timestamp(&self) -> String {
+        self.timestamp().to_string()
+    }
+
+    fn signature_type(&self) -> SignatureType {
+        self.signature_type
     }
 }
 
 impl WechatPay {
-    #[allow(dead_code)]
-    fn with_base_url(mut self, base_url: impl AsRef<str>) -> Self {
+    /// 设置请求域名，默认为`https://api.mch.weixin.qq.com`
+    pub fn with_base_url(mut self, base_url: impl AsRef<str>) -> Self {
         self.base_url = base_url.as_ref().to_string();
         self
     }
+
+    /// 每个host保留的最大空闲连接数
+    /// 高并发场景下建议设置为与峰值并发接近的值(如32~64)，避免频繁新建TLS连接
+    /// 按新配置重建http客户端，构建失败时返回`PayError::RequestError`
+    pub fn with_pool_max_idle_per_host(mut self, max_idle: usize) -> Result<Self, PayError> {
+        self.http_config.pool_max_idle_per_host = Some(max_idle);
+        self.rebuild_client()
+    }
+
+    /// 空闲连接的保活时间，默认90秒
+    /// 建议小于微信支付服务端的keep-alive时间，避免复用已被对端关闭的连接，一般设置为30~60秒
+    /// 按新配置重建http客户端，构建失败时返回`PayError::RequestError`
+    pub fn with_pool_idle_timeout(mut self, idle_timeout: Duration) -> Result<Self, PayError> {
+        self.http_config.pool_idle_timeout = Some(idle_timeout);
+        self.rebuild_client()
+    }
+
+    /// 按http_config重建客户端，使用`with_client`传入的客户端时不重建
+    fn rebuild_client(mut self) -> Result<Self, PayError> {
+        if !self.http_config.external {
+            self.client = self.http_config.build_client()?;
+        }
+        Ok(self)
+    }
+
+    /// 使用已配置好的http客户端(代理、DNS解析、mTLS等)，async模式传入`reqwest::Client`，blocking模式传入`reqwest::blocking::Client`
+    /// SDK只负责添加签名等请求头，连接池由调用方管理，设置后`with_pool_*`不再生效
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.http_config.external = true;
+        self.client = client;
+        self
+    }
+
+    /// 设置双向TLS的商户证书(apiclient_cert.pem)和私钥(apiclient_key.pem)，用于要求携带商户证书的接口
+    /// 证书或私钥格式错误时返回错误，使用`with_client`传入的客户端时需在该客户端上自行配置
+    pub fn with_client_cert(
+        self,
+        cert_pem: impl AsRef<[u8]>,
+        key_pem: impl AsRef<[u8]>,
+    ) -> Result<Self, PayError> {
+        if self.http_config.external {
+            return Err(PayError::InvalidParams(
+                "client certificate must be configured on the external client".to_string(),
+            ));
+        }
+        #[cfg(any(feature = "native-tls", feature = "rustls"))]
+        {
+            let identity = client::client_identity(cert_pem.as_ref(), key_pem.as_ref())?;
+            let mut wechat_pay = self;
+            wechat_pay.http_config.identity = Some(identity);
+            wechat_pay.rebuild_client()
+        }
+        #[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+        {
+            let _ = (cert_pem, key_pem);
+            Err(PayError::InvalidParams(
+                "client certificate requires the native-tls or rustls feature".to_string(),
+            ))
+        }
+    }
+
+    /// 服务商模式，appid/mch_id作为服务商的sp_appid/sp_mchid
+    pub fn with_sub_mch_id(mut self, sub_mch_id: impl AsRef<str>) -> Self {
+        self.sub_mch_id = Some(sub_mch_id.as_ref().to_string());
+        self
+    }
+
+    /// 服务商模式下子商户的appid，不设置时payer.openid按sp_openid处理
+    pub fn with_sub_appid(mut self, sub_appid: impl AsRef<str>) -> Self {
+        self.sub_appid = Some(sub_appid.as_ref().to_string());
+        self
+    }
+
+    /// 开启应答验签
+    /// 缓存中没有应答证书序列号对应的平台证书时，会自动下载一次平台证书后重试
+    pub fn with_response_verify(mut self, response_verify: bool) -> Self {
+        self.response_verify = response_verify;
+        self
+    }
+
+    /// 设置备用域名，如`https://api2.mch.weixin.qq.com`
+    /// 主域名连接失败时按顺序切换到备用域名重试，已建立连接后的失败不会重试，避免重复下单
+    pub fn with_fallback_hosts(mut self, fallback_hosts: Vec<String>) -> Self {
+        self.fallback_hosts = fallback_hosts;
+        self
+    }
+
+    /// 设置签名算法，私钥与签名算法不匹配时panic
+    #[deprecated(
+        note = "使用try_with_signature_type，私钥与签名算法不匹配时返回PayError::InvalidPrivateKey"
+    )]
+    pub fn with_signature_type(self, signature_type: SignatureType) -> Self {
+        self.try_with_signature_type(signature_type)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// 设置签名算法，使用国密签名时需开启`sm` feature，并传入国密商户私钥与证书序列号
+    /// 按新的签名算法重新解析商户私钥，解析失败时返回`PayError::InvalidPrivateKey`
+    /// 同时更换私钥和签名算法时使用`try_new_with_signature_type`
+    pub fn try_with_signature_type(
+        mut self,
+        signature_type: SignatureType,
+    ) -> Result<Self, PayError> {
+        let signing_key = signature_type.parse_private_key(&self.private_key)?;
+        self.signature_type = signature_type;
+        self.signing_key = Arc::new(Ok(signing_key));
+        Ok(self)
+    }
+
+    /// 更换商户私钥，按当前签名算法解析，解析失败时返回`PayError::InvalidPrivateKey`
+    pub fn with_private_key(mut self, private_key: impl AsRef<str>) -> Result<Self, PayError> {
+        let signing_key = self
+            .signature_type
+            .parse_private_key(private_key.as_ref())?;
+        self.private_key = private_key.as_ref().to_string();
+        self.signing_key = Arc::new(Ok(signing_key));
+        Ok(self)
+    }
+
+    /// 开启dry-run，只构建并签名请求，不访问网络
+    /// 用于接入阶段核对请求内容与签名
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// debug日志是否输出完整的签名、随机串等敏感信息，默认只输出前缀，仅建议在本地排查签名问题时开启
+    pub fn with_log_sensitive(mut self, log_sensitive: bool) -> Self {
+        self.log_sensitive = log_sensitive;
+        self
+    }
+
+    /// 设置每个请求附加的header，如链路追踪的trace id
+    /// 附加header不参与签名，不会覆盖Authorization、Accept等SDK设置的header
+    pub fn with_default_headers(mut self, default_headers: HeaderMap) -> Self {
+        self.default_headers = default_headers;
+        self
+    }
+
+    /// 设置应答体大小上限，超过时返回`PayError::ResponseTooLarge`，避免异常的超大应答耗尽内存
+    pub fn with_max_response_size(mut self, max_response_size: usize) -> Self {
+        self.max_response_size = max_response_size;
+        self
+    }
+
+    /// 开启请求体gzip压缩，不小于min_size字节的请求体压缩后发送并带`Content-Encoding: gzip`
+    /// 签名串中的请求主体为文本，签名按压缩前的JSON计算；并非所有接口都接受压缩的请求体，
+    /// 建议clone出单独的实例只用于支持压缩的接口，默认关闭
+    #[cfg(feature = "gzip")]
+    pub fn with_request_gzip(mut self, min_size: usize) -> Self {
+        self.request_gzip = Some(min_size);
+        self
+    }
+
+    /// 设置User-Agent，便于微信支付排查问题时识别调用方
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// 设置Accept-Language，微信支付按该语言返回错误应答的message，如`zh-CN`、`en`
+    pub fn with_accept_language(mut self, accept_language: impl Into<String>) -> Self {
+        self.accept_language = accept_language.into();
+        self
+    }
+
+    /// 设置时钟，签名时间戳、调起支付参数的时间戳和证书有效期判断都从这里读取当前时间，默认为系统时间
+    /// 测试时可传入`FixedClock`得到确定的签名结果
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// 时钟的当前时间，已按检测到的时钟偏差校正
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now() + chrono::Duration::seconds(self.clock_skew())
+    }
+
+    /// 签名使用的unix时间戳，已按检测到的时钟偏差校正
+    pub(crate) fn timestamp(&self) -> i64 {
+        self.clock.timestamp() + self.clock_skew()
+    }
+
+    /// 检测到的时钟偏差，单位秒，为微信支付服务器时间减去本机时间，未检测到时为0
+    /// 签名时间戳超出范围导致401时按应答的Date头校正，可用于日志和监控本机时钟
+    pub fn clock_skew(&self) -> i64 {
+        self.clock_skew.load(Ordering::Relaxed)
+    }
+
+    /// 401应答的Date头与校正后的时间相差超过MAX_CLOCK_SKEW_SECS时，记录新的时钟偏差并返回
+    pub(crate) fn detect_clock_skew(&self, headers: &HeaderMap) -> Option<i64> {
+        let date = headers
+            .get(reqwest::header::DATE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok())?;
+        let skew = date.timestamp() - self.clock.timestamp();
+        if (skew - self.clock_skew()).abs() <= MAX_CLOCK_SKEW_SECS {
+            return None;
+        }
+        self.clock_skew.store(skew, Ordering::Relaxed);
+        Some(skew)
+    }
+
+    /// 按时钟的当前时间选出有效的平台证书，见`CertificateResponse::valid_at`
+    pub fn current_certificate<'a>(
+        &self,
+        certificates: &'a CertificateResponse,
+    ) -> Option<&'a Certificate> {
+        certificates.valid_at(&self.now())
+    }
+
+    /// 预置平台证书公钥，避免首次验签时下载平台证书
+    pub fn with_platform_certificate(
+        self,
+        serial_no: impl AsRef<str>,
+        pub_key: impl AsRef<str>,
+    ) -> Self {
+        self.platform_certs.insert(serial_no, pub_key);
+        self
+    }
+
+    /// 固定信任的平台证书序列号，之后只接受该序列号签名的应答和回调，其他序列号直接验签失败，不会自动下载证书
+    /// 对共享同一证书缓存的所有clone生效，切换证书时使用`rotate_platform_serial`
+    pub fn pin_platform_serial(&self, serial_no: &str) {
+        self.platform_certs
+            .set_pinned_serial(Some(serial_no.to_string()));
+    }
+
+    /// 取消固定的平台证书序列号，返回之前固定的序列号
+    pub fn unpin_platform_serial(&self) -> Option<String> {
+        self.platform_certs.set_pinned_serial(None)
+    }
+
+    /// 当前固定的平台证书序列号
+    pub fn pinned_platform_serial(&self) -> Option<String> {
+        self.platform_certs.pinned_serial()
+    }
+
+    /// 是否为服务商模式
+    pub fn is_partner(&self) -> bool {
+        self.sub_mch_id.is_some()
+    }
+
+    /// 下单接口地址，服务商模式使用partner接口
+    pub(crate) fn transactions_url(&self, pay_type: &PayType) -> &'static str {
+        match (pay_type, self.is_partner()) {
+            (PayType::Jsapi | PayType::Micro, false) => endpoints::TRANSACTIONS_JSAPI,
+            (PayType::Jsapi | PayType::Micro, true) => endpoints::PARTNER_TRANSACTIONS_JSAPI,
+            (PayType::App, false) => endpoints::TRANSACTIONS_APP,
+            (PayType::App, true) => endpoints::PARTNER_TRANSACTIONS_APP,
+            (PayType::H5, false) => endpoints::TRANSACTIONS_H5,
+            (PayType::H5, true) => endpoints::PARTNER_TRANSACTIONS_H5,
+            (PayType::Native | PayType::Qrcode, false) => endpoints::TRANSACTIONS_NATIVE,
+            (PayType::Native | PayType::Qrcode, true) => endpoints::PARTNER_TRANSACTIONS_NATIVE,
+        }
+    }
+
+    /// 按inject注入商户信息，未注入任何字段时请求体原样返回
+    /// 直连模式注入appid/mchid，服务商模式注入sp_appid/sub_appid、sp_mchid/sub_mchid，
+    /// 注入appid时把payer.openid转换为sub_openid(设置了sub_appid时)或sp_openid
+    pub(crate) fn inject_body(&self, json: &str, inject: InjectFields) -> Result<String, PayError> {
+        if inject.is_none() {
+            return Ok(json.to_string());
+        }
+        let mut map: Map<String, Value> = serde_json::from_str(json)?;
+        // 直接指定的sp_openid/sub_openid只能用于服务商模式，sub_openid还需要sub_appid
+        if let Some(Value::Object(payer)) = map.get("payer") {
+            if self.sub_mch_id.is_none()
+                && (payer.contains_key("sp_openid") || payer.contains_key("sub_openid"))
+            {
+                return Err(PayError::InvalidParams(
+                    "payer sp_openid/sub_openid requires partner mode, use openid".to_string(),
+                ));
+            }
+            if self.sub_appid.is_none() && payer.contains_key("sub_openid") {
+                return Err(PayError::InvalidParams(
+                    "payer sub_openid requires sub_appid".to_string(),
+                ));
+            }
+        }
+        match &self.sub_mch_id {
+            None => {
+                if inject.appid {
+                    map.insert("appid".to_owned(), self.appid().into());
+                }
+                if inject.mchid {
+                    map.insert("mchid".to_owned(), self.mch_id().into());
+                }
+            }
+            Some(sub_mch_id) => {
+                if inject.appid {
+                    map.insert("sp_appid".to_owned(), self.appid().into());
+                    if let Some(sub_appid) = &self.sub_appid {
+                        map.insert("sub_appid".to_owned(), sub_appid.as_str().into());
+                    }
+                    if let Some(Value::Object(payer)) = map.get_mut("payer")
+                        && let Some(openid) = payer.remove("openid")
+                    {
+                        let key = if self.sub_appid.is_some() {
+                            "sub_openid"
+                        } else {
+                            "sp_openid"
+                        };
+                        payer.insert(key.to_owned(), openid);
+                    }
+                }
+                if inject.mchid {
+                    map.insert("sp_mchid".to_owned(), self.mch_id().into());
+                    map.insert("sub_mchid".to_owned(), sub_mch_id.as_str().into());
+                }
+            }
+        }
+        // 订单上指定的notify_url优先，都未配置时在发出请求前报错，下单接口必须传回调地址
+        if inject.notify_url {
+            let notify_url = map
+                .entry("notify_url")
+                .or_insert_with(|| self.notify_url().into());
+            if notify_url.as_str().is_some_and(str::is_empty) {
+                return Err(PayError::InvalidParams(
+                    "notify_url is required, set it on WechatPay or the order params".to_string(),
+                ));
+            }
+        }
+        // 非https地址会被微信拒绝，在发出请求前报错
+        if let Some(notify_url) = map.get("notify_url") {
+            validate_notify_url_value(notify_url)?;
+        }
+        Ok(serde_json::to_string(&map)?)
+    }
+
+    /// 创建客户端，私钥格式错误时不会panic，而是在签名请求时返回`PayError::InvalidPrivateKey`
+    #[deprecated(
+        note = "使用WechatPay::try_new，私钥格式错误时构造即返回PayError::InvalidPrivateKey"
+    )]
     pub fn new<S: AsRef<str>>(
         appid: S,
         mch_id: S,
@@ -180,6 +658,85 @@ impl WechatPay {
         serial_no: S,
         v3_key: S,
         notify_url: S,
+    ) -> Self {
+        let signature_type = SignatureType::default();
+        let signing_key = signature_type
+            .parse_private_key(private_key.as_ref())
+            .map_err(|e| match e {
+                PayError::InvalidPrivateKey(message) => message,
+                e => e.to_string(),
+            });
+        // 默认配置只设置TLS后端，与reqwest::Client::new一致
+        let client = HttpConfig::default().build_client().unwrap_or_default();
+        Self::build(
+            signature_type,
+            signing_key,
+            client,
+            appid,
+            mch_id,
+            private_key,
+            serial_no,
+            v3_key,
+            notify_url,
+        )
+    }
+
+    /// 创建客户端，构造时按RSA解析商户私钥，私钥格式错误时返回InvalidPrivateKey，而不是在首次请求签名时失败
+    pub fn try_new<S: AsRef<str>>(
+        appid: S,
+        mch_id: S,
+        private_key: S,
+        serial_no: S,
+        v3_key: S,
+        notify_url: S,
+    ) -> Result<Self, PayError> {
+        Self::try_new_with_signature_type(
+            SignatureType::default(),
+            appid,
+            mch_id,
+            private_key,
+            serial_no,
+            v3_key,
+            notify_url,
+        )
+    }
+
+    /// 同try_new，按指定的签名算法解析商户私钥，如使用国密商户私钥时传入`SignatureType::Sm2WithSm3`
+    pub fn try_new_with_signature_type<S: AsRef<str>>(
+        signature_type: SignatureType,
+        appid: S,
+        mch_id: S,
+        private_key: S,
+        serial_no: S,
+        v3_key: S,
+        notify_url: S,
+    ) -> Result<Self, PayError> {
+        let signing_key = signature_type.parse_private_key(private_key.as_ref())?;
+        let client = HttpConfig::default().build_client()?;
+        Ok(Self::build(
+            signature_type,
+            Ok(signing_key),
+            client,
+            appid,
+            mch_id,
+            private_key,
+            serial_no,
+            v3_key,
+            notify_url,
+        ))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn build<S: AsRef<str>>(
+        signature_type: SignatureType,
+        signing_key: Result<PrivateKey, String>,
+        client: Client,
+        appid: S,
+        mch_id: S,
+        private_key: S,
+        serial_no: S,
+        v3_key: S,
+        notify_url: S,
     ) -> Self {
         Self {
             appid: appid.as_ref().to_string(),
@@ -189,12 +746,31 @@ impl WechatPay {
             v3_key: v3_key.as_ref().to_string(),
             notify_url: notify_url.as_ref().to_string(),
             base_url: "https://api.mch.weixin.qq.com".to_string(),
+            fallback_hosts: vec![],
+            signature_type,
+            signing_key: Arc::new(signing_key),
+            sub_mch_id: None,
+            sub_appid: None,
+            response_verify: false,
+            dry_run: false,
+            log_sensitive: false,
+            default_headers: HeaderMap::new(),
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            accept_language: DEFAULT_ACCEPT_LANGUAGE.to_string(),
+            #[cfg(feature = "gzip")]
+            request_gzip: None,
+            platform_certs: Arc::new(PlatformCertificates::default()),
+            clock: Arc::new(SystemClock),
+            clock_skew: Arc::new(AtomicI64::new(0)),
+            http_config: HttpConfig::default(),
+            client,
         }
     }
 
     #[cfg(feature = "debug-print")]
     pub fn open_debug(&self) {
-        std::env::set_var("RUST_LOG", "oss=debug");
+        unsafe { std::env::set_var("RUST_LOG", "oss=debug") };
         tracing_subscriber::fmt()
             .with_max_level(tracing::Level::DEBUG)
             .with_line_number(true)
@@ -202,61 +778,179 @@ impl WechatPay {
     }
 
     pub fn from_env() -> Self {
-        let appid = std::env::var("WECHAT_APPID").expect("WECHAT_APPID not found");
-        let mch_id = std::env::var("WECHAT_MCH_ID").expect("WECHAT_MCH_ID not found");
-        let private_key =
-            std::env::var("WECHAT_PRIVATE_KEY").expect("WECHAT_PRIVATE_KEY not found");
-        let serial_no = std::env::var("WECHAT_SERIAL_NO").expect("WECHAT_SERIAL_NO not found");
-        let v3_key = std::env::var("WECHAT_V3_KEY").expect("WECHAT_V3_KEY not found");
-        let notify_url = std::env::var("WECHAT_NOTIFY_URL").expect("WECHAT_NOTIFY_URL not found");
+        Self::from_env_with_prefix("")
+    }
+
+    /// 读取带前缀的环境变量，如前缀为`SHOP1`时读取`SHOP1_WECHAT_APPID`，用于同一进程加载多个商户配置
+    /// 前缀为空时与from_env相同
+    pub fn from_env_with_prefix(prefix: &str) -> Self {
+        Self::from_lookup_with_prefix(prefix, |key| std::env::var(key).ok())
+    }
+
+    /// 同from_env_with_prefix，配置项通过lookup读取，如从配置中心或测试中的HashMap读取
+    pub fn from_lookup_with_prefix(prefix: &str, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name: &str| {
+            let key = if prefix.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}_{}", prefix, name)
+            };
+            lookup(&key).unwrap_or_else(|| panic!("{} not found", key))
+        };
+        let appid = var("WECHAT_APPID");
+        let mch_id = var("WECHAT_MCH_ID");
+        let private_key = var("WECHAT_PRIVATE_KEY");
+        let serial_no = var("WECHAT_SERIAL_NO");
+        let v3_key = var("WECHAT_V3_KEY");
+        let notify_url = var("WECHAT_NOTIFY_URL");
         let private_key = std::fs::read_to_string(private_key).expect("read private key error");
-        Self::new(appid, mch_id, private_key, serial_no, v3_key, notify_url)
+        Self::try_new(appid, mch_id, private_key, serial_no, v3_key, notify_url)
+            .unwrap_or_else(|e| panic!("{}", e))
     }
 
-    pub(crate) fn build_header(
+    /// 返回参与签名的原始字符串`METHOD\nURL\ntimestamp\nnonce_str\nbody\n`，不发送请求
+    /// url为不含域名的路径和查询参数(与实际发送的编码一致)，GET请求body为空字符串，
+    /// 遇到401签名错误时可与微信支付签名验证工具生成的签名串逐字比对
+    pub fn sign_message(
         &self,
         method: HttpMethod,
         url: impl AsRef<str>,
         body: impl AsRef<str>,
-    ) -> Result<HeaderMap, PayError> {
-        let method = method.to_string();
-        let url = url.as_ref();
-        let body = body.as_ref();
-        let timestamp = chrono::Local::now().timestamp();
-        let serial_no = self.serial_no.to_string();
-        let nonce_str = Uuid::new_v4().to_string().replace("-", "").to_uppercase();
-        let message = format!(
+        timestamp: i64,
+        nonce_str: impl AsRef<str>,
+    ) -> String {
+        format!(
             "{}\n{}\n{}\n{}\n{}\n",
-            method, url, timestamp, nonce_str, body,
-        );
-        debug!("rsa_sign message: {}", message);
-        let signature = self.rsa_sign(message);
-        let authorization = format!(
-            "WECHATPAY2-SHA256-RSA2048 mchid=\"{}\",nonce_str=\"{}\",signature=\"{}\",timestamp=\"{}\",serial_no=\"{}\"",
+            method,
+            url.as_ref(),
+            timestamp,
+            nonce_str.as_ref(),
+            body.as_ref(),
+        )
+    }
+
+    /// 按给定的时间戳和随机串生成Authorization header，相同输入总是得到相同结果，可用于固定值测试
+    pub fn authorization(
+        &self,
+        method: HttpMethod,
+        url: impl AsRef<str>,
+        body: impl AsRef<str>,
+        timestamp: i64,
+        nonce_str: impl AsRef<str>,
+    ) -> Result<String, PayError> {
+        let nonce_str = nonce_str.as_ref();
+        let sign_message = |nonce_str: &str| {
+            self.sign_message(
+                method.clone(),
+                url.as_ref(),
+                body.as_ref(),
+                timestamp,
+                nonce_str,
+            )
+        };
+        // 脱敏后的日志只在开启debug-print时生成，避免每次签名多拼一次签名串
+        #[cfg(feature = "debug-print")]
+        {
+            let logged_message = sign_message(&self.loggable(nonce_str));
+            debug!("rsa_sign message: {}", logged_message);
+        }
+        let signature = self.rsa_sign(sign_message(nonce_str))?;
+        #[cfg(feature = "debug-print")]
+        {
+            let logged_authorization = self.format_authorization(
+                &self.loggable(nonce_str),
+                &self.loggable(&signature),
+                timestamp,
+            );
+            debug!("authorization: {}", logged_authorization);
+        }
+        Ok(self.format_authorization(nonce_str, &signature, timestamp))
+    }
+
+    fn format_authorization(&self, nonce_str: &str, signature: &str, timestamp: i64) -> String {
+        format!(
+            "{} mchid=\"{}\",nonce_str=\"{}\",signature=\"{}\",timestamp=\"{}\",serial_no=\"{}\"",
+            self.signature_type.auth_scheme(),
             self.mch_id,
             nonce_str,
             signature,
             timestamp,
-            serial_no,
-        );
-        debug!("authorization: {}", authorization);
-        let mut headers = HeaderMap::new();
+            self.serial_no,
+        )
+    }
+
+    /// 写入日志的值，未开启log_sensitive时只保留前缀
+    #[cfg(any(feature = "debug-print", test))]
+    pub(crate) fn loggable<'a>(&self, value: &'a str) -> std::borrow::Cow<'a, str> {
+        if self.log_sensitive {
+            std::borrow::Cow::Borrowed(value)
+        } else {
+            std::borrow::Cow::Owned(util::redact(value))
+        }
+    }
+
+    /// 使用随机串和当前时间签名请求
+    pub(crate) fn build_header(
+        &self,
+        method: HttpMethod,
+        url: impl AsRef<str>,
+        body: impl AsRef<str>,
+    ) -> Result<HeaderMap, PayError> {
+        let timestamp = self.timestamp();
+        self.sign_request(method, url, body, self.nonce_str(), timestamp)
+    }
+
+    /// 使用指定的随机串和时间戳签名请求，生成请求header
+    pub(crate) fn sign_request(
+        &self,
+        method: HttpMethod,
+        url: impl AsRef<str>,
+        body: impl AsRef<str>,
+        nonce_str: impl AsRef<str>,
+        timestamp: i64,
+    ) -> Result<HeaderMap, PayError> {
+        let authorization = self.authorization(method, url, body, timestamp, nonce_str)?;
+        // 附加header在前，签名相关的header覆盖同名的附加header
+        let mut headers = self.default_headers.clone();
         headers.insert(ACCEPT, "application/json".parse().unwrap());
-        let chrome_agent = "Mozilla/5.0 (Linux; Android 10; Redmi K30 Pro) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/86.0.4240.198 Mobile Safari/537.36";
-        headers.insert(USER_AGENT, chrome_agent.parse().unwrap());
+        let user_agent = HeaderValue::from_str(&self.user_agent)
+            .map_err(|e| PayError::InvalidParams(format!("invalid user agent: {}", e)))?;
+        headers.insert(USER_AGENT, user_agent);
+        let accept_language = HeaderValue::from_str(&self.accept_language)
+            .map_err(|e| PayError::InvalidParams(format!("invalid accept language: {}", e)))?;
+        headers.insert(ACCEPT_LANGUAGE, accept_language);
         headers.insert(AUTHORIZATION, authorization.parse().unwrap());
         headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
         Ok(headers)
     }
 }
 
+/// 请求体中的notify_url必须是非空的https字符串
+fn validate_notify_url_value(notify_url: &Value) -> Result<(), PayError> {
+    match notify_url {
+        Value::String(notify_url) => util::validate_notify_url(notify_url),
+        other => Err(PayError::InvalidParams(format!(
+            "notify_url must be a string, got {}",
+            other
+        ))),
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::error::PayError;
+    use crate::mock_server::PRIVATE_KEY;
+    use crate::model::{JsapiParams, ParamsTrait, PayerInfo};
     use crate::pay::{PayNotifyTrait, WechatPay, WechatPayTrait};
+    use crate::pay_type::PayType;
+    use crate::request::{HttpMethod, InjectFields};
+    use crate::sign::SignatureType;
     use dotenvy::dotenv;
-    use rsa::pkcs8::DecodePublicKey;
+    use reqwest::header::{ACCEPT, ACCEPT_LANGUAGE, AUTHORIZATION, HeaderMap, USER_AGENT};
+    use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
     use rsa::sha2::{Digest, Sha256};
     use rsa::{Pkcs1v15Sign, RsaPublicKey};
+    use serde_json::Value;
     use tracing::debug;
     use uuid::Uuid;
 
@@ -268,13 +962,656 @@ mod tests {
             .init();
     }
 
+    const NOTIFY_URL: &str = "https://mydomain.com/notify";
+
+    fn jsapi_json() -> String {
+        JsapiParams::new("测试支付1分", "1243243", 1.into(), "open_id".into()).to_json()
+    }
+
+    #[test]
+    fn test_direct_order_body() {
+        let wechat_pay = WechatPay::try_new(
+            "wx_appid",
+            "1900000001",
+            PRIVATE_KEY,
+            "",
+            "",
+            "https://mydomain.com/notify",
+        )
+        .unwrap();
+        assert_eq!(
+            wechat_pay.transactions_url(&PayType::Jsapi),
+            "/v3/pay/transactions/jsapi"
+        );
+        assert_eq!(
+            wechat_pay.transactions_url(&PayType::Native),
+            "/v3/pay/transactions/native"
+        );
+        let body: Value = serde_json::from_str(
+            &wechat_pay
+                .inject_body(&jsapi_json(), InjectFields::ALL)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body["appid"], "wx_appid");
+        assert_eq!(body["mchid"], "1900000001");
+        assert_eq!(body["notify_url"], "https://mydomain.com/notify");
+        assert_eq!(body["payer"]["openid"], "open_id");
+        assert!(body.get("sp_mchid").is_none());
+        assert!(body.get("sub_mchid").is_none());
+    }
+
+    #[test]
+    fn test_build_header_auth_scheme() {
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "serial", "", "").unwrap();
+        assert_eq!(wechat_pay.signature_type(), SignatureType::Sha256Rsa2048);
+        let headers = wechat_pay
+            .build_header(HttpMethod::GET, "/v3/certificates", "")
+            .unwrap();
+        let authorization = headers[AUTHORIZATION].to_str().unwrap();
+        assert!(authorization.starts_with("WECHATPAY2-SHA256-RSA2048 mchid=\"1900000001\","));
+        let sign_data = wechat_pay
+            .mut_sign_data("prepay_id=", "wx201410272009395522657a690389285100")
+            .unwrap();
+        assert_eq!(sign_data.sign_type, "RSA");
+    }
+
+    #[test]
+    fn test_authorization_golden() {
+        let wechat_pay = WechatPay::try_new(
+            "wx_appid",
+            "1900000001",
+            PRIVATE_KEY,
+            "5157F09EFDC096DE15EBE81A47057A7232F1B8E1",
+            "",
+            "",
+        )
+        .unwrap();
+        let body = r#"{"appid":"wx_appid","mchid":"1900000001","description":"测试支付1分"}"#;
+        let authorization = wechat_pay
+            .authorization(
+                HttpMethod::POST,
+                "/v3/pay/transactions/jsapi",
+                body,
+                1554208460,
+                "593BEC0C930BF1AFEB40B4A08C8FB242",
+            )
+            .unwrap();
+        assert_eq!(
+            authorization,
+            concat!(
+                "WECHATPAY2-SHA256-RSA2048 mchid=\"1900000001\",",
+                "nonce_str=\"593BEC0C930BF1AFEB40B4A08C8FB242\",",
+                "signature=\"ZDd1646XPrvwX9pOZuQrJhnMC1qeb7GqL1yc0t3JJKdPf6HGALR2IhfCUyxf7odSazq7IVBT888vYyTT3SmBQbaKIw+uNp+IIl8/JbUsy5OglGcR86H/GcP/vOkBReEwmqaG1B65iP/Pd9Nt/DS3leyibA3pJ2Bev5Er7XjB21KQJv+/t+nzs0/t6Dtf5XT6oqjtObBIgtcy8AKO+CKDKwOlgUzOheRq0ypYNQWdOTPScW+dOjE70Rp22iYmKAxNKwrx+xolY/CzYREDr9Xg0pnZPpSXbt44cnBfW6CMmOE8c/GoKPzXEqmd3QTktV/QLIEIsafXA187utDcDTCBpA==\",",
+                "timestamp=\"1554208460\",",
+                "serial_no=\"5157F09EFDC096DE15EBE81A47057A7232F1B8E1\""
+            )
+        );
+        assert_eq!(
+            authorization,
+            wechat_pay
+                .authorization(
+                    HttpMethod::POST,
+                    "/v3/pay/transactions/jsapi",
+                    body,
+                    1554208460,
+                    "593BEC0C930BF1AFEB40B4A08C8FB242",
+                )
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_loggable_redacts_by_default() {
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "serial", "", "").unwrap();
+        let logged = wechat_pay.format_authorization(
+            &wechat_pay.loggable("593BEC0C930BF1AFEB40B4A08C8FB242"),
+            &wechat_pay.loggable("ZDd1646XPrvwX9pOZuQrJhnMC1qeb7Gq"),
+            1554208460,
+        );
+        assert!(logged.contains("nonce_str=\"593B***\""));
+        assert!(logged.contains("signature=\"ZDd1***\""));
+        assert!(!logged.contains("593BEC0C930BF1AFEB40B4A08C8FB242"));
+
+        let wechat_pay = wechat_pay.with_log_sensitive(true);
+        assert_eq!(
+            wechat_pay.loggable("593BEC0C930BF1AFEB40B4A08C8FB242"),
+            "593BEC0C930BF1AFEB40B4A08C8FB242"
+        );
+    }
+
+    #[test]
+    fn test_with_clock() {
+        use crate::clock::FixedClock;
+        use crate::response::CertificateResponse;
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "serial", "", "")
+                .unwrap()
+                .with_clock(FixedClock::from_timestamp(1554208460));
+        let headers = wechat_pay
+            .build_header(HttpMethod::GET, "/v3/certificates", "")
+            .unwrap();
+        assert!(
+            headers[AUTHORIZATION]
+                .to_str()
+                .unwrap()
+                .contains(r#"timestamp="1554208460""#)
+        );
+        let sign_data = wechat_pay
+            .mut_sign_data("prepay_id=", "wx201410272009395522657a690389285100")
+            .unwrap();
+        assert_eq!(sign_data.timestamp, "1554208460");
+        // 克隆的实例使用同一个时钟
+        assert_eq!(wechat_pay.clone().now().timestamp(), 1554208460);
+        assert_eq!(WechatPayTrait::clock(&wechat_pay).timestamp(), 1554208460);
+
+        let json = r#"{"data":[
+            {"serial_no":"OLD","effective_time":"2018-06-08T10:34:56+08:00","expire_time":"2023-06-08T10:34:56+08:00",
+             "encrypt_certificate":{"algorithm":"AEAD_AES_256_GCM","nonce":"","associated_data":"certificate","ciphertext":""}}
+        ]}"#;
+        let certificates: CertificateResponse = serde_json::from_str(json).unwrap();
+        let serial = |wechat_pay: &WechatPay| {
+            wechat_pay
+                .current_certificate(&certificates)
+                .map(|cert| cert.serial_no.clone())
+        };
+        assert_eq!(serial(&wechat_pay), Some("OLD".to_string()));
+        assert_eq!(
+            certificates
+                .current(&FixedClock::from_timestamp(1554208460))
+                .map(|cert| cert.serial_no.as_str()),
+            Some("OLD")
+        );
+        let wechat_pay = wechat_pay.with_clock(FixedClock::from_timestamp(1704067200));
+        assert_eq!(serial(&wechat_pay), None);
+    }
+
+    #[test]
+    fn test_with_pool() {
+        use std::time::Duration;
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "serial", "", "")
+                .unwrap()
+                .with_pool_max_idle_per_host(32)
+                .and_then(|wechat_pay| wechat_pay.with_pool_idle_timeout(Duration::from_secs(30)))
+                .unwrap();
+        assert_eq!(wechat_pay.http_config.pool_max_idle_per_host, Some(32));
+        assert_eq!(
+            wechat_pay.http_config.pool_idle_timeout,
+            Some(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn test_sign_message() {
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "serial", "", "").unwrap();
+        let message = wechat_pay.sign_message(
+            HttpMethod::POST,
+            "/v3/pay/transactions/native",
+            r#"{"appid":"wx_appid"}"#,
+            1554208460,
+            "593BEC0C930BF1AFEB40B4A08C8FB242",
+        );
+        assert_eq!(
+            message,
+            "POST\n/v3/pay/transactions/native\n1554208460\n593BEC0C930BF1AFEB40B4A08C8FB242\n{\"appid\":\"wx_appid\"}\n"
+        );
+        // Authorization中的签名即是对该字符串的签名
+        let authorization = wechat_pay
+            .authorization(
+                HttpMethod::POST,
+                "/v3/pay/transactions/native",
+                r#"{"appid":"wx_appid"}"#,
+                1554208460,
+                "593BEC0C930BF1AFEB40B4A08C8FB242",
+            )
+            .unwrap();
+        assert!(authorization.contains(&format!(
+            "signature=\"{}\"",
+            wechat_pay.rsa_sign(&message).unwrap()
+        )));
+    }
+
+    #[test]
+    fn test_sign_request_injected_nonce_and_timestamp() {
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "serial", "", "").unwrap();
+        let sign = |nonce_str: &str, timestamp: i64| {
+            wechat_pay
+                .sign_request(
+                    HttpMethod::GET,
+                    "/v3/certificates",
+                    "",
+                    nonce_str,
+                    timestamp,
+                )
+                .unwrap()
+        };
+        let headers = sign("593BEC0C930BF1AFEB40B4A08C8FB242", 1554208460);
+        assert_eq!(
+            headers,
+            sign("593BEC0C930BF1AFEB40B4A08C8FB242", 1554208460)
+        );
+        assert_eq!(
+            headers[AUTHORIZATION].to_str().unwrap(),
+            wechat_pay
+                .authorization(
+                    HttpMethod::GET,
+                    "/v3/certificates",
+                    "",
+                    1554208460,
+                    "593BEC0C930BF1AFEB40B4A08C8FB242",
+                )
+                .unwrap()
+        );
+        assert_ne!(
+            headers[AUTHORIZATION],
+            sign("593BEC0C930BF1AFEB40B4A08C8FB243", 1554208460)[AUTHORIZATION]
+        );
+        assert_ne!(
+            headers[AUTHORIZATION],
+            sign("593BEC0C930BF1AFEB40B4A08C8FB242", 1554208461)[AUTHORIZATION]
+        );
+    }
+
+    #[test]
+    fn test_try_new_invalid_private_key() {
+        let wechat_pay = WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "", "", "");
+        assert!(wechat_pay.is_ok());
+        let error =
+            WechatPay::try_new("wx_appid", "1900000001", "not a pem", "", "", "").unwrap_err();
+        assert!(matches!(error, PayError::InvalidPrivateKey(_)));
+        // new不会panic，签名请求时返回InvalidPrivateKey
+        #[allow(deprecated)]
+        let wechat_pay = WechatPay::new("wx_appid", "1900000001", "not a pem", "", "", "");
+        let error = wechat_pay
+            .build_header(HttpMethod::GET, "/v3/certificates", "")
+            .unwrap_err();
+        assert!(matches!(error, PayError::InvalidPrivateKey(_)));
+    }
+
+    #[test]
+    fn test_cached_private_key_signature() {
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "serial", "", "").unwrap();
+        let content = "GET\n/v3/certificates\n1554208460\n593BEC0C930BF1AFEB40B4A08C8FB242\n\n";
+        let signature = wechat_pay.rsa_sign(content).unwrap();
+        assert_eq!(
+            signature,
+            crate::sign::sha256_sign(PRIVATE_KEY, content).unwrap()
+        );
+        let private_key = rsa::RsaPrivateKey::from_pkcs8_pem(PRIVATE_KEY).unwrap();
+        let hashed = Sha256::new().chain_update(content).finalize();
+        RsaPublicKey::from(private_key)
+            .verify(
+                Pkcs1v15Sign::new::<Sha256>(),
+                &hashed,
+                &crate::util::base64_decode(&signature).unwrap(),
+            )
+            .unwrap();
+
+        let wechat_pay = wechat_pay
+            .try_with_signature_type(SignatureType::Sha256Rsa2048)
+            .unwrap();
+        assert_eq!(wechat_pay.rsa_sign(content).unwrap(), signature);
+
+        // 更换私钥后使用新私钥签名，格式错误的私钥在设置时返回错误
+        const PLATFORM_KEY: &str = include_str!("testdata/platform_key.pem");
+        let error = wechat_pay
+            .clone()
+            .with_private_key("not a pem")
+            .unwrap_err();
+        assert!(matches!(error, PayError::InvalidPrivateKey(_)));
+        let wechat_pay = wechat_pay.with_private_key(PLATFORM_KEY).unwrap();
+        assert_eq!(wechat_pay.private_key(), PLATFORM_KEY);
+        assert_eq!(
+            wechat_pay.rsa_sign(content).unwrap(),
+            crate::sign::sha256_sign(PLATFORM_KEY, content).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_lookup_with_prefix() {
+        let key_path = std::env::temp_dir().join("wechat_pay_from_env_with_prefix.pem");
+        std::fs::write(&key_path, PRIVATE_KEY).unwrap();
+        let mut vars = std::collections::HashMap::new();
+        for (prefix, mch_id) in [("SHOP1", "1900000001"), ("SHOP2", "1900000002")] {
+            let values = [
+                ("WECHAT_APPID", "wx_appid"),
+                ("WECHAT_MCH_ID", mch_id),
+                ("WECHAT_PRIVATE_KEY", key_path.to_str().unwrap()),
+                ("WECHAT_SERIAL_NO", "serial"),
+                ("WECHAT_V3_KEY", "v3_key"),
+                ("WECHAT_NOTIFY_URL", "https://mydomain.com/notify"),
+            ];
+            for (name, value) in values {
+                vars.insert(format!("{}_{}", prefix, name), value.to_string());
+            }
+        }
+        let lookup = |key: &str| vars.get(key).cloned();
+        let shop1 = WechatPay::from_lookup_with_prefix("SHOP1", lookup);
+        let shop2 = WechatPay::from_lookup_with_prefix("SHOP2", lookup);
+        assert_eq!(shop1.mch_id, "1900000001");
+        assert_eq!(shop2.mch_id, "1900000002");
+        assert_eq!(shop2.notify_url, "https://mydomain.com/notify");
+        assert_eq!(shop2.private_key(), PRIVATE_KEY);
+    }
+
+    #[test]
+    fn test_default_headers() {
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert("x-trace-id", "trace-1".parse().unwrap());
+        default_headers.insert(AUTHORIZATION, "Bearer token".parse().unwrap());
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "serial", "", "")
+                .unwrap()
+                .with_default_headers(default_headers);
+        let headers = wechat_pay
+            .sign_request(
+                HttpMethod::GET,
+                "/v3/certificates",
+                "",
+                "593BEC0C930BF1AFEB40B4A08C8FB242",
+                1554208460,
+            )
+            .unwrap();
+        assert_eq!(headers["x-trace-id"], "trace-1");
+        assert_eq!(headers.get_all(AUTHORIZATION).iter().count(), 1);
+        assert_eq!(
+            headers[AUTHORIZATION].to_str().unwrap(),
+            wechat_pay
+                .authorization(
+                    HttpMethod::GET,
+                    "/v3/certificates",
+                    "",
+                    1554208460,
+                    "593BEC0C930BF1AFEB40B4A08C8FB242",
+                )
+                .unwrap()
+        );
+        assert_eq!(headers[ACCEPT], "application/json");
+    }
+
+    #[test]
+    fn test_user_agent() {
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "serial", "", "").unwrap();
+        let headers = wechat_pay
+            .build_header(HttpMethod::GET, "/v3/certificates", "")
+            .unwrap();
+        assert_eq!(
+            headers[USER_AGENT],
+            format!("wechat-pay-rust-sdk/{}", env!("CARGO_PKG_VERSION")).as_str()
+        );
+
+        let wechat_pay = wechat_pay.with_user_agent("my-shop/1.0");
+        let headers = wechat_pay
+            .build_header(HttpMethod::GET, "/v3/certificates", "")
+            .unwrap();
+        assert_eq!(headers[USER_AGENT], "my-shop/1.0");
+
+        let wechat_pay = wechat_pay.with_user_agent("my-shop\n");
+        let error = wechat_pay
+            .build_header(HttpMethod::GET, "/v3/certificates", "")
+            .unwrap_err();
+        assert!(matches!(error, PayError::InvalidParams(_)));
+    }
+
+    #[test]
+    fn test_accept_language() {
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "serial", "", "").unwrap();
+        let headers = wechat_pay
+            .build_header(HttpMethod::GET, "/v3/certificates", "")
+            .unwrap();
+        assert_eq!(headers[ACCEPT_LANGUAGE], "zh-CN");
+
+        let wechat_pay = wechat_pay.with_accept_language("en");
+        let headers = wechat_pay
+            .build_header(HttpMethod::GET, "/v3/certificates", "")
+            .unwrap();
+        assert_eq!(headers[ACCEPT_LANGUAGE], "en");
+
+        let wechat_pay = wechat_pay.with_accept_language("en\n");
+        let error = wechat_pay
+            .build_header(HttpMethod::GET, "/v3/certificates", "")
+            .unwrap_err();
+        assert!(matches!(error, PayError::InvalidParams(_)));
+    }
+
+    #[test]
+    fn test_clone_shares_certificates() {
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "serial", "", "")
+                .unwrap()
+                .with_platform_certificate("OLD", "old_pub_key");
+        let cloned = wechat_pay.clone();
+        assert_eq!(
+            cloned.platform_certs.get("OLD").as_deref(),
+            Some("old_pub_key")
+        );
+        cloned.platform_certs.insert("NEW", "new_pub_key");
+        cloned.platform_certs.refreshed();
+        assert_eq!(
+            wechat_pay.platform_certs.get("NEW").as_deref(),
+            Some("new_pub_key")
+        );
+        assert_eq!(wechat_pay.platform_certs.generation(), 1);
+        assert_eq!(
+            cloned.rsa_sign("content").unwrap(),
+            wechat_pay.rsa_sign("content").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_order_notify_url_override() {
+        let wechat_pay = WechatPay::try_new(
+            "wx_appid",
+            "1900000001",
+            PRIVATE_KEY,
+            "",
+            "",
+            "https://mydomain.com/notify",
+        )
+        .unwrap();
+        let mut params = JsapiParams::new("测试支付1分", "1243243", 1.into(), "open_id".into());
+        params.notify_url = Some("https://mydomain.com/mall/notify".to_string());
+        let body: Value = serde_json::from_str(
+            &wechat_pay
+                .inject_body(&params.to_json(), InjectFields::ALL)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body["notify_url"], "https://mydomain.com/mall/notify");
+    }
+
+    #[test]
+    fn test_order_without_notify_url() {
+        // 下单接口必须有回调地址，WechatPay和订单上都未配置时在本地报错
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "", "", "").unwrap();
+        let result = wechat_pay.inject_body(&jsapi_json(), InjectFields::ALL);
+        assert!(
+            matches!(result, Err(PayError::InvalidParams(message)) if message.contains("notify_url is required"))
+        );
+        // 订单上传空字符串同样报错
+        let mut params = JsapiParams::new("测试支付1分", "1243243", 1.into(), "open_id".into());
+        params.notify_url = Some(String::new());
+        let wechat_pay = WechatPay::try_new(
+            "wx_appid",
+            "1900000001",
+            PRIVATE_KEY,
+            "",
+            "",
+            "https://mydomain.com/notify",
+        )
+        .unwrap();
+        let result = wechat_pay.inject_body(&params.to_json(), InjectFields::ALL);
+        assert!(matches!(result, Err(PayError::InvalidParams(_))));
+
+        // 订单上指定时WechatPay可以不配置
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "", "", "").unwrap();
+        params.notify_url = Some("https://mydomain.com/mall/notify".to_string());
+        let body: Value = serde_json::from_str(
+            &wechat_pay
+                .inject_body(&params.to_json(), InjectFields::ALL)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body["notify_url"], "https://mydomain.com/mall/notify");
+        assert_eq!(body["appid"], "wx_appid");
+    }
+
+    #[test]
+    fn test_params_notify_url_without_inject() {
+        // 不注入字段时请求体原样返回，参数上的notify_url由ParamsTrait::notify_url在发送前校验
+        let wechat_pay =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "", "", "").unwrap();
+        let json = r#"{"service_id":"500001","reason":"\"notify_url\":1"}"#;
+        assert_eq!(
+            wechat_pay.inject_body(json, InjectFields::NONE).unwrap(),
+            json
+        );
+        let mut params = crate::model::PayScorePermissionParams::new("500001", "1234323");
+        assert_eq!(params.notify_url(), None);
+        params.notify_url = Some("http://mydomain.com/notify".to_string());
+        assert_eq!(params.notify_url(), Some("http://mydomain.com/notify"));
+    }
+
+    #[test]
+    fn test_partner_explicit_openid() {
+        let json = |payer: PayerInfo| {
+            JsapiParams::new("测试支付1分", "1243243", 1.into(), payer).to_json()
+        };
+        let partner =
+            WechatPay::try_new("wx_sp_appid", "1900000001", PRIVATE_KEY, "", "", NOTIFY_URL)
+                .unwrap()
+                .with_sub_mch_id("1900000109");
+        let body: Value = serde_json::from_str(
+            &partner
+                .inject_body(&json(PayerInfo::sp_openid("sp_open_id")), InjectFields::ALL)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            body["payer"],
+            serde_json::json!({"sp_openid": "sp_open_id"})
+        );
+        // sub_openid需要sub_appid
+        let result = partner.inject_body(
+            &json(PayerInfo::sub_openid("sub_open_id")),
+            InjectFields::ALL,
+        );
+        assert!(matches!(result, Err(PayError::InvalidParams(_))));
+        let partner = partner.with_sub_appid("wx_sub_appid");
+        let body: Value = serde_json::from_str(
+            &partner
+                .inject_body(
+                    &json(PayerInfo::sub_openid("sub_open_id")),
+                    InjectFields::ALL,
+                )
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            body["payer"],
+            serde_json::json!({"sub_openid": "sub_open_id"})
+        );
+
+        let direct =
+            WechatPay::try_new("wx_appid", "1900000001", PRIVATE_KEY, "", "", NOTIFY_URL).unwrap();
+        let result =
+            direct.inject_body(&json(PayerInfo::sp_openid("sp_open_id")), InjectFields::ALL);
+        assert!(matches!(result, Err(PayError::InvalidParams(_))));
+    }
+
+    #[test]
+    fn test_partner_order_body() {
+        let wechat_pay = WechatPay::try_new(
+            "wx_sp_appid",
+            "1900000001",
+            PRIVATE_KEY,
+            "",
+            "",
+            "https://mydomain.com/notify",
+        )
+        .unwrap()
+        .with_sub_mch_id("1900000109")
+        .with_sub_appid("wx_sub_appid");
+        assert_eq!(
+            wechat_pay.transactions_url(&PayType::Jsapi),
+            "/v3/pay/partner/transactions/jsapi"
+        );
+        assert_eq!(
+            wechat_pay.transactions_url(&PayType::H5),
+            "/v3/pay/partner/transactions/h5"
+        );
+        let body: Value = serde_json::from_str(
+            &wechat_pay
+                .inject_body(&jsapi_json(), InjectFields::ALL)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body["sp_appid"], "wx_sp_appid");
+        assert_eq!(body["sp_mchid"], "1900000001");
+        assert_eq!(body["sub_mchid"], "1900000109");
+        assert_eq!(body["sub_appid"], "wx_sub_appid");
+        assert_eq!(body["payer"]["sub_openid"], "open_id");
+        assert!(body["payer"].get("openid").is_none());
+        assert!(body.get("appid").is_none());
+        assert!(body.get("mchid").is_none());
+    }
+
+    #[test]
+    fn test_inject_fields() {
+        let wechat_pay = WechatPay::try_new(
+            "wx_appid",
+            "1900000001",
+            PRIVATE_KEY,
+            "",
+            "",
+            "https://mydomain.com/notify",
+        )
+        .unwrap();
+        let json = r#"{"out_refund_no":"1217752501201407033233368018"}"#;
+        assert_eq!(
+            wechat_pay.inject_body(json, InjectFields::NONE).unwrap(),
+            json
+        );
+        let body: Value = serde_json::from_str(
+            &wechat_pay
+                .inject_body(json, InjectFields::APPID | InjectFields::NOTIFY_URL)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body["appid"], "wx_appid");
+        assert_eq!(body["notify_url"], "https://mydomain.com/notify");
+        assert!(body.get("mchid").is_none());
+
+        let wechat_pay = wechat_pay.with_sub_mch_id("1900000109");
+        let body: Value = serde_json::from_str(
+            &wechat_pay
+                .inject_body(&jsapi_json(), InjectFields::MCHID)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body["sp_mchid"], "1900000001");
+        assert_eq!(body["sub_mchid"], "1900000109");
+        assert!(body.get("sp_appid").is_none());
+        assert_eq!(body["payer"]["openid"], "open_id");
+    }
+
     #[test]
     fn test_rsa_sign() {
         init_log();
         let private_key_path = "./apiclient_key.pem";
         let private_key = std::fs::read_to_string(private_key_path).unwrap();
-        let wechat_pay = WechatPay::new("", "", private_key.as_ref(), "", "", "");
-        let sign_str = wechat_pay.rsa_sign("hello");
+        let wechat_pay = WechatPay::try_new("", "", private_key.as_ref(), "", "", "").unwrap();
+        let sign_str = wechat_pay.rsa_sign("hello").unwrap();
         debug!("sign_str: {}", sign_str);
     }
 
@@ -302,6 +1639,65 @@ mod tests {
         debug!("data: {:#?}", data);
     }
 
+    /// AEAD_AES_256_GCM已知向量，nonce和associated_data取自资源本身
+    #[test]
+    fn test_decrypt_aead_vectors() {
+        use crate::model::{WechatPayDecodeData, WechatPayNotifySource};
+        use crate::response::EncryptCertificate;
+
+        let wechat_pay = WechatPay::try_new(
+            "wx_appid",
+            "1900000001",
+            PRIVATE_KEY,
+            "",
+            "0123456789abcdef0123456789abcdef",
+            "",
+        )
+        .unwrap();
+        let mut certificate = EncryptCertificate {
+            algorithm: "AEAD_AES_256_GCM".to_string(),
+            nonce: "d3f7a1c9e2b4".to_string(),
+            associated_data: "certificate".to_string(),
+            ciphertext: "UfsGSyqv5xnB8ZdeaUT6H5+M1pm2IoIYkYk3z5xUGfJyVAfyF0Ds63+/0md+vcwlQZYLJ2vLWb64XOrLI0c7MBwBiarVPQxcgc+X8OgTsQ==".to_string(),
+        };
+        let plaintext = wechat_pay.decrypt_aead(&certificate).unwrap();
+        assert_eq!(
+            String::from_utf8(plaintext).unwrap(),
+            "-----BEGIN CERTIFICATE-----\nMIIDtest\n-----END CERTIFICATE-----\n"
+        );
+
+        let mut resource = WechatPayNotifySource {
+            algorithm: "AEAD_AES_256_GCM".to_string(),
+            ciphertext: "Iz6VFhANjqukuStkn5nfekAc8oIRTV//af7tgXV9bXJnufEetO3Y2E71/dOyO3g0cVWFNtMIYzFojLh+2CrVF+6S1E+trr9lo0oK1SHMQQLGoREKiP+01v8zrCxWi93W+cNzKQZdviKVYxBUlP1jbS+dTn6fy5rv/DzbYAXmdyyiIaOwr1rTiNIdZyT0a7Xg9P+jfDpzx8R7ettGy7BU3iZ3uWKX0xErztK5ivCZkb2Zc5Rnz8dnDc3xYzCYpS7F4bd7LKFISJp4CUMiqnI59lf13YtB1az8UFNvBD4REJSG5lRJ6Tsx8xjgbi2V6ufAzlC1BBRnLVUhPfQnItgYA2NboYuB8658FJU3YM9XiBJHByK3szV/zYxxmNnxpfp5u3ee0kshFGkuSNA14CfjLbSpU/yOIiiSl+901aJsXIpfFFs0KmZE5rGIYz8PMUDo2bK9mgWW0JwMgqsd4C+WJXZK8v5ebfM50spkudAODVC1zw3hX0MXSSptjnNg4EdTBlz/eRu/hoM=".to_string(),
+            associated_data: Some("transaction".to_string()),
+            original_type: "transaction".to_string(),
+            nonce: "uaGeNnBYNjl7".to_string(),
+        };
+        let data: WechatPayDecodeData = wechat_pay.decrypt_aead_as(&resource).unwrap();
+        assert_eq!(data.out_trade_no, "1243243");
+        assert_eq!(data.trade_state, "SUCCESS");
+        assert_eq!(data.trade_state_desc, "支付成功");
+        assert_eq!(data.amount.total, 1);
+
+        // associated_data参与认证，用错资源类型时解密失败
+        resource.associated_data = Some("refund".to_string());
+        assert!(matches!(
+            wechat_pay.decrypt_aead(&resource),
+            Err(PayError::DecryptError(_))
+        ));
+        certificate.associated_data = "transaction".to_string();
+        assert!(matches!(
+            wechat_pay.decrypt_aead(&certificate),
+            Err(PayError::DecryptError(_))
+        ));
+        certificate.associated_data = "certificate".to_string();
+        certificate.algorithm = "AEAD_SM4_GCM".to_string();
+        assert!(matches!(
+            wechat_pay.decrypt_aead(&certificate),
+            Err(PayError::DecryptError(message)) if message.contains("AEAD_SM4_GCM")
+        ));
+    }
+
     /// 把微信支付平台证书序列号转换成16进制字符串
     /// ```text
     /// -----BEGIN CERTIFICATE-----
@@ -1,4 +1,6 @@
+use reqwest::header::HeaderMap;
 use std::fmt::{Display, Formatter};
+use std::ops::BitOr;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum HttpMethod {
@@ -24,3 +26,71 @@ impl Display for HttpMethod {
 unsafe impl Send for HttpMethod {}
 
 unsafe impl Sync for HttpMethod {}
+
+/// 签名前向请求体注入的商户字段
+/// 下单接口注入全部字段，退款、转账、分账等接口的请求体不能带appid等字段时使用`NONE`
+/// 服务商模式下appid对应sp_appid/sub_appid，mchid对应sp_mchid/sub_mchid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InjectFields {
+    pub appid: bool,
+    pub mchid: bool,
+    pub notify_url: bool,
+}
+
+impl InjectFields {
+    /// 注入appid、mchid和notify_url，用于下单接口
+    pub const ALL: Self = Self {
+        appid: true,
+        mchid: true,
+        notify_url: true,
+    };
+    /// 请求体原样发送
+    pub const NONE: Self = Self {
+        appid: false,
+        mchid: false,
+        notify_url: false,
+    };
+    pub const APPID: Self = Self {
+        appid: true,
+        ..Self::NONE
+    };
+    pub const MCHID: Self = Self {
+        mchid: true,
+        ..Self::NONE
+    };
+    pub const NOTIFY_URL: Self = Self {
+        notify_url: true,
+        ..Self::NONE
+    };
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+}
+
+impl BitOr for InjectFields {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self {
+            appid: self.appid || rhs.appid,
+            mchid: self.mchid || rhs.mchid,
+            notify_url: self.notify_url || rhs.notify_url,
+        }
+    }
+}
+
+/// dry-run模式下未发送的请求，签名等header与实际发送时完全一致
+#[derive(Debug, Clone)]
+pub struct DryRunRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl Display for DryRunRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.method, self.url)
+    }
+}
@@ -1,74 +1,400 @@
+use crate::clock::Clock;
+use crate::error::{PayError, WechatErrorCode};
+use crate::model::{
+    AeadResource, FapiaoItem, PayScoreAuthorizationState, PayScoreLocation, PayScorePostDiscount,
+    PayScorePostPayment, PayScoreRiskFund, PayScoreState, PayScoreTimeRange, Payer, QueryAmount,
+    RefundChannel, RefundStatus, TransferBillState, TransferReceiptState,
+};
+use crate::pagination::Paginated;
+use chrono::{DateTime, FixedOffset, TimeZone};
 use serde::de::DeserializeOwned;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 
-pub trait ResponseTrait: DeserializeOwned {}
+/// 微信支付应答头中的请求ID
+pub const REQUEST_ID: &str = "request-id";
+
+pub trait ResponseTrait: DeserializeOwned {
+    /// 是否保留原始JSON，覆盖`set_raw`的应答设为true；为false时应答直接解析为结构，不经过Value
+    const KEEP_RAW: bool = false;
+    /// 保存应答的原始JSON，需要保留原始JSON的应答覆盖该方法
+    fn set_raw(&mut self, _raw: Value) {}
+    /// 保存应答头Request-ID，需要保留的应答覆盖该方法
+    fn set_request_id(&mut self, _request_id: String) {}
+    /// 保存应答状态码，`WeChatResponse`记录到错误应答中
+    fn set_status(&mut self, _status: u16) {}
+    /// 微信支付应答头中的Request-ID，向微信支付反馈问题时提供，未保留时返回None
+    fn request_id(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl ResponseTrait for Value {}
+
+/// 应答是否成功，下单等接口直接返回应答结构，出错时只有code/message有值
+/// 与`WeChatResponse`一致地通过`is_success`/`err`判断
+pub trait ResponseStatus {
+    ///【错误码】 成功时为None
+    fn error_code(&self) -> Option<&str>;
+    ///【错误信息】
+    fn error_message(&self) -> Option<&str>;
+    ///【错误详情】 参数校验失败时指出出错的字段
+    fn error_detail(&self) -> Option<&ErrorDetail> {
+        None
+    }
+
+    fn is_success(&self) -> bool {
+        self.error_code().is_none()
+    }
+
+    fn err(&self) -> Option<ErrorResponse> {
+        self.error_code().map(|code| ErrorResponse {
+            code: Some(code.to_string()),
+            message: self.error_message().map(|message| message.to_string()),
+            detail: self.error_detail().cloned(),
+            ..Default::default()
+        })
+    }
+}
+
+/// 实现`ResponseTrait`并保留应答头Request-ID，结构体需带有`#[serde(skip)] request_id: Option<String>`
+/// 带`raw`时同时保存原始JSON，结构体需带有`#[serde(skip)] raw: Value`
+macro_rules! impl_request_id {
+    ($name:ty) => {
+        impl_request_id!(@inherent $name);
+
+        impl ResponseTrait for $name {
+            fn set_request_id(&mut self, request_id: String) {
+                self.request_id = Some(request_id);
+            }
+
+            fn request_id(&self) -> Option<&str> {
+                self.request_id.as_deref()
+            }
+        }
+    };
+    ($name:ty, raw) => {
+        impl_request_id!(@inherent $name);
+
+        impl ResponseTrait for $name {
+            const KEEP_RAW: bool = true;
+
+            fn set_raw(&mut self, raw: Value) {
+                self.raw = raw;
+            }
+
+            fn set_request_id(&mut self, request_id: String) {
+                self.request_id = Some(request_id);
+            }
+
+            fn request_id(&self) -> Option<&str> {
+                self.request_id.as_deref()
+            }
+        }
+    };
+    (@inherent $name:ty) => {
+        impl $name {
+            /// 应答头中的Request-ID，向微信支付反馈问题时提供
+            pub fn request_id(&self) -> Option<&str> {
+                self.request_id.as_deref()
+            }
+        }
+    };
+}
+
+macro_rules! impl_response_status {
+    ($($name:ty),+) => {
+        $(
+            impl ResponseStatus for $name {
+                fn error_code(&self) -> Option<&str> {
+                    self.code.as_deref()
+                }
+
+                fn error_message(&self) -> Option<&str> {
+                    self.message.as_deref()
+                }
+
+                fn error_detail(&self) -> Option<&ErrorDetail> {
+                    self.detail.as_ref()
+                }
+
+                fn err(&self) -> Option<ErrorResponse> {
+                    self.error_code().map(|code| ErrorResponse {
+                        code: Some(code.to_string()),
+                        message: self.message.clone(),
+                        detail: self.detail.clone(),
+                        request_id: self.request_id.clone(),
+                        ..Default::default()
+                    })
+                }
+            }
+        )+
+    };
+}
+
+impl_response_status!(
+    NativeResponse,
+    JsapiResponse,
+    AppResponse,
+    MicroResponse,
+    H5Response
+);
 
 #[derive(Debug, Deserialize)]
 pub struct NativeResponse {
     pub code: Option<String>,
     pub message: Option<String>,
+    ///【错误详情】 参数校验失败时指出出错的字段
+    #[serde(default)]
+    pub detail: Option<ErrorDetail>,
     ///【支付跳转链接】 h5_url为拉起微信支付收银台的中间页面，可通过访问该URL来拉起微信客户端，完成支付，h5_url的有效期为5分钟。
     pub code_url: Option<String>,
+    #[serde(skip)]
+    request_id: Option<String>,
+}
+
+impl NativeResponse {
+    /// 二维码链接，微信返回错误时返回错误码和错误信息
+    pub fn code_url(&self) -> Result<&str, PayError> {
+        self.code_url
+            .as_deref()
+            .ok_or_else(|| ResponseStatus::err(self).unwrap_or_default().into())
+    }
+
+    /// 将二维码链接生成PNG图片，返回图片内容
+    #[cfg(feature = "qrcode")]
+    pub fn qr_png(&self) -> Result<Vec<u8>, PayError> {
+        let code = qrcode::QrCode::new(self.code_url()?)
+            .map_err(|e| PayError::QrCodeError(e.to_string()))?;
+        let image = code.render::<image::Luma<u8>>().build();
+        let mut png = std::io::Cursor::new(vec![]);
+        image
+            .write_to(&mut png, image::ImageFormat::Png)
+            .map_err(|e| PayError::QrCodeError(e.to_string()))?;
+        Ok(png.into_inner())
+    }
 }
 
-impl ResponseTrait for NativeResponse {}
+impl_request_id!(NativeResponse);
 
 #[derive(Debug, Deserialize)]
 pub struct JsapiResponse {
     pub code: Option<String>,
     pub message: Option<String>,
+    ///【错误详情】 参数校验失败时指出出错的字段
+    #[serde(default)]
+    pub detail: Option<ErrorDetail>,
     ///【预支付交易会话标识】 预支付交易会话标识。用于后续接口调用中使用，该值有效期为2小时
     pub prepay_id: Option<String>,
     ///【签名数据】
     pub sign_data: Option<SignData>,
+    #[serde(skip)]
+    request_id: Option<String>,
 }
 
+/// 调起支付的签名数据，字段名与`wx.requestPayment`/`WeixinJSBridge`的参数一致，可直接序列化后返回给前端
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SignData {
+    #[serde(rename = "appId")]
     pub app_id: String,
+    #[serde(rename = "signType")]
     pub sign_type: String,
     pub package: String,
+    #[serde(rename = "nonceStr")]
     pub nonce_str: String,
+    #[serde(rename = "timeStamp")]
     pub timestamp: String,
+    #[serde(rename = "paySign")]
     pub pay_sign: String,
 }
 
-impl ResponseTrait for JsapiResponse {}
+impl_request_id!(JsapiResponse);
+
+impl JsapiResponse {
+    /// 生成`wx.requestPayment`/`WeixinJSBridge`调起支付的JSON，字段名大小写与前端接口一致，可直接返回给前端
+    /// 微信返回错误时返回错误码和错误信息
+    pub fn to_request_payment_json(&self) -> Result<Value, PayError> {
+        let sign_data = self
+            .sign_data
+            .as_ref()
+            .ok_or_else(|| PayError::from(ResponseStatus::err(self).unwrap_or_default()))?;
+        Ok(serde_json::to_value(sign_data)?)
+    }
+}
 
 #[derive(Debug, Deserialize)]
 pub struct AppResponse {
     pub code: Option<String>,
     pub message: Option<String>,
+    ///【错误详情】 参数校验失败时指出出错的字段
+    #[serde(default)]
+    pub detail: Option<ErrorDetail>,
     ///【预支付交易会话标识】 预支付交易会话标识。用于后续接口调用中使用，该值有效期为2小时
     pub prepay_id: Option<String>,
     ///【签名数据】
     pub sign_data: Option<SignData>,
+    #[serde(skip)]
+    request_id: Option<String>,
 }
 
-impl ResponseTrait for AppResponse {}
+impl_request_id!(AppResponse);
 
 #[derive(Debug, Deserialize)]
 pub struct MicroResponse {
     pub code: Option<String>,
     pub message: Option<String>,
+    ///【错误详情】 参数校验失败时指出出错的字段
+    #[serde(default)]
+    pub detail: Option<ErrorDetail>,
     ///【预支付交易会话标识】 预支付交易会话标识。用于后续接口调用中使用，该值有效期为2小时
     pub prepay_id: Option<String>,
     ///【签名数据】
     pub sign_data: Option<SignData>,
+    #[serde(skip)]
+    request_id: Option<String>,
 }
 
-impl ResponseTrait for MicroResponse {}
+impl_request_id!(MicroResponse);
+
+impl From<JsapiResponse> for MicroResponse {
+    fn from(value: JsapiResponse) -> Self {
+        Self {
+            code: value.code,
+            message: value.message,
+            detail: value.detail,
+            prepay_id: value.prepay_id,
+            sign_data: value.sign_data,
+            request_id: value.request_id,
+        }
+    }
+}
 
 #[derive(Debug, Deserialize)]
 pub struct H5Response {
     pub code: Option<String>,
     pub message: Option<String>,
+    ///【错误详情】 参数校验失败时指出出错的字段
+    #[serde(default)]
+    pub detail: Option<ErrorDetail>,
     ///【二维码链接】 此URL用于生成支付二维码，然后提供给用户扫码支付。
     /// 注意：code_url并非固定值，使用时按照URL格式转成二维码即可。
     pub h5_url: Option<String>,
+    #[serde(skip)]
+    request_id: Option<String>,
+}
+
+impl_request_id!(H5Response);
+
+/// H5支付的跳转信息
+#[derive(Debug, Clone)]
+pub struct H5LaunchInfo {
+    ///【支付跳转链接】 下单返回的h5_url
+    pub h5_url: String,
+    ///【微信客户端跳转链接】 从h5_url页面解析出的weixin://链接
+    pub weixin_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrderQueryResponse {
+    ///【公众账号ID】
+    pub appid: String,
+    ///【商户号】
+    pub mchid: String,
+    ///【商户订单号】 商户系统内部订单号
+    pub out_trade_no: String,
+    ///【微信支付订单号】 微信支付系统生成的订单号，订单未支付时不返回。
+    pub transaction_id: Option<String>,
+    ///【交易类型】 JSAPI、NATIVE、APP、MICROPAY、MWEB、FACEPAY
+    pub trade_type: Option<String>,
+    ///【交易状态】 SUCCESS、REFUND、NOTPAY、CLOSED、REVOKED、USERPAYING、PAYERROR
+    pub trade_state: String,
+    ///【交易状态描述】
+    pub trade_state_desc: String,
+    ///【银行类型】
+    pub bank_type: Option<String>,
+    ///【附加数据】 在查询API和支付通知中原样返回
+    pub attach: Option<String>,
+    ///【支付完成时间】 遵循rfc3339标准格式
+    pub success_time: Option<String>,
+    ///【支付者】
+    pub payer: Option<Payer>,
+    ///【订单金额】
+    pub amount: Option<QueryAmount>,
+    ///【优惠功能】 享受优惠时返回该字段，列出订单使用的代金券
+    pub promotion_detail: Option<Vec<PromotionDetailResponse>>,
+    #[serde(skip)]
+    raw: Value,
+    #[serde(skip)]
+    request_id: Option<String>,
+}
+
+#[cfg(feature = "chrono")]
+impl OrderQueryResponse {
+    /// 解析后的支付完成时间，未支付或格式不符合预期时返回None
+    pub fn success_at(&self) -> Option<DateTime<FixedOffset>> {
+        crate::time::parse_option(self.success_time.as_deref())
+    }
+}
+
+/// 订单使用的代金券，订单查询和支付回调共用
+/// 记账时按wechatpay_contribute、merchant_contribute、other_contribute区分微信、商户和其他出资
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromotionDetailResponse {
+    ///【券ID】
+    pub coupon_id: String,
+    ///【优惠名称】
+    pub name: Option<String>,
+    ///【优惠范围】 GLOBAL：全场代金券；SINGLE：单品优惠
+    pub scope: Option<String>,
+    ///【优惠类型】 CASH：充值型代金券；NOCASH：免充值型代金券
+    pub r#type: Option<String>,
+    ///【优惠券面额】 单位为分
+    pub amount: i32,
+    ///【活动ID】
+    pub stock_id: Option<String>,
+    ///【微信出资】 单位为分
+    pub wechatpay_contribute: Option<i32>,
+    ///【商户出资】 单位为分
+    pub merchant_contribute: Option<i32>,
+    ///【其他出资】 单位为分
+    pub other_contribute: Option<i32>,
+    ///【优惠币种】 CNY：人民币
+    pub currency: Option<String>,
+    ///【单品列表】 单品优惠时返回，每个商品的优惠金额
+    pub goods_detail: Option<Vec<PromotionGoodsDetailResponse>>,
 }
 
-impl ResponseTrait for H5Response {}
+/// 订单使用的优惠，见`PromotionDetailResponse`
+pub type Promotion = PromotionDetailResponse;
+
+/// 单品优惠的商品
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromotionGoodsDetailResponse {
+    ///【商品编码】
+    pub goods_id: String,
+    ///【商品数量】
+    pub quantity: i32,
+    ///【商品单价】 单位为分
+    pub unit_price: i32,
+    ///【商品优惠金额】 单位为分
+    pub discount_amount: i32,
+    ///【商品备注】
+    pub goods_remark: Option<String>,
+}
+
+impl OrderQueryResponse {
+    /// 订单使用的代金券，没有优惠时为空
+    pub fn promotions(&self) -> &[PromotionDetailResponse] {
+        self.promotion_detail.as_deref().unwrap_or_default()
+    }
+
+    /// 原始JSON，可读取尚未建模的新字段
+    pub fn raw(&self) -> &Value {
+        &self.raw
+    }
+}
+
+impl_request_id!(OrderQueryResponse, raw);
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct EncryptCertificate {
@@ -78,23 +404,74 @@ pub struct EncryptCertificate {
     pub ciphertext: String,
 }
 
+impl AeadResource for EncryptCertificate {
+    fn algorithm(&self) -> &str {
+        self.algorithm.as_str()
+    }
+
+    fn ciphertext(&self) -> &str {
+        self.ciphertext.as_str()
+    }
+
+    fn nonce(&self) -> &str {
+        self.nonce.as_str()
+    }
+
+    fn associated_data(&self) -> &str {
+        self.associated_data.as_str()
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Certificate {
+    ///【证书序列号】
     pub serial_no: String,
-    pub effective_time: String,
-    pub expire_time: String,
+    ///【证书启用时间】 遵循rfc3339标准格式
+    pub effective_time: DateTime<FixedOffset>,
+    ///【证书弃用时间】 遵循rfc3339标准格式
+    pub expire_time: DateTime<FixedOffset>,
+    ///【证书信息】 需要使用APIv3密钥解密
     pub encrypt_certificate: EncryptCertificate,
 }
 
+impl Certificate {
+    /// 证书在指定时间是否处于有效期内
+    pub fn is_valid_at<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> bool {
+        self.effective_time <= *time && *time < self.expire_time
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CertificateResponse {
     pub data: Option<Vec<Certificate>>,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
 }
 
-impl ResponseTrait for CertificateResponse {}
+impl CertificateResponse {
+    /// 选出指定时间有效的证书，多张同时有效时（证书轮换期间）取启用时间最晚的一张
+    pub fn valid_at<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> Option<&Certificate> {
+        self.data
+            .iter()
+            .flatten()
+            .filter(|cert| cert.is_valid_at(time))
+            .max_by_key(|cert| cert.effective_time)
+    }
+
+    /// 按clock的当前时间选出有效的证书，无需APIv3密钥即可查看序列号与有效期
+    /// 使用`WechatPay`时可直接调用`current_certificate`，与签名使用同一个注入的时钟
+    pub fn current(&self, clock: &dyn Clock) -> Option<&Certificate> {
+        self.valid_at(&clock.now())
+    }
+}
+
+impl_request_id!(CertificateResponse);
 
-#[derive(Deserialize, Debug)]
-#[serde(untagged, bound = "T: ResponseTrait + DeserializeOwned")]
+/// 接口应答，按应答体内容区分成功与失败，与HTTP状态码无关
+/// 应答体为`{"code":"...","message":"..."}`格式的错误时为Err，即使HTTP状态码为200；
+/// 否则按T解析为Ok，应答体既不是错误格式也无法解析为T时返回解析错误，而不是空的ErrorResponse
+#[derive(Debug)]
 pub enum WeChatResponse<T>
 where
     T: ResponseTrait + DeserializeOwned,
@@ -103,16 +480,69 @@ where
     Err(ErrorResponse),
 }
 
-impl<T> ResponseTrait for WeChatResponse<T> where T: ResponseTrait + DeserializeOwned {}
+impl<'de, T> Deserialize<'de> for WeChatResponse<T>
+where
+    T: ResponseTrait + DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        if ErrorResponse::is_error_body(&value) {
+            return ErrorResponse::deserialize(value)
+                .map(WeChatResponse::Err)
+                .map_err(serde::de::Error::custom);
+        }
+        T::deserialize(value)
+            .map(WeChatResponse::Ok)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl<T> ResponseTrait for WeChatResponse<T>
+where
+    T: ResponseTrait + DeserializeOwned,
+{
+    const KEEP_RAW: bool = T::KEEP_RAW;
+
+    fn set_raw(&mut self, raw: Value) {
+        if let WeChatResponse::Ok(response) = self {
+            response.set_raw(raw);
+        }
+    }
+
+    fn set_request_id(&mut self, request_id: String) {
+        match self {
+            WeChatResponse::Ok(response) => response.set_request_id(request_id),
+            WeChatResponse::Err(error) => error.request_id = Some(request_id),
+        }
+    }
+
+    fn set_status(&mut self, status: u16) {
+        if let WeChatResponse::Err(error) = self {
+            error.status = Some(status);
+        }
+    }
+
+    fn request_id(&self) -> Option<&str> {
+        match self {
+            WeChatResponse::Ok(response) => response.request_id(),
+            WeChatResponse::Err(error) => error.request_id.as_deref(),
+        }
+    }
+}
 
 impl<T> WeChatResponse<T>
 where
     T: ResponseTrait + DeserializeOwned,
 {
+    /// 应答体是否为成功结果，HTTP 200但应答体为错误码时返回false
     pub fn is_success(&self) -> bool {
         matches!(self, WeChatResponse::Ok(_))
     }
 
+    /// 成功时的应答，应答体为错误码时返回None
     pub fn ok(&self) -> Option<&T> {
         if let WeChatResponse::Ok(response) = self {
             Some(response)
@@ -121,6 +551,7 @@ where
         }
     }
 
+    /// 应答体中的错误，无论HTTP状态码是否为200，成功时返回None
     pub fn err(&self) -> Option<&ErrorResponse> {
         if let WeChatResponse::Err(error_response) = self {
             Some(error_response)
@@ -128,14 +559,158 @@ where
             None
         }
     }
+
+    /// 应答头中的Request-ID，成功时取自应答结构(未保留时为None)，失败时取自ErrorResponse
+    pub fn request_id(&self) -> Option<&str> {
+        ResponseTrait::request_id(self)
+    }
+}
+
+impl<T> ResponseStatus for WeChatResponse<T>
+where
+    T: ResponseTrait + DeserializeOwned,
+{
+    fn error_code(&self) -> Option<&str> {
+        match self {
+            WeChatResponse::Ok(_) => None,
+            WeChatResponse::Err(error) => Some(error.code.as_deref().unwrap_or_default()),
+        }
+    }
+
+    fn error_message(&self) -> Option<&str> {
+        WeChatResponse::err(self).and_then(|error| error.message.as_deref())
+    }
+
+    fn error_detail(&self) -> Option<&ErrorDetail> {
+        WeChatResponse::err(self).and_then(|error| error.detail.as_ref())
+    }
+
+    fn err(&self) -> Option<ErrorResponse> {
+        WeChatResponse::err(self).cloned()
+    }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct ErrorResponse {
     /// 【错误码】 错误码
     pub code: Option<String>,
     /// 【错误信息】 错误信息
     pub message: Option<String>,
+    /// 【错误详情】 参数校验失败(如PARAM_ERROR)时指出出错的字段和传入的值
+    #[serde(default)]
+    pub detail: Option<ErrorDetail>,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+    /// 应答状态码，HTTP 200但应答体为错误码时为200
+    #[serde(skip)]
+    pub status: Option<u16>,
+}
+
+/// 参数校验失败时应答中的detail，如`{"field":"/amount/total","value":0,"issue":"...","location":"body"}`
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ErrorDetail {
+    /// 【出错字段】 JSON Pointer格式，如/amount/total
+    pub field: Option<String>,
+    /// 【出错的值】 请求中传入的原始值
+    pub value: Option<Value>,
+    /// 【错误说明】 具体的校验规则，如必须大于等于1
+    pub issue: Option<String>,
+    /// 【出错位置】 body、query或header
+    pub location: Option<String>,
+}
+
+impl ErrorDetail {
+    /// 点分隔的字段路径，如/amount/total转为amount.total
+    pub fn field_path(&self) -> Option<String> {
+        self.field
+            .as_deref()
+            .map(|field| field.trim_start_matches('/').replace('/', "."))
+            .filter(|field| !field.is_empty())
+    }
+}
+
+impl std::fmt::Display for ErrorDetail {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "field {}", self.field_path().as_deref().unwrap_or("-"))?;
+        if let Some(issue) = &self.issue {
+            write!(f, " {}", issue)?;
+        }
+        if let Some(value) = &self.value {
+            write!(f, " (value: {})", value)?;
+        }
+        Ok(())
+    }
+}
+
+/// 与`PayError::Api`的展示格式一致
+impl std::fmt::Display for ErrorResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&crate::error::api_error_message(
+            self.code.as_deref().unwrap_or_default(),
+            self.message.as_deref().unwrap_or_default(),
+            self.detail.as_ref(),
+            self.request_id.as_deref(),
+        ))
+    }
+}
+
+impl From<ErrorResponse> for PayError {
+    fn from(error: ErrorResponse) -> Self {
+        PayError::Api {
+            status: error.status,
+            code: error.code.unwrap_or_default(),
+            message: error.message.unwrap_or_default(),
+            detail: error.detail.map(Box::new),
+            request_id: error.request_id,
+        }
+    }
+}
+
+impl ErrorResponse {
+    /// 应答体是否为错误格式：code为非空字符串且带有字符串message
+    pub fn is_error_body(body: &Value) -> bool {
+        body.get("code")
+            .and_then(Value::as_str)
+            .is_some_and(|code| !code.is_empty())
+            && body.get("message").is_some_and(Value::is_string)
+    }
+
+    /// 解析错误码，code为空时返回None
+    pub fn error_code(&self) -> Option<WechatErrorCode> {
+        self.code.as_deref().map(WechatErrorCode::from)
+    }
+
+    /// 是否可以使用相同参数重试，如SYSTEM_ERROR、FREQUENCY_LIMITED
+    pub fn is_retryable(&self) -> bool {
+        self.error_code()
+            .is_some_and(|error_code| error_code.is_retryable())
+    }
+}
+
+/// 幂等关闭订单的结果，订单已处于终态时不作为错误返回
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseOrderOutcome {
+    /// 本次调用关闭了订单
+    Closed,
+    /// 订单此前已关闭
+    AlreadyClosed,
+    /// 订单不存在，如用户未拉起支付
+    NotExist,
+    /// 订单已支付，不能关闭，需按支付成功处理
+    Paid,
+}
+
+impl CloseOrderOutcome {
+    /// 按关闭订单返回的错误码判断订单终态，其他错误码返回None
+    pub fn from_error_code(error_code: &WechatErrorCode) -> Option<Self> {
+        match error_code {
+            WechatErrorCode::OrderClosed => Some(CloseOrderOutcome::AlreadyClosed),
+            WechatErrorCode::OrderNotExist => Some(CloseOrderOutcome::NotExist),
+            WechatErrorCode::OrderPaid => Some(CloseOrderOutcome::Paid),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -154,7 +729,7 @@ pub struct RefundsResponse {
     /// BALANCE: 退回到余额
     /// OTHER_BALANCE: 原账户异常退到其他余额账户
     /// OTHER_BANKCARD: 原银行卡异常退到其他银行卡(发起异常退款成功后返回)
-    pub channel: String,
+    pub channel: RefundChannel,
     /// 【退款入账账户】 取当前退款单的退款入账方，有以下几种情况：
     /// 1）退回银行卡：{银行名称}{卡类型}{卡尾号}
     /// 2）退回支付用户零钱:支付用户零钱
@@ -182,7 +757,7 @@ pub struct RefundsResponse {
     /// PROCESSING: 退款处理中
     /// ABNORMAL: 退款异常，退款到银行发现用户的卡作废或者冻结了，导致原路退款银行卡失败，可前往商户平台-交易中心，手动处理此笔退款，可参考： 退款异常的处理，或者通过发起异常退款接口进行处理。
     /// 注：状态流转说明请参考状态流转图
-    pub status: String,
+    pub status: RefundStatus,
     /// 【资金账户】 退款所使用资金对应的资金账户类型
     /// UNSETTLED: 未结算资金
     /// AVAILABLE: 可用余额
@@ -195,9 +770,33 @@ pub struct RefundsResponse {
     pub amount: RefundsAmountResponse,
     /// 【优惠退款详情】 订单各个代金券的退款详情，订单使用了代金券且代金券发生退款时返回。
     pub promotion_detail: Option<Vec<RefundsPromotionDetailResponse>>,
+    #[serde(skip)]
+    raw: Value,
+    #[serde(skip)]
+    request_id: Option<String>,
 }
 
-impl ResponseTrait for RefundsResponse {}
+#[cfg(feature = "chrono")]
+impl RefundsResponse {
+    /// 解析后的退款成功时间，退款未成功或格式不符合预期时返回None
+    pub fn success_at(&self) -> Option<DateTime<FixedOffset>> {
+        crate::time::parse_option(self.success_time.as_deref())
+    }
+
+    /// 解析后的退款创建时间，格式不符合预期时返回None
+    pub fn created_at(&self) -> Option<DateTime<FixedOffset>> {
+        crate::time::parse_option(Some(self.create_time.as_str()))
+    }
+}
+
+impl RefundsResponse {
+    /// 原始JSON，可读取尚未建模的新字段
+    pub fn raw(&self) -> &Value {
+        &self.raw
+    }
+}
+
+impl_request_id!(RefundsResponse, raw);
 
 #[derive(Debug, Deserialize)]
 pub struct RefundsAmountResponse {
@@ -271,3 +870,1069 @@ pub struct RefundsGoodsDetailResponse {
     /// 【商品退货数量】 申请退款的商品退货数量。
     pub refund_quantity: i32,
 }
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ComplaintOrderInfo {
+    ///【微信订单号】
+    pub transaction_id: String,
+    ///【商户订单号】
+    pub out_trade_no: String,
+    ///【订单金额】 单位为分
+    pub amount: i32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Complaint {
+    ///【投诉单号】
+    pub complaint_id: String,
+    ///【投诉时间】 遵循rfc3339标准格式
+    pub complaint_time: String,
+    ///【投诉详情】
+    pub complaint_detail: String,
+    ///【投诉单状态】 PENDING: 待处理 PROCESSING: 处理中 PROCESSED: 已处理完成
+    pub complaint_state: String,
+    ///【投诉人联系方式】 使用平台证书公钥加密
+    pub payer_phone: Option<String>,
+    ///【投诉单关联订单信息】
+    #[serde(default)]
+    pub complaint_order_info: Vec<ComplaintOrderInfo>,
+    ///【投诉单是否已全额退款】
+    pub complaint_full_refunded: Option<bool>,
+    ///【是否有待回复的用户留言】
+    pub incoming_user_response: Option<bool>,
+    ///【用户投诉次数】
+    pub user_complaint_times: Option<i32>,
+    ///【问题描述】
+    pub problem_description: Option<String>,
+}
+
+/// 查询投诉单列表
+#[derive(Debug, Deserialize)]
+pub struct ComplaintListResponse {
+    #[serde(default)]
+    pub data: Vec<Complaint>,
+    pub limit: u32,
+    pub offset: u32,
+    pub total_count: Option<u32>,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl_request_id!(ComplaintListResponse);
+
+impl Paginated for ComplaintListResponse {
+    type Item = Complaint;
+
+    fn items(self) -> Vec<Complaint> {
+        self.data
+    }
+
+    fn total_count(&self) -> Option<u32> {
+        self.total_count
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NormalCouponInformation {
+    ///【面额】 单位为分
+    pub coupon_amount: i32,
+    ///【门槛】 消费门槛，单位为分
+    pub transaction_minimum: i32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserCoupon {
+    ///【创建批次的商户号】
+    pub stock_creator_mchid: String,
+    ///【批次号】
+    pub stock_id: String,
+    ///【代金券id】
+    pub coupon_id: String,
+    ///【代金券名称】
+    pub coupon_name: String,
+    ///【代金券状态】 SENDED: 可用 USED: 已实扣 EXPIRED: 已过期
+    pub status: String,
+    ///【使用说明】
+    pub description: Option<String>,
+    ///【领券时间】 遵循rfc3339标准格式
+    pub create_time: String,
+    ///【券类型】 NORMAL: 满减券 CUT_TO: 减至券
+    pub coupon_type: Option<String>,
+    ///【是否无资金流】
+    pub no_cash: bool,
+    ///【可用开始时间】
+    pub available_begin_time: String,
+    ///【可用结束时间】
+    pub available_end_time: String,
+    ///【是否单品优惠】
+    pub singleitem: bool,
+    ///【满减券信息】
+    pub normal_coupon_information: Option<NormalCouponInformation>,
+    ///【核销信息】 券已核销时返回
+    pub consume_information: Option<CouponConsumeInformation>,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl_request_id!(UserCoupon);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CouponConsumeInformation {
+    ///【核销时间】 遵循rfc3339标准格式
+    pub consume_time: String,
+    ///【核销商户号】
+    pub consume_mchid: String,
+    ///【支付单号】
+    pub transaction_id: String,
+}
+
+/// 营销图片上传
+#[derive(Debug, Deserialize)]
+pub struct MarketingImageUploadResponse {
+    ///【媒体文件URL地址】 用于创建代金券批次时的券图片等字段
+    pub media_url: String,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl_request_id!(MarketingImageUploadResponse);
+
+/// 创建代金券批次
+#[derive(Debug, Deserialize)]
+pub struct CouponStockResponse {
+    ///【批次号】
+    pub stock_id: String,
+    ///【创建时间】 遵循rfc3339标准格式
+    pub create_time: String,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl_request_id!(CouponStockResponse);
+
+/// 根据商户号查用户的券
+#[derive(Debug, Deserialize)]
+pub struct UserCouponListResponse {
+    #[serde(default)]
+    pub data: Vec<UserCoupon>,
+    pub limit: u32,
+    pub offset: u32,
+    pub total_count: Option<u32>,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl_request_id!(UserCouponListResponse);
+
+impl Paginated for UserCouponListResponse {
+    type Item = UserCoupon;
+
+    fn items(self) -> Vec<UserCoupon> {
+        self.data
+    }
+
+    fn total_count(&self) -> Option<u32> {
+        self.total_count
+    }
+}
+
+/// 无应答体的接口，如开具电子发票返回202
+impl ResponseTrait for () {}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FapiaoDetail {
+    ///【发票代码】
+    pub fapiao_code: String,
+    ///【发票号码】
+    pub fapiao_number: String,
+    ///【校验码】
+    pub check_code: Option<String>,
+    ///【密码】
+    pub password: Option<String>,
+    ///【开票时间】 遵循rfc3339标准格式
+    pub fapiao_time: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Fapiao {
+    ///【商户发票单号】
+    pub fapiao_id: String,
+    ///【发票状态】 ISSUE_ACCEPTED: 开票已受理 ISSUED: 已开具 REVERSE_ACCEPTED: 冲红已受理 REVERSED: 已冲红
+    pub status: String,
+    ///【蓝字发票信息】
+    pub blue_fapiao: Option<FapiaoDetail>,
+    ///【红字发票信息】
+    pub red_fapiao: Option<FapiaoDetail>,
+    ///【总价税合计】 单位为分
+    pub total_amount: Option<i64>,
+    ///【总税额】 单位为分
+    pub tax_amount: Option<i64>,
+    ///【总金额】 单位为分
+    pub amount: Option<i64>,
+    ///【发票行信息】
+    #[serde(default)]
+    pub items: Vec<FapiaoItem>,
+    ///【备注】
+    pub remark: Option<String>,
+}
+
+/// 查询电子发票
+#[derive(Debug, Deserialize)]
+pub struct FapiaoApplicationResponse {
+    ///【发票数量】
+    pub total_count: u32,
+    ///【发票信息】
+    #[serde(default)]
+    pub fapiao_information: Vec<Fapiao>,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl_request_id!(FapiaoApplicationResponse);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProfitSharingReceiver {
+    ///【分账金额】 单位为分
+    pub amount: i64,
+    ///【分账描述】
+    pub description: String,
+    ///【分账接收方类型】 MERCHANT_ID: 商户号 PERSONAL_OPENID: 个人openid
+    #[serde(rename = "type")]
+    pub receiver_type: String,
+    ///【分账接收方账号】
+    pub account: String,
+    ///【分账结果】 PENDING: 待分账 SUCCESS: 分账成功 CLOSED: 已关闭
+    pub result: String,
+    ///【分账失败原因】 分账结果为CLOSED时返回
+    pub fail_reason: Option<String>,
+    ///【分账明细单号】
+    pub detail_id: String,
+    ///【分账创建时间】 遵循rfc3339标准格式
+    pub create_time: String,
+    ///【分账完成时间】 遵循rfc3339标准格式
+    pub finish_time: String,
+}
+
+impl ProfitSharingReceiver {
+    /// 是否分账成功
+    pub fn is_success(&self) -> bool {
+        self.result == "SUCCESS"
+    }
+}
+
+/// 请求分账、查询分账结果、解冻剩余资金
+#[derive(Debug, Deserialize)]
+pub struct ProfitSharingOrderResponse {
+    ///【微信订单号】
+    pub transaction_id: String,
+    ///【商户分账单号】
+    pub out_order_no: String,
+    ///【微信分账单号】
+    pub order_id: String,
+    ///【分账单状态】 PROCESSING: 处理中 FINISHED: 分账完成
+    pub state: String,
+    ///【分账接收方列表】
+    #[serde(default)]
+    pub receivers: Vec<ProfitSharingReceiver>,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl ProfitSharingOrderResponse {
+    /// 按接收方账号(商户号或openid)查找分账结果，账号不在本次分账中时返回None
+    pub fn receiver(&self, account: impl AsRef<str>) -> Option<&ProfitSharingReceiver> {
+        self.receivers
+            .iter()
+            .find(|receiver| receiver.account == account.as_ref())
+    }
+}
+
+impl_request_id!(ProfitSharingOrderResponse);
+
+/// 请求分账回退、查询分账回退结果
+#[derive(Debug, Deserialize)]
+pub struct ProfitSharingReturnResponse {
+    ///【微信分账单号】
+    pub order_id: String,
+    ///【商户分账单号】
+    pub out_order_no: String,
+    ///【商户回退单号】
+    pub out_return_no: String,
+    ///【微信回退单号】
+    pub return_id: String,
+    ///【回退商户号】
+    pub return_mchid: String,
+    ///【回退金额】 单位为分
+    pub amount: i64,
+    ///【回退描述】
+    pub description: String,
+    ///【回退结果】 PROCESSING: 处理中 SUCCESS: 已成功 FAILED: 已失败
+    pub result: String,
+    ///【失败原因】 回退结果为FAILED时返回
+    pub fail_reason: Option<String>,
+    ///【创建时间】 遵循rfc3339标准格式
+    pub create_time: String,
+    ///【完成时间】 遵循rfc3339标准格式
+    pub finish_time: String,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl_request_id!(ProfitSharingReturnResponse);
+
+/// 查询剩余待分金额
+#[derive(Debug, Deserialize)]
+pub struct ProfitSharingAmountResponse {
+    ///【微信订单号】
+    pub transaction_id: String,
+    ///【订单剩余待分金额】 单位为分
+    pub unsplit_amount: i64,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl_request_id!(ProfitSharingAmountResponse);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CombineSubOrderAmount {
+    ///【标价金额】 子单金额，单位为分
+    pub total_amount: i64,
+    ///【标价币种】
+    pub currency: Option<String>,
+    ///【现金支付金额】 订单未支付时不返回
+    pub payer_amount: Option<i64>,
+    ///【现金支付币种】
+    pub payer_currency: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CombineSubOrder {
+    ///【子单商户号】
+    pub mchid: String,
+    ///【二级商户号】
+    pub sub_mchid: Option<String>,
+    ///【交易类型】 NATIVE、JSAPI、APP、MWEB
+    pub trade_type: Option<String>,
+    ///【交易状态】 SUCCESS、REFUND、NOTPAY、CLOSED、PAYERROR
+    pub trade_state: String,
+    ///【付款银行】
+    pub bank_type: Option<String>,
+    ///【附加数据】
+    pub attach: Option<String>,
+    ///【支付完成时间】 遵循rfc3339标准格式
+    pub success_time: Option<String>,
+    ///【微信订单号】 订单未支付时不返回
+    pub transaction_id: Option<String>,
+    ///【子单商户订单号】
+    pub out_trade_no: String,
+    ///【订单金额】
+    pub amount: CombineSubOrderAmount,
+}
+
+#[cfg(feature = "chrono")]
+impl CombineSubOrder {
+    /// 解析后的子单支付完成时间，未支付或格式不符合预期时返回None
+    pub fn success_at(&self) -> Option<DateTime<FixedOffset>> {
+        crate::time::parse_option(self.success_time.as_deref())
+    }
+}
+
+/// 合单查询订单
+#[derive(Debug, Deserialize)]
+pub struct CombineOrderQueryResponse {
+    ///【合单商户appid】
+    pub combine_appid: String,
+    ///【合单商户号】
+    pub combine_mchid: String,
+    ///【合单商户订单号】
+    pub combine_out_trade_no: String,
+    ///【子单信息】 每个子单的支付状态
+    #[serde(default)]
+    pub sub_orders: Vec<CombineSubOrder>,
+    ///【支付者】
+    pub combine_payer_info: Option<Payer>,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl_request_id!(CombineOrderQueryResponse);
+
+/// 发起转账
+#[derive(Debug, Deserialize)]
+pub struct TransferBillsResponse {
+    ///【商户单号】
+    pub out_bill_no: String,
+    ///【微信转账单号】
+    pub transfer_bill_no: String,
+    ///【单据创建时间】 遵循rfc3339标准格式
+    pub create_time: String,
+    ///【单据状态】 ACCEPTED、PROCESSING、WAIT_USER_CONFIRM、TRANSFERING、SUCCESS、FAIL、CANCELING、CANCELLED
+    pub state: TransferBillState,
+    ///【失败原因】 单据状态为FAIL时返回
+    pub fail_reason: Option<String>,
+    ///【跳转领取页面的package信息】 单据状态为WAIT_USER_CONFIRM时返回，用于小程序调起用户确认收款
+    pub package_info: Option<String>,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+#[cfg(feature = "chrono")]
+impl TransferBillsResponse {
+    /// 解析后的单据创建时间，格式不符合预期时返回None
+    pub fn created_at(&self) -> Option<DateTime<FixedOffset>> {
+        crate::time::parse_option(Some(self.create_time.as_str()))
+    }
+}
+
+impl TransferBillsResponse {
+    /// 生成`wx.requestMerchantTransfer`的JSON(mchId、appId、package)，字段名大小写与前端接口一致
+    /// 单据状态不是WAIT_USER_CONFIRM或没有package_info时返回错误
+    pub fn to_merchant_transfer_json<S: AsRef<str>>(
+        &self,
+        mch_id: S,
+        app_id: S,
+    ) -> Result<Value, PayError> {
+        let data = self.merchant_transfer_data(mch_id, app_id)?;
+        Ok(serde_json::to_value(data)?)
+    }
+
+    /// 生成小程序`wx.requestMerchantTransfer`调起用户确认收款的参数
+    /// 仅单据状态为WAIT_USER_CONFIRM且返回了package_info时可用
+    pub fn merchant_transfer_data<S: AsRef<str>>(
+        &self,
+        mch_id: S,
+        app_id: S,
+    ) -> Result<MerchantTransferData, PayError> {
+        if self.state != TransferBillState::WaitUserConfirm {
+            return Err(PayError::InvalidParams(format!(
+                "transfer bill {} is {}, not WAIT_USER_CONFIRM",
+                self.transfer_bill_no, self.state
+            )));
+        }
+        let package = self.package_info.clone().ok_or_else(|| {
+            PayError::InvalidParams(format!(
+                "transfer bill {} has no package_info",
+                self.transfer_bill_no
+            ))
+        })?;
+        Ok(MerchantTransferData {
+            mch_id: mch_id.as_ref().to_string(),
+            app_id: app_id.as_ref().to_string(),
+            package,
+        })
+    }
+}
+
+impl_request_id!(TransferBillsResponse);
+
+/// 转账电子回单，申请后回单异步生成，状态为FINISHED时返回download_url
+#[derive(Debug, Deserialize)]
+pub struct TransferReceiptResponse {
+    ///【电子回单状态】 GENERATING、FINISHED、FAILED
+    pub state: TransferReceiptState,
+    ///【电子回单申请单创建时间】 遵循rfc3339标准格式
+    pub create_time: Option<String>,
+    ///【电子回单申请单更新时间】 遵循rfc3339标准格式
+    pub update_time: Option<String>,
+    ///【电子回单文件的hash方法】 如SHA256
+    pub hash_type: Option<String>,
+    ///【电子回单文件的hash值】 下载后用于校验文件完整性
+    pub hash_value: Option<String>,
+    ///【电子回单文件的下载地址】 有效期10分钟，下载请求同样需要签名
+    pub download_url: Option<String>,
+    ///【失败原因】 状态为FAILED时返回
+    pub fail_reason: Option<String>,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl_request_id!(TransferReceiptResponse);
+
+/// 调起用户确认收款的参数，字段名与`wx.requestMerchantTransfer`的参数一致，可直接序列化后返回给前端
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MerchantTransferData {
+    #[serde(rename = "mchId")]
+    pub mch_id: String,
+    #[serde(rename = "appId")]
+    pub app_id: String,
+    pub package: String,
+}
+
+/// 支付分订单收款明细
+#[derive(Debug, Clone, Deserialize)]
+pub struct PayScoreCollectionDetail {
+    ///【收款序号】
+    pub seq: Option<u32>,
+    ///【单笔收款金额】 单位为分
+    pub amount: Option<i64>,
+    ///【收款成功渠道】 NEWTON: 微信支付分 MCH: 商户渠道
+    pub paid_type: Option<String>,
+    ///【收款成功时间】
+    pub paid_time: Option<String>,
+    ///【微信支付交易单号】
+    pub transaction_id: Option<String>,
+}
+
+/// 支付分订单收款信息
+#[derive(Debug, Clone, Deserialize)]
+pub struct PayScoreCollection {
+    ///【收款状态】 USER_PAYING: 待支付 USER_PAID: 已支付
+    pub state: String,
+    ///【总收款金额】 单位为分
+    pub total_amount: Option<i64>,
+    ///【待收金额】 单位为分
+    pub paying_amount: Option<i64>,
+    ///【已收金额】 单位为分
+    pub paid_amount: Option<i64>,
+    ///【收款明细列表】
+    #[serde(default)]
+    pub details: Vec<PayScoreCollectionDetail>,
+}
+
+/// 创建、查询、完结支付分订单
+#[derive(Debug, Deserialize)]
+pub struct PayScoreOrderResponse {
+    ///【应用ID】
+    pub appid: String,
+    ///【商户号】
+    pub mchid: String,
+    ///【商户服务订单号】
+    pub out_order_no: String,
+    ///【服务ID】
+    pub service_id: String,
+    ///【服务信息】
+    pub service_introduction: Option<String>,
+    ///【服务订单状态】 CREATED、DOING、DONE、REVOKED、EXPIRED
+    pub state: PayScoreState,
+    ///【订单状态说明】 USER_CONFIRM: 用户确认 MCH_COMPLETE: 商户完结 USER_PAID: 用户已支付等
+    pub state_description: Option<String>,
+    ///【总金额】 单位为分，完结订单后返回
+    pub total_amount: Option<i64>,
+    ///【后付费项目】
+    #[serde(default)]
+    pub post_payments: Vec<PayScorePostPayment>,
+    ///【后付费商户优惠】
+    #[serde(default)]
+    pub post_discounts: Vec<PayScorePostDiscount>,
+    ///【订单风险金】
+    pub risk_fund: Option<PayScoreRiskFund>,
+    ///【服务时间段】
+    pub time_range: Option<PayScoreTimeRange>,
+    ///【服务位置】
+    pub location: Option<PayScoreLocation>,
+    ///【商户数据包】
+    pub attach: Option<String>,
+    ///【商户回调地址】
+    pub notify_url: Option<String>,
+    ///【微信支付服务订单号】
+    pub order_id: Option<String>,
+    ///【跳转微信侧小程序订单数据】 需要用户确认时返回，用于调起确认订单页面
+    pub package: Option<String>,
+    ///【是否需要收款】
+    pub need_collection: Option<bool>,
+    ///【收款信息】
+    pub collection: Option<PayScoreCollection>,
+    ///【用户标识】
+    pub openid: Option<String>,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl_request_id!(PayScoreOrderResponse);
+
+/// 支付分授权小程序的AppID
+pub const PAYSCORE_MINI_PROGRAM_APPID: &str = "wxd8f3793ea3b935b8";
+/// 支付分授权小程序的页面路径
+pub const PAYSCORE_PERMISSION_PATH: &str = "pages/use/enable";
+
+/// 商户预授权
+#[derive(Debug, Deserialize)]
+pub struct PayScorePermissionApplyResponse {
+    ///【预授权token】 用于跳转支付分小程序授权
+    pub apply_permissions_token: String,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl PayScorePermissionApplyResponse {
+    /// 生成小程序`wx.navigateToMiniProgram`跳转支付分授权页面的参数
+    pub fn mini_program_launch(&self) -> PayScorePermissionLaunch {
+        PayScorePermissionLaunch {
+            app_id: PAYSCORE_MINI_PROGRAM_APPID.to_string(),
+            path: PAYSCORE_PERMISSION_PATH.to_string(),
+            extra_data: PayScorePermissionExtraData {
+                apply_permissions_token: self.apply_permissions_token.clone(),
+            },
+        }
+    }
+}
+
+impl_request_id!(PayScorePermissionApplyResponse);
+
+/// 跳转支付分授权小程序的参数，字段名与`wx.navigateToMiniProgram`的参数一致，可直接序列化后返回给前端
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PayScorePermissionLaunch {
+    #[serde(rename = "appId")]
+    pub app_id: String,
+    pub path: String,
+    #[serde(rename = "extraData")]
+    pub extra_data: PayScorePermissionExtraData,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PayScorePermissionExtraData {
+    pub apply_permissions_token: String,
+}
+
+/// 查询用户授权记录
+#[derive(Debug, Deserialize)]
+pub struct PayScorePermissionResponse {
+    ///【服务ID】
+    pub service_id: String,
+    ///【应用ID】
+    pub appid: String,
+    ///【商户号】
+    pub mchid: String,
+    ///【授权协议号】
+    pub authorization_code: Option<String>,
+    ///【授权状态】 AVAILABLE、UNAVAILABLE、UNBINDUSER
+    pub authorization_state: PayScoreAuthorizationState,
+    ///【授权通知地址】
+    pub notify_url: Option<String>,
+    ///【最近一次解除授权时间】
+    pub cancel_authorization_time: Option<String>,
+    ///【最近一次授权成功时间】
+    pub authorization_success_time: Option<String>,
+    ///【用户标识】
+    pub openid: Option<String>,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl_request_id!(PayScorePermissionResponse);
+
+/// 申请账单(交易账单、分账账单等)的应答，下载地址有效期为30秒，下载请求同样需要签名
+#[derive(Debug, Deserialize)]
+pub struct BillResponse {
+    ///【哈希类型】 SHA1
+    pub hash_type: String,
+    ///【哈希值】 下载后用于校验账单文件完整性
+    pub hash_value: String,
+    ///【账单下载地址】
+    pub download_url: String,
+    /// 应答头中的Request-ID，向微信支付反馈问题时提供
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+impl_request_id!(BillResponse);
+
+/// 申请交易账单的应答，与其他账单的应答相同
+pub type TradeBillResponse = BillResponse;
+
+/// 交易账单中的一行明细，金额单位为分，字段取自账单表头，账单类型不包含的列为空
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TradeBillRecord {
+    ///【交易时间】 格式为yyyy-MM-dd HH:mm:ss
+    pub trade_time: String,
+    ///【公众账号ID】
+    pub appid: String,
+    ///【商户号】
+    pub mchid: String,
+    ///【微信订单号】
+    pub transaction_id: String,
+    ///【商户订单号】
+    pub out_trade_no: String,
+    ///【用户标识】
+    pub openid: String,
+    ///【交易类型】 JSAPI、NATIVE、APP等
+    pub trade_type: String,
+    ///【交易状态】 SUCCESS: 支付成功 REFUND: 退款
+    pub trade_state: String,
+    ///【应结订单金额】 单位为分
+    pub settlement_total: i64,
+    ///【微信退款单号】
+    pub refund_id: String,
+    ///【商户退款单号】
+    pub out_refund_no: String,
+    ///【退款金额】 单位为分
+    pub refund: i64,
+    ///【手续费】 单位为分
+    pub fee: i64,
+    ///【订单金额】 单位为分
+    pub total: i64,
+}
+
+/// 对账结果，matched和extra保留账单原始明细，同一订单的支付和退款明细都会列出
+#[derive(Debug, Clone, Default)]
+pub struct Reconciliation {
+    ///【账单中存在的我方订单明细】
+    pub matched: Vec<TradeBillRecord>,
+    ///【账单中不存在的我方订单号】 可能未支付成功或不在当日账单中，需逐笔查询订单确认
+    pub unmatched: Vec<String>,
+    ///【账单中存在但不在我方订单号中的明细】
+    pub extra: Vec<TradeBillRecord>,
+}
+
+impl Reconciliation {
+    /// 没有缺失和多余的订单时对账通过
+    pub fn is_balanced(&self) -> bool {
+        self.unmatched.is_empty() && self.extra.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CertificateResponse, ErrorDetail, ErrorResponse, H5Response, JsapiResponse, NativeResponse,
+        OrderQueryResponse, ProfitSharingOrderResponse, RefundsResponse, ResponseStatus,
+        ResponseTrait, TransferBillsResponse, WeChatResponse,
+    };
+    use crate::error::PayError;
+    use crate::error::WechatErrorCode;
+    use crate::mock_server::PRIVATE_KEY;
+    use crate::model::TransferBillState;
+    use crate::pay::{WechatPay, WechatPayTrait};
+    use chrono::DateTime;
+    use serde_json::Value;
+
+    #[test]
+    fn test_order_query_promotion_detail() {
+        let response: OrderQueryResponse = serde_json::from_str(
+            r#"{"appid":"wxd678efh567hg6787","mchid":"1230000109","out_trade_no":"1217752501201407033233368018","transaction_id":"1217752501201407033233368018","trade_type":"JSAPI","trade_state":"SUCCESS","trade_state_desc":"支付成功","amount":{"total":100,"payer_total":90,"currency":"CNY","payer_currency":"CNY"},"promotion_detail":[{"coupon_id":"109519","name":"单品惠-6","scope":"SINGLE","type":"CASH","amount":10,"stock_id":"931386","wechatpay_contribute":0,"merchant_contribute":10,"other_contribute":0,"currency":"CNY","goods_detail":[{"goods_id":"M1006","quantity":1,"unit_price":100,"discount_amount":10,"goods_remark":"商品备注信息"}]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(response.promotions().len(), 1);
+        let promotions = response.promotion_detail.unwrap();
+        assert_eq!(promotions.len(), 1);
+        assert_eq!(promotions[0].coupon_id, "109519");
+        assert_eq!(promotions[0].amount, 10);
+        assert_eq!(promotions[0].merchant_contribute, Some(10));
+        assert_eq!(promotions[0].wechatpay_contribute, Some(0));
+        let goods = promotions[0].goods_detail.as_deref().unwrap();
+        assert_eq!(goods[0].goods_id, "M1006");
+        assert_eq!(goods[0].discount_amount, 10);
+        assert_eq!(goods[0].goods_remark.as_deref(), Some("商品备注信息"));
+        let response: OrderQueryResponse = serde_json::from_str(
+            r#"{"appid":"wxd678efh567hg6787","mchid":"1230000109","out_trade_no":"1217752501201407033233368018","trade_state":"NOTPAY","trade_state_desc":"订单未支付"}"#,
+        )
+        .unwrap();
+        assert!(response.promotion_detail.is_none());
+        assert!(response.promotions().is_empty());
+
+        // 支付回调与订单查询使用相同的结构
+        let data: crate::model::WechatPayDecodeData = serde_json::from_str(
+            r#"{"mchid":"1230000109","appid":"wxd678efh567hg6787","out_trade_no":"1243243","transaction_id":"4200001","trade_type":"JSAPI","trade_state":"SUCCESS","trade_state_desc":"支付成功","bank_type":"OTHERS","attach":"","success_time":"2024-05-01T10:00:00+08:00","amount":{"total":100},"promotion_detail":[{"coupon_id":"109519","amount":20,"wechatpay_contribute":5,"merchant_contribute":15,"other_contribute":0,"goods_detail":[{"goods_id":"M1006","quantity":2,"unit_price":50,"discount_amount":20}]}]}"#,
+        )
+        .unwrap();
+        let promotion: &super::Promotion = &data.promotions()[0];
+        assert_eq!(promotion.merchant_contribute, Some(15));
+        assert_eq!(promotion.wechatpay_contribute, Some(5));
+        assert_eq!(promotion.goods_detail.as_ref().unwrap()[0].quantity, 2);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_order_query_success_at() {
+        let response: OrderQueryResponse = serde_json::from_str(
+            r#"{"appid":"wxd678efh567hg6787","mchid":"1230000109","out_trade_no":"1217752501201407033233368018","trade_state":"SUCCESS","trade_state_desc":"支付成功","success_time":"2018-06-08T10:34:56+08:00"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            response.success_at(),
+            Some(DateTime::parse_from_rfc3339("2018-06-08T10:34:56+08:00").unwrap())
+        );
+        let response: OrderQueryResponse = serde_json::from_str(
+            r#"{"appid":"wxd678efh567hg6787","mchid":"1230000109","out_trade_no":"1217752501201407033233368018","trade_state":"SUCCESS","trade_state_desc":"支付成功","success_time":"2018-06-08 10:34:56"}"#,
+        )
+        .unwrap();
+        assert!(response.success_at().is_none());
+        assert_eq!(
+            response.success_time.as_deref(),
+            Some("2018-06-08 10:34:56")
+        );
+    }
+
+    #[test]
+    fn test_response_status() {
+        let response: JsapiResponse =
+            serde_json::from_str(r#"{"prepay_id":"wx201410272009395522657a690389285100"}"#)
+                .unwrap();
+        assert!(ResponseStatus::is_success(&response));
+        assert!(ResponseStatus::err(&response).is_none());
+        let response: JsapiResponse =
+            serde_json::from_str(r#"{"code":"PARAM_ERROR","message":"参数错误"}"#).unwrap();
+        assert!(!ResponseStatus::is_success(&response));
+        let error = ResponseStatus::err(&response).unwrap();
+        assert_eq!(error.code.as_deref(), Some("PARAM_ERROR"));
+        assert_eq!(error.message.as_deref(), Some("参数错误"));
+        let response: H5Response =
+            serde_json::from_str(r#"{"code":"SYSTEM_ERROR","message":"系统错误"}"#).unwrap();
+        assert!(ResponseStatus::err(&response).unwrap().is_retryable());
+
+        let response: WeChatResponse<OrderQueryResponse> =
+            serde_json::from_str(r#"{"code":"ORDER_NOT_EXIST","message":"订单不存在"}"#).unwrap();
+        assert!(!ResponseStatus::is_success(&response));
+        assert_eq!(response.error_code(), Some("ORDER_NOT_EXIST"));
+        assert_eq!(response.error_message(), Some("订单不存在"));
+    }
+
+    #[test]
+    fn test_wechat_response_success_detection() {
+        // 字段全部可选的应答结构，错误应答体也能按T解析，需先按错误格式判断
+        #[derive(Debug, serde::Deserialize)]
+        struct TransferState {
+            state: Option<String>,
+        }
+        impl ResponseTrait for TransferState {}
+
+        let error_body = r#"{"code":"NOT_ENOUGH","message":"余额不足"}"#;
+        let response: WeChatResponse<TransferState> = serde_json::from_str(error_body).unwrap();
+        assert!(!response.is_success());
+        assert!(response.ok().is_none());
+        assert_eq!(response.err().unwrap().code.as_deref(), Some("NOT_ENOUGH"));
+        let response: WeChatResponse<RefundsResponse> = serde_json::from_str(error_body).unwrap();
+        assert!(!response.is_success());
+        assert_eq!(
+            response.err().unwrap().error_code(),
+            Some(WechatErrorCode::NotEnough)
+        );
+
+        let response: WeChatResponse<TransferState> =
+            serde_json::from_str(r#"{"state":"SUCCESS"}"#).unwrap();
+        assert!(response.is_success());
+        assert!(response.err().is_none());
+        assert_eq!(response.ok().unwrap().state.as_deref(), Some("SUCCESS"));
+        let success_body = r#"{"refund_id":"50300001","out_refund_no":"R1","transaction_id":"4200001","out_trade_no":"1243243","channel":"ORIGINAL","user_received_account":"支付用户零钱","create_time":"2024-05-01T10:00:00+08:00","status":"SUCCESS","funds_account":"AVAILABLE","amount":{"total":100,"refund":10,"payer_total":100,"payer_refund":10,"settlement_refund":10,"settlement_total":100,"discount_refund":0,"currency":"CNY"}}"#;
+        let response: WeChatResponse<RefundsResponse> = serde_json::from_str(success_body).unwrap();
+        assert!(response.is_success());
+        assert_eq!(response.ok().unwrap().out_refund_no, "R1");
+
+        // 既不是错误格式也无法解析为T时返回解析错误，而不是空的ErrorResponse
+        let result =
+            serde_json::from_str::<WeChatResponse<RefundsResponse>>(r#"{"refund_id":"1"}"#);
+        assert!(result.unwrap_err().to_string().contains("missing field"));
+        assert!(!ErrorResponse::is_error_body(
+            &serde_json::json!({"code":"","message":""})
+        ));
+    }
+
+    #[test]
+    fn test_error_response_code() {
+        let response: ErrorResponse =
+            serde_json::from_str(r#"{"code":"FREQUENCY_LIMITED","message":"频率超限"}"#).unwrap();
+        assert_eq!(
+            response.error_code(),
+            Some(WechatErrorCode::FrequencyLimited)
+        );
+        assert!(response.is_retryable());
+        let response: ErrorResponse =
+            serde_json::from_str(r#"{"code":"ORDER_NOT_EXIST","message":"订单不存在"}"#).unwrap();
+        assert_eq!(response.error_code(), Some(WechatErrorCode::OrderNotExist));
+        assert!(!response.is_retryable());
+        let response: ErrorResponse = serde_json::from_str(r#"{}"#).unwrap();
+        assert_eq!(response.error_code(), None);
+    }
+
+    #[test]
+    fn test_merchant_transfer_data() {
+        let response: TransferBillsResponse = serde_json::from_str(
+            r#"{"out_bill_no":"plfk2020042013","transfer_bill_no":"1330000071100999991182020050700019480001","create_time":"2015-05-20T13:29:35+08:00","state":"WAIT_USER_CONFIRM","package_info":"affffddafdfafddffda=="}"#,
+        )
+        .unwrap();
+        assert_eq!(response.state, TransferBillState::WaitUserConfirm);
+        let data = response
+            .merchant_transfer_data("1230000109", "wxd678efh567hg6787")
+            .unwrap();
+        let json: Value = serde_json::to_value(&data).unwrap();
+        assert_eq!(json["mchId"], "1230000109");
+        assert_eq!(json["appId"], "wxd678efh567hg6787");
+        assert_eq!(json["package"], "affffddafdfafddffda==");
+        assert_eq!(
+            response
+                .to_merchant_transfer_json("1230000109", "wxd678efh567hg6787")
+                .unwrap(),
+            serde_json::json!({
+                "mchId": "1230000109",
+                "appId": "wxd678efh567hg6787",
+                "package": "affffddafdfafddffda==",
+            })
+        );
+
+        let response: TransferBillsResponse = serde_json::from_str(
+            r#"{"out_bill_no":"plfk2020042013","transfer_bill_no":"1330000071100999991182020050700019480001","create_time":"2015-05-20T13:29:35+08:00","state":"SUCCESS"}"#,
+        )
+        .unwrap();
+        assert_eq!(response.state, TransferBillState::Success);
+        assert!(
+            response
+                .merchant_transfer_data("1230000109", "wxd678efh567hg6787")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_native_code_url() {
+        let response: NativeResponse =
+            serde_json::from_str(r#"{"code_url":"weixin://wxpay/bizpayurl?pr=p4lpSuKzz"}"#)
+                .unwrap();
+        assert_eq!(
+            response.code_url().unwrap(),
+            "weixin://wxpay/bizpayurl?pr=p4lpSuKzz"
+        );
+        let response: NativeResponse =
+            serde_json::from_str(r#"{"code":"PARAM_ERROR","message":"参数错误"}"#).unwrap();
+        let error = response.code_url().unwrap_err();
+        assert!(
+            matches!(&error, PayError::Api { code, message, .. } if code == "PARAM_ERROR" && message == "参数错误")
+        );
+        assert_eq!(error.to_string(), "pay error: PARAM_ERROR: 参数错误");
+    }
+
+    #[test]
+    fn test_profit_sharing_receiver() {
+        let response: ProfitSharingOrderResponse = serde_json::from_str(
+            r#"{"transaction_id":"4208450740201411110007820472","out_order_no":"P20150806125346","order_id":"3008450740201411110007820472","state":"FINISHED","receivers":[{"amount":100,"description":"分给商户A","type":"MERCHANT_ID","account":"86693852","result":"SUCCESS","detail_id":"36011111111111111111111","create_time":"2015-05-20T13:29:35+08:00","finish_time":"2015-05-20T13:29:35+08:00"},{"amount":200,"description":"分给用户B","type":"PERSONAL_OPENID","account":"oUpF8uMuAJO_M2pxb1Q9zNjWeS6o","result":"CLOSED","fail_reason":"ACCOUNT_ABNORMAL","detail_id":"36011111111111111111112","create_time":"2015-05-20T13:29:35+08:00","finish_time":"2015-05-20T13:29:35+08:00"}]}"#,
+        )
+        .unwrap();
+        let receiver = response.receiver("86693852").unwrap();
+        assert!(receiver.is_success());
+        assert_eq!(receiver.amount, 100);
+        let receiver = response.receiver("oUpF8uMuAJO_M2pxb1Q9zNjWeS6o").unwrap();
+        assert!(!receiver.is_success());
+        assert_eq!(receiver.result, "CLOSED");
+        assert_eq!(receiver.fail_reason.as_deref(), Some("ACCOUNT_ABNORMAL"));
+        assert!(response.receiver("1900000109").is_none());
+    }
+
+    #[test]
+    fn test_error_detail() {
+        let body = r#"{"code":"PARAM_ERROR","message":"参数错误","detail":{"field":"/amount/total","value":0,"issue":"must be >= 1","location":"body"}}"#;
+        let response: WeChatResponse<OrderQueryResponse> = serde_json::from_str(body).unwrap();
+        let detail = response.error_detail().unwrap();
+        assert_eq!(detail.field_path().as_deref(), Some("amount.total"));
+        assert_eq!(detail.value, Some(Value::from(0)));
+        assert_eq!(detail.location.as_deref(), Some("body"));
+        let error = PayError::from(response.err().unwrap().clone());
+        assert_eq!(
+            error.to_string(),
+            "pay error: PARAM_ERROR: 参数错误, field amount.total must be >= 1 (value: 0)"
+        );
+        assert_eq!(error.wechat_error_code(), Some(WechatErrorCode::ParamError));
+
+        let response: NativeResponse = serde_json::from_str(body).unwrap();
+        let error = response.code_url().unwrap_err();
+        assert_eq!(
+            error
+                .error_detail()
+                .and_then(ErrorDetail::field_path)
+                .as_deref(),
+            Some("amount.total")
+        );
+        assert_eq!(
+            error.to_string(),
+            "pay error: PARAM_ERROR: 参数错误, field amount.total must be >= 1 (value: 0)"
+        );
+
+        // 没有detail时与原格式一致
+        let error: ErrorResponse =
+            serde_json::from_str(r#"{"code":"NOAUTH","message":"商户无权限"}"#).unwrap();
+        assert!(error.detail.is_none());
+        assert_eq!(error.to_string(), "NOAUTH: 商户无权限");
+    }
+
+    #[cfg(feature = "qrcode")]
+    #[test]
+    fn test_native_qr_png() {
+        let response: NativeResponse =
+            serde_json::from_str(r#"{"code_url":"weixin://wxpay/bizpayurl?pr=p4lpSuKzz"}"#)
+                .unwrap();
+        let png = response.qr_png().unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn test_sign_data_request_payment_json() {
+        let wechat_pay =
+            WechatPay::try_new("wx0000000000000001", "", PRIVATE_KEY, "", "", "").unwrap();
+        let sign_data = wechat_pay
+            .mut_sign_data("prepay_id=", "wx201410272009395522657a690389285100")
+            .unwrap();
+        let json = serde_json::to_value(&sign_data).unwrap();
+        let mut keys = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|key| key.as_str())
+            .collect::<Vec<&str>>();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![
+                "appId",
+                "nonceStr",
+                "package",
+                "paySign",
+                "signType",
+                "timeStamp"
+            ]
+        );
+        assert_eq!(
+            json["package"],
+            Value::from("prepay_id=wx201410272009395522657a690389285100")
+        );
+        assert_eq!(json["signType"], Value::from("RSA"));
+        assert_eq!(json["timeStamp"], Value::from(sign_data.timestamp.as_str()));
+
+        let response: JsapiResponse = serde_json::from_value(serde_json::json!({
+            "prepay_id": "wx201410272009395522657a690389285100",
+            "sign_data": json,
+        }))
+        .unwrap();
+        assert_eq!(response.to_request_payment_json().unwrap(), json);
+        let response: JsapiResponse =
+            serde_json::from_str(r#"{"code":"PARAM_ERROR","message":"参数错误"}"#).unwrap();
+        assert!(matches!(
+            response.to_request_payment_json(),
+            Err(PayError::Api { code, .. }) if code == "PARAM_ERROR"
+        ));
+    }
+
+    #[test]
+    fn test_certificate_valid_at() {
+        let json = r#"{"data":[
+            {"serial_no":"OLD","effective_time":"2018-06-08T10:34:56+08:00","expire_time":"2023-06-08T10:34:56+08:00",
+             "encrypt_certificate":{"algorithm":"AEAD_AES_256_GCM","nonce":"","associated_data":"certificate","ciphertext":""}},
+            {"serial_no":"NEW","effective_time":"2023-03-22T22:58:57+08:00","expire_time":"2028-03-20T22:58:57+08:00",
+             "encrypt_certificate":{"algorithm":"AEAD_AES_256_GCM","nonce":"","associated_data":"certificate","ciphertext":""}}
+        ]}"#;
+        let response: CertificateResponse = serde_json::from_str(json).unwrap();
+        let at = |time: &str| DateTime::parse_from_rfc3339(time).unwrap();
+        let pick = |time: &str| {
+            response
+                .valid_at(&at(time))
+                .map(|cert| cert.serial_no.as_str())
+        };
+        assert_eq!(pick("2020-01-01T00:00:00+08:00"), Some("OLD"));
+        // 轮换期间两张证书都有效，取较新的一张
+        assert_eq!(pick("2023-05-01T00:00:00+08:00"), Some("NEW"));
+        assert_eq!(pick("2024-01-01T00:00:00Z"), Some("NEW"));
+        assert_eq!(pick("2030-01-01T00:00:00+08:00"), None);
+        assert_eq!(pick("2010-01-01T00:00:00+08:00"), None);
+    }
+}
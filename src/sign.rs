@@ -1,24 +1,288 @@
+use crate::error::PayError;
 use crate::util;
-use rsa::pkcs8::DecodePrivateKey;
-use rsa::sha2::Digest;
-use rsa::{Pkcs1v15Sign, RsaPrivateKey};
-
-/// sha256签名
-/// ```Cargo.toml
-/// base64 = "0.21.6"
-/// rsa = { version = "0.9.6", features = ["sha2"] }
-/// sha2 = "0.11.0-pre.0"
-/// ```
-pub(crate) fn sha256_sign<S>(private_key: S, content: S) -> String
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::sha2::{Digest, Sha256};
+use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+
+/// 签名算法，请求签名、调起支付签名统一由这里选择
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SignatureType {
+    /// SHA256 with RSA，密钥长度2048
+    #[default]
+    Sha256Rsa2048,
+    /// 国密SM2 with SM3，使用国密商户私钥
+    #[cfg(feature = "sm")]
+    Sm2WithSm3,
+}
+
+impl SignatureType {
+    /// Authorization头中的认证类型
+    pub fn auth_scheme(&self) -> &'static str {
+        match self {
+            SignatureType::Sha256Rsa2048 => "WECHATPAY2-SHA256-RSA2048",
+            #[cfg(feature = "sm")]
+            SignatureType::Sm2WithSm3 => "WECHATPAY2-SM2-WITH-SM3",
+        }
+    }
+
+    /// 调起支付时的signType
+    pub fn sign_type(&self) -> &'static str {
+        match self {
+            SignatureType::Sha256Rsa2048 => "RSA",
+            #[cfg(feature = "sm")]
+            SignatureType::Sm2WithSm3 => "SM2",
+        }
+    }
+
+    /// 按当前签名算法解析商户私钥
+    pub(crate) fn parse_private_key(&self, private_key: &str) -> Result<PrivateKey, PayError> {
+        match self {
+            SignatureType::Sha256Rsa2048 => RsaPrivateKey::from_pkcs8_pem(private_key)
+                .map(|key| PrivateKey::Rsa(Box::new(key)))
+                .map_err(|e| PayError::InvalidPrivateKey(e.to_string())),
+            #[cfg(feature = "sm")]
+            SignatureType::Sm2WithSm3 => {
+                use sm2::pkcs8::DecodePrivateKey;
+                let secret_key = sm2::SecretKey::from_pkcs8_pem(private_key)
+                    .map_err(|e| PayError::InvalidPrivateKey(e.to_string()))?;
+                sm2::dsa::SigningKey::new(SM2_DIST_ID, &secret_key)
+                    .map(|key| PrivateKey::Sm2(Box::new(key)))
+                    .map_err(|e| PayError::InvalidPrivateKey(e.to_string()))
+            }
+        }
+    }
+
+    /// 使用平台公钥验证base64编码的签名，应答和回调的签名算法与商户的签名算法一致
+    pub(crate) fn verify(
+        &self,
+        pub_key: &str,
+        message: &str,
+        signature: &str,
+    ) -> Result<(), PayError> {
+        let signature = util::base64_decode(signature)?;
+        match self {
+            SignatureType::Sha256Rsa2048 => {
+                let pub_key = RsaPublicKey::from_public_key_pem(pub_key).map_err(|e| {
+                    PayError::VerifyError(format!("public key parser error: {}", e))
+                })?;
+                let hashed = Sha256::new().chain_update(message).finalize();
+                pub_key
+                    .verify(Pkcs1v15Sign::new::<Sha256>(), &hashed, &signature)
+                    .map_err(|e| PayError::VerifyError(e.to_string()))
+            }
+            #[cfg(feature = "sm")]
+            SignatureType::Sm2WithSm3 => sm2_verify(pub_key, message, &signature),
+        }
+    }
+}
+
+/// 解析后的商户私钥，构造时解析一次，避免每次签名重复解析PEM
+pub(crate) enum PrivateKey {
+    Rsa(Box<RsaPrivateKey>),
+    #[cfg(feature = "sm")]
+    Sm2(Box<sm2::dsa::SigningKey>),
+}
+
+impl PrivateKey {
+    /// 签名，返回base64编码的签名
+    pub(crate) fn sign(&self, content: &str) -> Result<String, PayError> {
+        match self {
+            PrivateKey::Rsa(private_key) => {
+                let hasher = rsa::sha2::Sha256::new().chain_update(content).finalize();
+                let padding = Pkcs1v15Sign::new::<rsa::sha2::Sha256>();
+                let sign_result = private_key
+                    .sign(padding, &hasher)
+                    .map_err(|e| PayError::InvalidPrivateKey(e.to_string()))?;
+                Ok(util::base64_encode(sign_result))
+            }
+            #[cfg(feature = "sm")]
+            PrivateKey::Sm2(signing_key) => {
+                use sm2::dsa::Signature;
+                use sm2::dsa::signature::Signer;
+                let signature: Signature = signing_key
+                    .try_sign(content.as_bytes())
+                    .map_err(|e| PayError::InvalidPrivateKey(e.to_string()))?;
+                Ok(util::base64_encode(der_signature(
+                    &signature.r_bytes(),
+                    &signature.s_bytes(),
+                )))
+            }
+        }
+    }
+}
+
+/// 不输出私钥内容
+impl std::fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrivateKey::Rsa(_) => f.write_str("PrivateKey::Rsa(..)"),
+            #[cfg(feature = "sm")]
+            PrivateKey::Sm2(_) => f.write_str("PrivateKey::Sm2(..)"),
+        }
+    }
+}
+
+/// 测试中按PEM私钥做sha256签名
+#[cfg(test)]
+pub(crate) fn sha256_sign<S>(private_key: S, content: S) -> Result<String, PayError>
+where
+    S: AsRef<str>,
+{
+    SignatureType::Sha256Rsa2048
+        .parse_private_key(private_key.as_ref())?
+        .sign(content.as_ref())
+}
+
+/// SM2签名默认的用户身份标识
+#[cfg(feature = "sm")]
+const SM2_DIST_ID: &str = "1234567812345678";
+
+/// 测试中按PEM私钥做SM2 with SM3签名，签名结果为DER编码的(r, s)
+#[cfg(all(test, feature = "sm"))]
+pub(crate) fn sm2_sign<S>(private_key: S, content: S) -> Result<String, PayError>
 where
     S: AsRef<str>,
 {
-    let private_key =
-        RsaPrivateKey::from_pkcs8_pem(private_key.as_ref()).expect("failed to parse key");
-    let hasher = rsa::sha2::Sha256::new()
-        .chain_update(content.as_ref())
-        .finalize();
-    let padding = Pkcs1v15Sign::new::<rsa::sha2::Sha256>();
-    let sign_result = private_key.sign(padding, &hasher).expect("failed to sign");
-    util::base64_encode(sign_result)
+    SignatureType::Sm2WithSm3
+        .parse_private_key(private_key.as_ref())?
+        .sign(content.as_ref())
+}
+
+/// SM2 with SM3验签，signature为DER编码的(r, s)
+#[cfg(feature = "sm")]
+fn sm2_verify(pub_key: &str, message: &str, signature: &[u8]) -> Result<(), PayError> {
+    use sm2::dsa::signature::Verifier;
+    use sm2::dsa::{Signature, VerifyingKey};
+    use sm2::pkcs8::DecodePublicKey;
+
+    let pub_key = sm2::PublicKey::from_public_key_pem(pub_key)
+        .map_err(|e| PayError::VerifyError(format!("public key parser error: {}", e)))?;
+    let verifying_key = VerifyingKey::new(SM2_DIST_ID, pub_key)
+        .map_err(|e| PayError::VerifyError(e.to_string()))?;
+    let signature = raw_signature(signature)
+        .and_then(|raw| Signature::from_slice(&raw).ok())
+        .ok_or_else(|| PayError::VerifyError("invalid sm2 signature".to_string()))?;
+    verifying_key
+        .verify(message.as_bytes(), &signature)
+        .map_err(|e| PayError::VerifyError(e.to_string()))
+}
+
+/// 解析DER编码的(r, s)为定长的r||s，格式错误时返回None
+#[cfg(feature = "sm")]
+fn raw_signature(der: &[u8]) -> Option<Vec<u8>> {
+    let [0x30, len, body @ ..] = der else {
+        return None;
+    };
+    if *len as usize != body.len() {
+        return None;
+    }
+    let mut rest = body;
+    let mut raw = Vec::with_capacity(64);
+    for _ in 0..2 {
+        let [0x02, len, tail @ ..] = rest else {
+            return None;
+        };
+        let (integer, next) = tail.split_at_checked(*len as usize)?;
+        let start = integer
+            .iter()
+            .position(|b| *b != 0)
+            .unwrap_or(integer.len());
+        let integer = &integer[start..];
+        if integer.len() > 32 {
+            return None;
+        }
+        raw.extend(std::iter::repeat_n(0, 32 - integer.len()));
+        raw.extend_from_slice(integer);
+        rest = next;
+    }
+    rest.is_empty().then_some(raw)
+}
+
+/// 按ASN.1 SEQUENCE { INTEGER r, INTEGER s }编码
+#[cfg(feature = "sm")]
+fn der_signature(r: &[u8], s: &[u8]) -> Vec<u8> {
+    fn der_integer(bytes: &[u8]) -> Vec<u8> {
+        let start = bytes
+            .iter()
+            .position(|b| *b != 0)
+            .unwrap_or(bytes.len() - 1);
+        let bytes = &bytes[start..];
+        let mut integer = vec![0x02];
+        if bytes[0] & 0x80 != 0 {
+            integer.push(bytes.len() as u8 + 1);
+            integer.push(0);
+        } else {
+            integer.push(bytes.len() as u8);
+        }
+        integer.extend_from_slice(bytes);
+        integer
+    }
+    let mut body = der_integer(r);
+    body.extend(der_integer(s));
+    let mut sequence = vec![0x30, body.len() as u8];
+    sequence.extend(body);
+    sequence
+}
+
+#[cfg(all(test, feature = "sm"))]
+mod tests {
+    use super::{SignatureType, raw_signature, sm2_sign};
+    use crate::error::PayError;
+    use crate::util;
+    use sm2::dsa::signature::Verifier;
+    use sm2::dsa::{Signature, SigningKey};
+    use sm2::pkcs8::{DecodePrivateKey, EncodePublicKey, LineEnding};
+
+    const SM2_KEY: &str = include_str!("testdata/sm2_key.pem");
+
+    #[test]
+    fn test_sm2_sign() {
+        let content = "GET\n/v3/certificates\n1554208460\n593BEC0C930BF1AFEB40B4A08C8FB242\n\n";
+        let signature = util::base64_decode(sm2_sign(SM2_KEY, content).unwrap()).unwrap();
+        assert_eq!(signature[0], 0x30);
+        let signature = Signature::from_slice(&raw_signature(&signature).unwrap()).unwrap();
+        let secret_key = sm2::SecretKey::from_pkcs8_pem(SM2_KEY).unwrap();
+        let signing_key = SigningKey::new("1234567812345678", &secret_key).unwrap();
+        signing_key
+            .verifying_key()
+            .verify(content.as_bytes(), &signature)
+            .expect("sm2 verify");
+        assert_eq!(
+            SignatureType::Sm2WithSm3.auth_scheme(),
+            "WECHATPAY2-SM2-WITH-SM3"
+        );
+    }
+    #[test]
+    fn test_sm2_parsed_private_key() {
+        let content = "GET\n/v3/certificates\n1554208460\n593BEC0C930BF1AFEB40B4A08C8FB242\n\n";
+        let private_key = SignatureType::Sm2WithSm3
+            .parse_private_key(SM2_KEY)
+            .unwrap();
+        let signature = util::base64_decode(private_key.sign(content).unwrap()).unwrap();
+        let signature = Signature::from_slice(&raw_signature(&signature).unwrap()).unwrap();
+        let secret_key = sm2::SecretKey::from_pkcs8_pem(SM2_KEY).unwrap();
+        let signing_key = SigningKey::new("1234567812345678", &secret_key).unwrap();
+        signing_key
+            .verifying_key()
+            .verify(content.as_bytes(), &signature)
+            .expect("sm2 verify");
+    }
+
+    #[test]
+    fn test_sm2_verify() {
+        let content = "1554208460\n593BEC0C930BF1AFEB40B4A08C8FB242\n{\"code\":\"SUCCESS\"}\n";
+        let signature = sm2_sign(SM2_KEY, content).unwrap();
+        let pub_key = sm2::SecretKey::from_pkcs8_pem(SM2_KEY)
+            .unwrap()
+            .public_key()
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        SignatureType::Sm2WithSm3
+            .verify(&pub_key, content, &signature)
+            .unwrap();
+        let result = SignatureType::Sm2WithSm3.verify(&pub_key, "tampered", &signature);
+        assert!(matches!(result, Err(PayError::VerifyError(_))));
+        let result = SignatureType::Sm2WithSm3.verify(&pub_key, content, "AAAA");
+        assert!(matches!(result, Err(PayError::VerifyError(_))));
+    }
 }
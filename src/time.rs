@@ -0,0 +1,78 @@
+//! 微信支付时间字段解析，需开启`chrono` feature
+//!
+//! 应答和回调中的时间为带`+08:00`时区的rfc3339字符串，结构体上保留原始字符串，
+//! 通过`success_at`、`created_at`等方法取得解析后的时间，格式不符合预期时返回None，仍可读取原始字符串
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Deserializer};
+
+/// 北京时间，支付分等接口的`yyyyMMddHHmmss`格式不带时区，按该时区解析
+const CHINA_OFFSET_SECONDS: i32 = 8 * 3600;
+
+/// 解析微信支付的时间，支持rfc3339格式和不带时区的`yyyyMMddHHmmss`格式(按北京时间)
+pub fn parse_time(value: &str) -> Result<DateTime<FixedOffset>, chrono::ParseError> {
+    DateTime::parse_from_rfc3339(value).or_else(|e| {
+        let offset = FixedOffset::east_opt(CHINA_OFFSET_SECONDS).unwrap();
+        NaiveDateTime::parse_from_str(value, "%Y%m%d%H%M%S")
+            .ok()
+            .and_then(|time| offset.from_local_datetime(&time).single())
+            .ok_or(e)
+    })
+}
+
+/// 自定义结构体的serde反序列化辅助
+/// `#[serde(deserialize_with = "wechat_pay_rust_sdk::time::deserialize")]`
+pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<FixedOffset>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    parse_time(&value).map_err(serde::de::Error::custom)
+}
+
+/// 可选时间字段的serde反序列化辅助，字段缺失时需同时标注`#[serde(default)]`
+pub fn deserialize_option<'de, D>(
+    deserializer: D,
+) -> Result<Option<DateTime<FixedOffset>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse_time(&value).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+pub(crate) fn parse_option(value: Option<&str>) -> Option<DateTime<FixedOffset>> {
+    value.and_then(|value| parse_time(value).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[test]
+    fn test_parse_time() {
+        let time = parse_time("2015-05-20T13:29:35+08:00").unwrap();
+        assert_eq!(time.offset().local_minus_utc(), 8 * 3600);
+        assert_eq!(time.timestamp(), 1432099775);
+        assert_eq!(parse_time("20150520132935").unwrap(), time);
+        assert!(parse_time("2015/05/20 13:29:35").is_err());
+    }
+
+    #[test]
+    fn test_deserialize() {
+        #[derive(Deserialize)]
+        struct Notify {
+            #[serde(deserialize_with = "deserialize")]
+            create_time: DateTime<FixedOffset>,
+            #[serde(default, deserialize_with = "deserialize_option")]
+            success_time: Option<DateTime<FixedOffset>>,
+        }
+        let notify: Notify =
+            serde_json::from_str(r#"{"create_time":"2015-05-20T13:29:35+08:00"}"#).unwrap();
+        assert_eq!(notify.create_time.timestamp(), 1432099775);
+        assert!(notify.success_time.is_none());
+        assert!(serde_json::from_str::<Notify>(r#"{"create_time":"yesterday"}"#).is_err());
+    }
+}
@@ -1,10 +1,236 @@
+use crate::error::PayError;
+use crate::response::{ErrorResponse, ResponseTrait, TradeBillRecord};
 use base64::engine::general_purpose;
-use base64::{DecodeError, Engine};
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::{DecodeError, Engine, alphabet};
+use chrono::Utc;
+use serde::de::DeserializeOwned;
 use std::error::Error;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU32, Ordering};
 use uuid::Uuid;
 
+const TRADE_NO_COUNTER_MODULUS: u32 = 10_000_000;
+
+/// 生成32位纯数字的商户订单号，可并发调用
+/// 格式为`UTC时间yyyyMMddHHmmss(14位)` + `微秒(6位)` + `进程号后5位` + `自增计数器(7位)`
+/// 同一进程内计数器按原子操作自增，只有同一微秒内生成超过1000万个才会重复，因此进程内不会碰撞
+/// 跨进程/跨机器依赖进程号区分，计数器起点随机，两台机器在同一微秒、进程号后5位相同时碰撞概率约为1/10^7
+/// 订单号重复会导致下单被拒绝甚至查到其他订单，多机部署对唯一性要求更高时请在订单号中加入机器标识
 pub fn random_trade_no() -> String {
-    Uuid::new_v4().simple().to_string()
+    static COUNTER: OnceLock<AtomicU32> = OnceLock::new();
+    let counter = COUNTER.get_or_init(|| {
+        AtomicU32::new((Uuid::new_v4().as_u128() % TRADE_NO_COUNTER_MODULUS as u128) as u32)
+    });
+    let sequence = counter.fetch_add(1, Ordering::Relaxed) % TRADE_NO_COUNTER_MODULUS;
+    let now = Utc::now();
+    format!(
+        "{}{:06}{:05}{:07}",
+        now.format("%Y%m%d%H%M%S"),
+        now.timestamp_subsec_micros() % 1_000_000,
+        std::process::id() % 100_000,
+        sequence
+    )
+}
+
+/// 随机字符串的默认长度，微信支付要求nonce不超过32个字符
+pub const NONCE_STR_LEN: usize = 32;
+
+const NONCE_CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+/// 生成由大小写字母和数字组成的随机字符串，用于签名的nonce_str、multipart的boundary等
+/// 随机数取自`Uuid::new_v4`，丢弃版本号和变体所在的字节，按62取模时拒绝采样避免偏差
+pub fn nonce_str(len: usize) -> String {
+    let mut nonce = String::with_capacity(len);
+    while nonce.len() < len {
+        let uuid = Uuid::new_v4();
+        let bytes = uuid.as_bytes();
+        for (index, byte) in bytes.iter().enumerate() {
+            if index == 6 || index == 8 {
+                continue;
+            }
+            if nonce.len() == len {
+                break;
+            }
+            if (*byte as usize) < NONCE_CHARSET.len() * 4 {
+                nonce.push(NONCE_CHARSET[*byte as usize % NONCE_CHARSET.len()] as char);
+            }
+        }
+    }
+    nonce
+}
+
+/// 校验下载文件的hash，hash_type支持SHA1(账单)和SHA256(电子回单)，hash_value为十六进制，不区分大小写
+/// 微信对文件本身计算hash，传入的应是去掉HTTP传输压缩(Content-Encoding)后的内容，
+/// 开启gzip等feature时reqwest已自动解压；tar_type为GZIP的账单文件本身是压缩包，直接对压缩包校验
+pub fn verify_hash(
+    content: impl AsRef<[u8]>,
+    hash_type: &str,
+    hash_value: &str,
+) -> Result<(), PayError> {
+    use rsa::sha2::{Digest, Sha256};
+    let digest = match hash_type.to_ascii_uppercase().as_str() {
+        "SHA1" => sha1::Sha1::digest(content.as_ref()).to_vec(),
+        "SHA256" => Sha256::digest(content.as_ref()).to_vec(),
+        other => {
+            return Err(PayError::InvalidParams(format!(
+                "unsupported hash_type {}",
+                other
+            )));
+        }
+    };
+    let actual = digest
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    if actual.eq_ignore_ascii_case(hash_value) {
+        Ok(())
+    } else {
+        Err(PayError::VerifyError(format!(
+            "{} hash mismatch, expected {}, got {}",
+            hash_type, hash_value, actual
+        )))
+    }
+}
+
+/// 解析交易账单(tar_type不为GZIP时下载到的文本)，按表头列名取值，兼容ALL、SUCCESS、REFUND三种账单
+/// 明细行每个字段以`开头，遇到汇总表头(总交易单数)时结束，金额从元转换为分
+pub fn parse_trade_bill(content: &str) -> Result<Vec<TradeBillRecord>, PayError> {
+    let mut lines = content
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty());
+    let header: Vec<&str> = lines
+        .next()
+        .filter(|line| line.starts_with("交易时间"))
+        .ok_or_else(|| PayError::InvalidParams("trade bill header not found".to_string()))?
+        .split(',')
+        .collect();
+    let column = |name: &str| header.iter().position(|column| *column == name);
+    let columns = [
+        column("交易时间"),
+        column("公众账号ID"),
+        column("商户号"),
+        column("微信订单号"),
+        column("商户订单号"),
+        column("用户标识"),
+        column("交易类型"),
+        column("交易状态"),
+        column("应结订单金额"),
+        column("微信退款单号"),
+        column("商户退款单号"),
+        column("退款金额"),
+        column("手续费"),
+        column("订单金额"),
+    ];
+    let mut records = Vec::new();
+    for line in lines.take_while(|line| line.starts_with('`')) {
+        let fields: Vec<&str> = line
+            .split(',')
+            .map(|field| field.trim_start_matches('`'))
+            .collect();
+        let text = |index: Option<usize>| {
+            index
+                .and_then(|index| fields.get(index))
+                .map(|field| field.to_string())
+                .unwrap_or_default()
+        };
+        let cents = |index: Option<usize>| match index.and_then(|index| fields.get(index)) {
+            Some(field) if !field.is_empty() => yuan_to_cents(field),
+            _ => Ok(0),
+        };
+        let [
+            trade_time,
+            appid,
+            mchid,
+            transaction_id,
+            out_trade_no,
+            openid,
+            trade_type,
+            trade_state,
+            settlement_total,
+            refund_id,
+            out_refund_no,
+            refund,
+            fee,
+            total,
+        ] = columns;
+        records.push(TradeBillRecord {
+            trade_time: text(trade_time),
+            appid: text(appid),
+            mchid: text(mchid),
+            transaction_id: text(transaction_id),
+            out_trade_no: text(out_trade_no),
+            openid: text(openid),
+            trade_type: text(trade_type),
+            trade_state: text(trade_state),
+            settlement_total: cents(settlement_total)?,
+            refund_id: text(refund_id),
+            out_refund_no: text(out_refund_no),
+            refund: cents(refund)?,
+            fee: cents(fee)?,
+            total: cents(total)?,
+        });
+    }
+    Ok(records)
+}
+
+/// 账单金额以元为单位、最多两位小数，转换为分
+fn yuan_to_cents(amount: &str) -> Result<i64, PayError> {
+    let invalid = || PayError::InvalidParams(format!("invalid bill amount {}", amount));
+    let (negative, digits) = match amount.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, amount),
+    };
+    let (yuan, fen) = digits.split_once('.').unwrap_or((digits, ""));
+    if yuan.is_empty()
+        || fen.len() > 2
+        || !(yuan.chars().chain(fen.chars())).all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    let yuan: i64 = yuan.parse().map_err(|_| invalid())?;
+    let fen: i64 = format!("{:0<2}", fen).parse().map_err(|_| invalid())?;
+    let cents = yuan * 100 + fen;
+    Ok(if negative { -cents } else { cents })
+}
+
+/// 在接口路径后追加查询参数，参数按application/x-www-form-urlencoded编码，值中的&、#、+、空格不会改变请求
+/// 带查询参数的GET接口都通过这里拼接，path已带查询参数时追加在后面
+pub(crate) fn with_query(path: &str, params: &[(&str, &str)]) -> String {
+    if params.is_empty() {
+        return path.to_string();
+    }
+    // 只借用Url的查询参数编码，域名不会出现在结果中
+    let mut url = reqwest::Url::parse("https://api.mch.weixin.qq.com/").expect("valid url");
+    url.query_pairs_mut().extend_pairs(params);
+    let separator = if path.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", path, separator, url.query().unwrap_or_default())
+}
+
+/// 校验回调地址，微信要求notify_url为可直接访问的https地址，不能携带查询参数
+pub fn validate_notify_url(notify_url: &str) -> Result<(), PayError> {
+    if notify_url.is_empty() {
+        return Err(PayError::InvalidParams(
+            "notify_url must not be empty".to_string(),
+        ));
+    }
+    let url = reqwest::Url::parse(notify_url)
+        .map_err(|e| PayError::InvalidParams(format!("notify_url {}: {}", notify_url, e)))?;
+    if url.scheme() != "https" || url.host_str().is_none() {
+        return Err(PayError::InvalidParams(format!(
+            "notify_url must be an https url, got {}",
+            notify_url
+        )));
+    }
+    if url.query().is_some() {
+        return Err(PayError::InvalidParams(format!(
+            "notify_url must not contain query parameters, got {}",
+            notify_url
+        )));
+    }
+    Ok(())
 }
 
 pub fn base64_encode<S>(content: S) -> String
@@ -14,11 +240,125 @@ where
     general_purpose::STANDARD.encode(content)
 }
 
+const INDIFFERENT_PADDING: GeneralPurposeConfig =
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+const STANDARD_INDIFFERENT: GeneralPurpose =
+    GeneralPurpose::new(&alphabet::STANDARD, INDIFFERENT_PADDING);
+const URL_SAFE_INDIFFERENT: GeneralPurpose =
+    GeneralPurpose::new(&alphabet::URL_SAFE, INDIFFERENT_PADDING);
+
+/// base64解码，签名、密文等字段统一由这里解码
+/// 兼容标准与URL安全两种字母表，允许省略填充，忽略换行等空白字符
 pub fn base64_decode<S>(content: S) -> Result<Vec<u8>, DecodeError>
 where
     S: AsRef<[u8]>,
 {
-    general_purpose::STANDARD.decode(content.as_ref())
+    let content = content
+        .as_ref()
+        .iter()
+        .copied()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect::<Vec<u8>>();
+    if content.iter().any(|b| *b == b'-' || *b == b'_') {
+        URL_SAFE_INDIFFERENT.decode(content)
+    } else {
+        STANDARD_INDIFFERENT.decode(content)
+    }
+}
+
+/// 日志中隐藏敏感值，只保留前4个字符
+pub fn redact(value: &str) -> String {
+    match value.char_indices().nth(4) {
+        Some((index, _)) => format!("{}***", &value[..index]),
+        None => "***".to_string(),
+    }
+}
+
+/// 解析应答JSON，默认使用serde_json，开启`simd-json` feature后使用simd-json
+/// 应答解析统一经过这里，错误统一为serde_json::Error便于构造`PayError::ResponseParseError`
+pub fn from_json_str<T: DeserializeOwned>(content: &str) -> Result<T, serde_json::Error> {
+    #[cfg(feature = "simd-json")]
+    {
+        let mut bytes = content.as_bytes().to_vec();
+        simd_json::serde::from_slice(&mut bytes).map_err(serde::de::Error::custom)
+    }
+    #[cfg(not(feature = "simd-json"))]
+    {
+        serde_json::from_str(content)
+    }
+}
+
+/// 解析应答JSON为应答结构，只有需要保留原始JSON(`ResponseTrait::KEEP_RAW`)的应答才先解析为Value
+pub fn parse_response<R: ResponseTrait>(content: &str) -> Result<R, serde_json::Error> {
+    if !R::KEEP_RAW {
+        return from_json_str(content);
+    }
+    let raw: serde_json::Value = from_json_str(content)?;
+    let mut response = R::deserialize(&raw)?;
+    response.set_raw(raw);
+    Ok(response)
+}
+
+/// gzip压缩请求体
+#[cfg(feature = "gzip")]
+pub(crate) fn gzip(content: &[u8]) -> Result<Vec<u8>, PayError> {
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(content)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| PayError::InvalidParams(format!("gzip request body: {}", e)))
+}
+
+/// 图片上传接口的multipart请求体，meta为签名使用的JSON
+/// 文件名原样写入Content-Disposition，含有双引号、反斜杠或换行等控制字符时返回错误，避免请求体被截断
+pub(crate) fn multipart_image_body(
+    boundary: &str,
+    meta: &str,
+    filename: &str,
+    content: &[u8],
+) -> Result<Vec<u8>, PayError> {
+    if filename.is_empty()
+        || filename
+            .chars()
+            .any(|c| c == '"' || c == '\\' || c.is_control())
+    {
+        return Err(PayError::InvalidParams(format!(
+            "invalid filename {:?}: must not be empty or contain quotes, backslashes or control characters",
+            filename
+        )));
+    }
+    let extension = filename
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+    let content_type = match extension.as_str() {
+        "png" => "image/png",
+        "bmp" => "image/bmp",
+        _ => "image/jpeg",
+    };
+    let mut body = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"meta\"\r\nContent-Type: application/json\r\n\r\n{meta}\r\n\
+         --{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\nContent-Type: {content_type}\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(content);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    Ok(body)
+}
+
+/// 401应答体是否说明签名时间戳超出范围，只有这种错误才需要按Date头校正时钟后重签
+/// 签名错误、证书序列号错误等其他原因的401重签也不会成功
+pub(crate) fn is_timestamp_rejected(body: &str) -> bool {
+    from_json_str::<ErrorResponse>(body)
+        .ok()
+        .and_then(|error| error.message)
+        .is_some_and(|message| {
+            message.contains("时间戳") || message.to_ascii_lowercase().contains("timestamp")
+        })
 }
 
 pub fn x509_to_pem(content: &[u8]) -> Result<String, Box<dyn Error>> {
@@ -45,3 +385,223 @@ pub fn x509_is_valid(content: &[u8]) -> Result<(bool, i64), Box<dyn Error>> {
     let expire_time = cert.validity().is_valid();
     Ok((expire_time, cert.validity.not_after.timestamp()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        NONCE_STR_LEN, base64_decode, base64_encode, from_json_str, is_timestamp_rejected,
+        multipart_image_body, nonce_str, parse_response, parse_trade_bill, random_trade_no, redact,
+        validate_notify_url, verify_hash, with_query,
+    };
+    use crate::error::PayError;
+    use crate::response::{CertificateResponse, ErrorResponse, OrderQueryResponse};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_with_query() {
+        assert_eq!(with_query("/v3/bill/tradebill", &[]), "/v3/bill/tradebill");
+        assert_eq!(
+            with_query(
+                "/v3/bill/tradebill",
+                &[("bill_date", "2024-05-01"), ("bill_type", "ALL")]
+            ),
+            "/v3/bill/tradebill?bill_date=2024-05-01&bill_type=ALL"
+        );
+        // 值中的特殊字符被编码，不会拆成新的参数或截断URL
+        assert_eq!(
+            with_query(
+                "/v3/payscore/serviceorder",
+                &[("out_order_no", "a&b=c #d+e")]
+            ),
+            "/v3/payscore/serviceorder?out_order_no=a%26b%3Dc+%23d%2Be"
+        );
+        assert_eq!(
+            with_query("/v3/x?a=1", &[("offset", "0")]),
+            "/v3/x?a=1&offset=0"
+        );
+    }
+
+    #[test]
+    fn test_multipart_image_body_filename() {
+        let body = multipart_image_body("b", "{}", "logo.png", b"png").unwrap();
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.contains("filename=\"logo.png\"\r\nContent-Type: image/png\r\n"));
+        assert!(body.ends_with("png\r\n--b--\r\n"));
+
+        // 双引号、换行会破坏Content-Disposition，使发送的内容与签名的meta不一致
+        for filename in ["a\".png", "a.png\r\nX-Injected: 1", "a\\.png", ""] {
+            let result = multipart_image_body("b", "{}", filename, b"png");
+            assert!(
+                matches!(result, Err(PayError::InvalidParams(_))),
+                "{:?}",
+                filename
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_timestamp_rejected() {
+        assert!(is_timestamp_rejected(
+            r#"{"code":"SIGN_ERROR","message":"签名时间戳与当前时间相差过大"}"#
+        ));
+        assert!(is_timestamp_rejected(
+            r#"{"code":"SIGN_ERROR","message":"Timestamp is expired"}"#
+        ));
+        assert!(!is_timestamp_rejected(
+            r#"{"code":"SIGN_ERROR","message":"签名错误"}"#
+        ));
+        assert!(!is_timestamp_rejected("<html>timestamp</html>"));
+    }
+
+    #[test]
+    fn test_from_json_str() {
+        let error: ErrorResponse =
+            from_json_str(r#"{"code":"PARAM_ERROR","message":"参数错误"}"#).unwrap();
+        assert_eq!(error.code.as_deref(), Some("PARAM_ERROR"));
+        assert_eq!(error.message.as_deref(), Some("参数错误"));
+        let value: serde_json::Value = from_json_str("null").unwrap();
+        assert!(value.is_null());
+        assert!(from_json_str::<serde_json::Value>("<html>").is_err());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        // 保留原始JSON的应答可以读取结构中没有的字段
+        let order: OrderQueryResponse = parse_response(
+            r#"{"appid":"wx_appid","mchid":"1900000001","out_trade_no":"1243243","trade_state":"SUCCESS","trade_state_desc":"支付成功","new_field":"new"}"#,
+        )
+        .unwrap();
+        assert_eq!(order.trade_state, "SUCCESS");
+        assert_eq!(order.raw()["new_field"], "new");
+        let certificates: CertificateResponse = parse_response(r#"{"data":[]}"#).unwrap();
+        assert_eq!(certificates.data.map(|data| data.len()), Some(0));
+        assert!(parse_response::<CertificateResponse>("<html>").is_err());
+    }
+
+    #[test]
+    fn test_verify_hash() {
+        let content = b"hello";
+        assert!(verify_hash(content, "SHA1", "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d").is_ok());
+        assert!(
+            verify_hash(
+                content,
+                "SHA256",
+                "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824"
+            )
+            .is_ok()
+        );
+        assert!(matches!(
+            verify_hash(content, "SHA256", "00"),
+            Err(PayError::VerifyError(_))
+        ));
+        assert!(matches!(
+            verify_hash(content, "MD5", "5d41402abc4b2a76b9719d911017c592"),
+            Err(PayError::InvalidParams(_))
+        ));
+    }
+
+    const TRADE_BILL: &str = include_str!("testdata/tradebill.csv");
+
+    #[test]
+    fn test_parse_trade_bill() {
+        let records = parse_trade_bill(TRADE_BILL).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].out_trade_no, "T001");
+        assert_eq!(records[0].trade_time, "2024-05-01 10:00:00");
+        assert_eq!(records[0].settlement_total, 1234);
+        assert_eq!(records[0].fee, 7);
+        assert_eq!(records[1].total, 50);
+        assert_eq!(records[2].trade_state, "REFUND");
+        assert_eq!(records[2].out_refund_no, "R001");
+        assert_eq!(records[2].refund, 100);
+        assert_eq!(records[2].fee, -1);
+
+        assert!(matches!(
+            parse_trade_bill("not a bill"),
+            Err(PayError::InvalidParams(_))
+        ));
+        let invalid = TRADE_BILL.replace("`12.34,`0.00", "`12.345,`0.00");
+        assert!(matches!(
+            parse_trade_bill(&invalid),
+            Err(PayError::InvalidParams(message)) if message.contains("12.345")
+        ));
+    }
+
+    #[test]
+    fn test_nonce_str() {
+        for len in [0, 1, 16, NONCE_STR_LEN, 100] {
+            let nonce = nonce_str(len);
+            assert_eq!(nonce.len(), len);
+            assert!(nonce.chars().all(|c| c.is_ascii_alphanumeric()));
+        }
+        assert_ne!(nonce_str(NONCE_STR_LEN), nonce_str(NONCE_STR_LEN));
+    }
+
+    #[test]
+    fn test_validate_notify_url() {
+        assert!(validate_notify_url("https://mydomain.com/notify").is_ok());
+        for notify_url in [
+            "",
+            "mydomain.com/notify",
+            "http://mydomain.com/notify",
+            "https://mydomain.com/notify?order=1",
+        ] {
+            assert!(validate_notify_url(notify_url).is_err(), "{}", notify_url);
+        }
+    }
+
+    #[test]
+    fn test_random_trade_no_unique() {
+        let handles = (0..8)
+            .map(|_| {
+                std::thread::spawn(|| (0..20_000).map(|_| random_trade_no()).collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+        let mut trade_nos = HashSet::new();
+        for handle in handles {
+            for trade_no in handle.join().unwrap() {
+                assert_eq!(trade_no.len(), 32);
+                assert!(trade_no.chars().all(|c| c.is_ascii_digit()));
+                assert!(trade_nos.insert(trade_no), "duplicate trade no");
+            }
+        }
+        assert_eq!(trade_nos.len(), 160_000);
+    }
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(redact("593BEC0C930BF1AFEB40B4A08C8FB242"), "593B***");
+        assert_eq!(redact("签名数据很长"), "签名数据***");
+        assert_eq!(redact("abcd"), "***");
+        assert_eq!(redact(""), "***");
+    }
+
+    #[test]
+    fn test_base64_decode_signature() {
+        // 应答签名使用标准字母表，包含+和/
+        let signature = "ZDd1646XPrvwX9pOZuQrJhnMC1qeb7GqL1yc0t3JJKdPf6HGALR2IhfCUyxf7odSazq7IVBT888vYyTT3SmBQbaKIw+uNp+IIl8/JbUsy5OglGcR86H/GcP/vOkBReEwmqaG1B65iP/Pd9Nt/DS3leyibA3pJ2Bev5Er7XjB21KQJv+/t+nzs0/t6Dtf5XT6oqjtObBIgtcy8AKO+CKDKwOlgUzOheRq0ypYNQWdOTPScW+dOjE70Rp22iYmKAxNKwrx+xolY/CzYREDr9Xg0pnZPpSXbt44cnBfW6CMmOE8c/GoKPzXEqmd3QTktV/QLIEIsafXA187utDcDTCBpA==";
+        let bytes = base64_decode(signature).unwrap();
+        assert_eq!(bytes.len(), 256);
+        assert_eq!(base64_encode(&bytes), signature);
+        // 按64字符换行的内容
+        let wrapped = signature
+            .as_bytes()
+            .chunks(64)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap())
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        assert_eq!(base64_decode(wrapped).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_base64_decode_ciphertext() {
+        let ciphertext = [0xfb, 0xff, 0xbf, 0x3e, 0x01];
+        // 标准字母表
+        assert_eq!(base64_decode("+/+/PgE=").unwrap(), ciphertext);
+        // URL安全字母表，省略填充
+        assert_eq!(base64_decode("-_-_PgE").unwrap(), ciphertext);
+        assert_eq!(base64_decode("-_-_PgE=").unwrap(), ciphertext);
+        assert!(base64_decode("+/-_PgE=").is_err());
+        assert!(base64_decode("not base64!").is_err());
+    }
+}
@@ -0,0 +1,224 @@
+//! 使用wiremock模拟微信支付服务，不需要真实的商户证书即可离线测试
+//! 模拟服务只响应签名正确的请求，新接口的测试可以按同样的方式挂载应答
+//! 同步模式下模拟服务运行在独立的tokio运行时中，测试线程直接调用阻塞接口
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::sha2::{Digest, Sha256};
+use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+use wechat_pay_rust_sdk::error::PayError;
+use wechat_pay_rust_sdk::model::{NativeParams, RefundsParams};
+use wechat_pay_rust_sdk::pay::WechatPay;
+use wechat_pay_rust_sdk::response::WeChatResponse;
+use wiremock::matchers::{body_partial_json, method, path, query_param};
+use wiremock::{Match, Mock, MockServer, Request, ResponseTemplate};
+
+const PRIVATE_KEY: &str = include_str!("../src/testdata/apiclient_key.pem");
+const APPID: &str = "wxd678efh567hg6787";
+const MCHID: &str = "1230000109";
+
+#[cfg(feature = "async")]
+macro_rules! run {
+    ($future:expr) => {
+        $future.await
+    };
+}
+
+#[cfg(not(feature = "async"))]
+macro_rules! run {
+    ($future:expr) => {
+        runtime().block_on($future)
+    };
+}
+
+#[cfg(not(feature = "async"))]
+fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: std::sync::OnceLock<tokio::runtime::Runtime> = std::sync::OnceLock::new();
+    RUNTIME.get_or_init(|| tokio::runtime::Runtime::new().unwrap())
+}
+
+/// 校验Authorization签名，签名串使用服务端收到的method、path、查询参数和body
+struct SignedBy(RsaPublicKey);
+
+impl SignedBy {
+    fn merchant() -> Self {
+        let private_key = RsaPrivateKey::from_pkcs8_pem(PRIVATE_KEY).unwrap();
+        Self(RsaPublicKey::from(&private_key))
+    }
+}
+
+impl Match for SignedBy {
+    fn matches(&self, request: &Request) -> bool {
+        let Some(authorization) = request
+            .headers
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok())
+        else {
+            return false;
+        };
+        let field = |name: &str| {
+            authorization
+                .split(&format!("{}=\"", name))
+                .nth(1)
+                .and_then(|value| value.split('"').next())
+                .unwrap_or_default()
+        };
+        if field("mchid") != MCHID {
+            return false;
+        }
+        let url = match request.url.query() {
+            Some(query) => format!("{}?{}", request.url.path(), query),
+            None => request.url.path().to_string(),
+        };
+        let message = format!(
+            "{}\n{}\n{}\n{}\n{}\n",
+            request.method,
+            url,
+            field("timestamp"),
+            field("nonce_str"),
+            String::from_utf8_lossy(&request.body)
+        );
+        let Ok(signature) = STANDARD.decode(field("signature")) else {
+            return false;
+        };
+        self.0
+            .verify(
+                Pkcs1v15Sign::new::<Sha256>(),
+                &Sha256::digest(message.as_bytes()),
+                &signature,
+            )
+            .is_ok()
+    }
+}
+
+fn wechat_pay(server: &MockServer) -> WechatPay {
+    WechatPay::try_new(
+        APPID,
+        MCHID,
+        PRIVATE_KEY,
+        "serial_no",
+        "",
+        "https://mydomain.com/notify",
+    )
+    .unwrap()
+    .with_base_url(server.uri())
+}
+
+#[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+async fn test_native_pay() {
+    let server = run!(MockServer::start());
+    run!(
+        Mock::given(method("POST"))
+            .and(path("/v3/pay/transactions/native"))
+            .and(body_partial_json(serde_json::json!({
+                "appid": APPID,
+                "mchid": MCHID,
+                "out_trade_no": "1217752501201407033233368018",
+                "notify_url": "https://mydomain.com/notify",
+                "amount": {"total": 100},
+            })))
+            .and(SignedBy::merchant())
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(r#"{"code_url":"weixin://wxpay/bizpayurl?pr=p4lpSuKzz"}"#),
+            )
+            .expect(1)
+            .mount(&server)
+    );
+    let params = NativeParams::new(
+        "Image形象店-深圳腾大-QQ公仔",
+        "1217752501201407033233368018",
+        100.into(),
+    );
+    let response = wechat_pay(&server).native_pay(params).await.unwrap();
+    assert_eq!(
+        response.code_url().unwrap(),
+        "weixin://wxpay/bizpayurl?pr=p4lpSuKzz"
+    );
+    run!(server.verify());
+}
+
+#[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+async fn test_query_order() {
+    let server = run!(MockServer::start());
+    run!(
+        Mock::given(method("GET"))
+            .and(path("/v3/pay/transactions/out-trade-no/1217752501201407033233368018"))
+            .and(query_param("mchid", MCHID))
+            .and(SignedBy::merchant())
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"appid":"wxd678efh567hg6787","mchid":"1230000109","out_trade_no":"1217752501201407033233368018","transaction_id":"1217752501201407033233368018","trade_type":"NATIVE","trade_state":"SUCCESS","trade_state_desc":"支付成功","bank_type":"CMC","success_time":"2018-06-08T10:34:56+08:00","payer":{"openid":"oUpF8uMuAJO_M2pxb1Q9zNjWeS6o"},"amount":{"total":100,"payer_total":100,"currency":"CNY","payer_currency":"CNY"}}"#,
+            ))
+            .expect(1)
+            .mount(&server)
+    );
+    let response = wechat_pay(&server)
+        .query_order_by_out_trade_no("1217752501201407033233368018")
+        .await
+        .unwrap();
+    let order = response.ok().unwrap();
+    assert_eq!(order.trade_state, "SUCCESS");
+    assert_eq!(order.amount.as_ref().unwrap().payer_total, Some(100));
+    run!(server.verify());
+}
+
+#[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+async fn test_refunds() {
+    let server = run!(MockServer::start());
+    run!(
+        Mock::given(method("POST"))
+            .and(path("/v3/refund/domestic/refunds"))
+            .and(body_partial_json(serde_json::json!({
+                "out_refund_no": "1217752501201407033233368018",
+                "amount": {"refund": 888, "total": 888, "currency": "CNY"},
+            })))
+            .and(SignedBy::merchant())
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"refund_id":"50000000382019052709732678859","out_refund_no":"1217752501201407033233368018","transaction_id":"1217752501201407033233368018","out_trade_no":"1217752501201407033233368018","channel":"ORIGINAL","user_received_account":"招商银行信用卡0403","create_time":"2020-12-01T16:18:12+08:00","status":"PROCESSING","funds_account":"AVAILABLE","amount":{"total":888,"refund":888,"payer_total":888,"payer_refund":888,"settlement_refund":888,"settlement_total":888,"discount_refund":0,"currency":"CNY"}}"#,
+            ))
+            .expect(1)
+            .mount(&server)
+    );
+    let params = RefundsParams::new(
+        "1217752501201407033233368018",
+        888,
+        888,
+        None,
+        Some("1217752501201407033233368018"),
+    );
+    let response = wechat_pay(&server).refunds(params).await.unwrap();
+    let refund = match response {
+        WeChatResponse::Ok(refund) => refund,
+        WeChatResponse::Err(error) => panic!("unexpected error: {:?}", error),
+    };
+    assert_eq!(refund.refund_id, "50000000382019052709732678859");
+    // 退款请求体不能带appid
+    let requests = run!(server.received_requests()).unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert!(body.get("appid").is_none());
+    run!(server.verify());
+}
+
+#[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+async fn test_unsigned_match_is_rejected() {
+    let server = run!(MockServer::start());
+    run!(
+        Mock::given(SignedBy::merchant())
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .expect(0)
+            .mount(&server)
+    );
+    // 商户号与签名不符时模拟服务不响应，wiremock返回404
+    let wechat_pay = WechatPay::try_new(APPID, "1900000001", PRIVATE_KEY, "serial_no", "", "")
+        .unwrap()
+        .with_base_url(server.uri());
+    let result = wechat_pay
+        .query_order_by_out_trade_no("1217752501201407033233368018")
+        .await;
+    assert!(matches!(
+        result,
+        Err(PayError::ResponseParseError { status: 404, .. })
+    ));
+    run!(server.verify());
+}
@@ -0,0 +1,16 @@
+//! 校验同步/异步两种模式下公开接口的调用方式，模式不匹配时应编译失败
+
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    #[cfg(feature = "async")]
+    {
+        cases.pass("tests/ui/async/*_pass.rs");
+        cases.compile_fail("tests/ui/async/*_fail.rs");
+    }
+    #[cfg(not(feature = "async"))]
+    {
+        cases.pass("tests/ui/blocking/*_pass.rs");
+        cases.compile_fail("tests/ui/blocking/*_fail.rs");
+    }
+}
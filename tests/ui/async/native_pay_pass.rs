@@ -0,0 +1,17 @@
+use wechat_pay_rust_sdk::error::PayError;
+use wechat_pay_rust_sdk::mode::{Async, Current, Mode};
+use wechat_pay_rust_sdk::model::NativeParams;
+use wechat_pay_rust_sdk::pay::WechatPay;
+use wechat_pay_rust_sdk::response::NativeResponse;
+
+async fn native_pay(wechat_pay: &WechatPay) -> Result<NativeResponse, PayError> {
+    wechat_pay
+        .native_pay(NativeParams::new("测试支付1分", "1243243", 1.into()))
+        .await
+}
+
+fn main() {
+    let _: Async = Async;
+    assert!(Current::IS_ASYNC);
+    let _ = native_pay;
+}